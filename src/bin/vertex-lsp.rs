@@ -0,0 +1,25 @@
+//! A language server for Vertex source files, communicating with a language
+//! client over the standard input and output streams.
+
+
+use std::io;
+use std::process::ExitCode;
+use vertex_lang::lsp::Server;
+use vertex_lang::registry::standard_library;
+
+
+fn main() -> ExitCode {
+    let registry = standard_library();
+    let mut server = Server::new(&registry);
+
+    if let Err(e) = server.run(io::stdin().lock(), io::stdout().lock()) {
+        eprintln!("error: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if server.is_shutdown() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
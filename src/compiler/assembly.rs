@@ -0,0 +1,886 @@
+//! Converts Vertex bytecode to and from a human-readable text form, so that
+//! compiled programs can be inspected and bytecode can be written by hand.
+//!
+//! A disassembly contains, in order:
+//!
+//! - The `constants:` section, listing each constant data value.
+//! - The `structs:` section, listing the name of each struct type.
+//! - The `externs:` section, listing the name and signature of each external
+//!   function.
+//! - One `function` section per internal function, containing it's identifier
+//!   path and parameter count, followed by one line per operation and a final
+//!   line for the output of the function.
+//!
+//! Every entry is numbered by it's index. Within operations, `$n` reads the
+//! function parameter `n` and `%n` reads the result of operation `n`. Comments
+//! start with `;` and continue until the end of the line.
+//!
+//! ```text
+//! constants:
+//!     0 = Int 2
+//!
+//! structs:
+//!
+//! externs:
+//!     0 = "Print": fn(Int) -> Null
+//!
+//! function 0 "Main.Double" (params: 1):
+//!     %0 = constant 0 ()
+//!     %1 = operator Multiply ($0, %0)
+//!     %2 = external 0 (%1)
+//!     output = %1 after %2
+//! ```
+
+
+// Because Pest generates impl for AssemblyParser that doesn't have docs.
+#[allow(missing_docs)]
+mod peg {
+
+    #[derive(Parser)]
+    #[grammar = "grammars/bytecode.pest"]
+    pub struct AssemblyParser;
+}
+
+use super::bytecode::{FunctionCall, InternalFunction, Operation, OperationInput, VertexBytecode};
+use super::ir::IRDataType;
+use super::vxb::{bind_extern, validate, BytecodeFileError, OPERATORS};
+use crate::data::{Data, StructData};
+use crate::registry::FunctionRegistry;
+use peg::{AssemblyParser, Rule};
+use pest::error::{Error, LineColLocation};
+use pest::iterators::{Pair, Pairs};
+use pest::Parser;
+use std::str::FromStr;
+use thiserror::Error;
+
+
+type Result<T> = std::result::Result<T, AssemblyError>;
+
+
+/// An error that occurred while assembling bytecode from it's text form.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AssemblyError {
+    /// The text does not follow the syntax of a disassembly. Contains the
+    /// message, line and column of the error.
+    #[error("Syntax error, {0}, at {1}:{2}")]
+    Syntax(String, usize, usize),
+
+    /// The text contains a value that is not valid at it's location. Contains
+    /// the message, line and column of the error.
+    #[error("{0}, at {1}:{2}")]
+    InvalidValue(String, usize, usize),
+
+    /// The assembled bytecode cannot be loaded, such as when an external
+    /// function is not registered.
+    #[error(transparent)]
+    Bytecode(#[from] BytecodeFileError),
+}
+
+
+/// Converts the given bytecode into it's human-readable text form.
+///
+/// Constant function values and unresolved data types have no text form that
+/// can be assembled again, and are only written for inspection.
+pub fn disassemble(bytecode: &VertexBytecode) -> String {
+    let mut lines = vec![String::from("constants:")];
+    for (index, constant) in bytecode.get_constants().iter().enumerate() {
+        lines.push(format!("    {} = {}", index, format_data(constant)));
+    }
+
+    lines.push(String::new());
+    lines.push(String::from("structs:"));
+    for (index, struct_type) in bytecode.get_struct_types().iter().enumerate() {
+        lines.push(format!("    {} = {}", index, quote(struct_type, '"')));
+    }
+
+    lines.push(String::new());
+    lines.push(String::from("externs:"));
+    for (index, function) in bytecode.get_external_functions().iter().enumerate() {
+        let signature = IRDataType::Function(
+            function.get_inputs().clone(),
+            Box::new(function.get_output().clone()),
+        );
+        lines.push(format!(
+            "    {} = {}: {}",
+            index,
+            quote(function.get_function_name(), '"'),
+            format_type(&signature)
+        ));
+    }
+
+    for (index, function) in bytecode.get_internal_functions().iter().enumerate() {
+        lines.push(String::new());
+        lines.push(format!(
+            "function {} {} (params: {}):",
+            index,
+            quote(&function.get_path().join("."), '"'),
+            function.get_param_count()
+        ));
+
+        for (index, operation) in function.get_operations().iter().enumerate() {
+            let inputs = operation.get_inputs().iter().map(format_input).collect::<Vec<_>>();
+            lines.push(format!(
+                "    %{} = {} ({}){}",
+                index,
+                format_call(operation.get_function()),
+                inputs.join(", "),
+                format_after(operation.get_after())
+            ));
+        }
+
+        let output = function.get_output().map_or(String::from("none"), format_input);
+        lines.push(format!("    output = {}{}", output, format_after(function.get_after())));
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+
+/// Converts the given text form back into bytecode, binding each external
+/// function to the function with the same name within the given function
+/// registry.
+///
+/// The entries of each section must be numbered in order, starting from 0. As
+/// with bytecode files, every index must point to an existing element, every
+/// operation may only read the operations before it and must be given the
+/// number of inputs its function call expects, and the signature of each
+/// external function must match the registry.
+pub fn assemble(text: &str, registry: &FunctionRegistry) -> Result<VertexBytecode> {
+    let program = match AssemblyParser::parse(Rule::Program, text) {
+        Ok(mut pairs) => pairs.next().unwrap(),
+        Err(e) => return Err(syntax_error(e)),
+    };
+
+    let mut bytecode = VertexBytecode::new();
+
+    for section in program.into_inner() {
+        match section.as_rule() {
+            Rule::Constants => {
+                for (index, entry) in section.into_inner().enumerate() {
+                    let mut entry = entry.into_inner();
+                    expect_index(entry.next().unwrap(), index)?;
+                    bytecode.add_constant(parse_data(entry.next().unwrap())?);
+                }
+            },
+            Rule::Structs => {
+                for (index, entry) in section.into_inner().enumerate() {
+                    let mut entry = entry.into_inner();
+                    expect_index(entry.next().unwrap(), index)?;
+                    bytecode.add_struct_type(parse_string(entry.next().unwrap())?);
+                }
+            },
+            Rule::Externs => {
+                for (index, entry) in section.into_inner().enumerate() {
+                    let mut entry = entry.into_inner();
+                    expect_index(entry.next().unwrap(), index)?;
+                    let name = parse_string(entry.next().unwrap())?;
+
+                    let signature = entry.next().unwrap();
+                    let (inputs, output) = match parse_type(signature.clone())? {
+                        IRDataType::Function(inputs, output) => (inputs, *output),
+                        _ => {
+                            let message = String::from("expected a function signature");
+                            return Err(invalid(&signature, message));
+                        },
+                    };
+
+                    bytecode.add_external_function(bind_extern(registry, name, inputs, output)?);
+                }
+            },
+            Rule::Function => {
+                let index = bytecode.get_internal_functions().len();
+                bytecode.add_internal_function(parse_function(section, index)?);
+            },
+            Rule::EOI => {},
+            _ => panic!("Unexpected token: {}", section),
+        }
+    }
+
+    validate(&bytecode)?;
+    Ok(bytecode)
+}
+
+
+fn syntax_error(error: Error<Rule>) -> AssemblyError {
+    let (line, col) = match error.line_col {
+        LineColLocation::Pos(position) => position,
+        LineColLocation::Span(start, _) => start,
+    };
+
+    AssemblyError::Syntax(error.variant.message().into_owned(), line, col)
+}
+
+
+fn invalid(pair: &Pair<Rule>, message: String) -> AssemblyError {
+    let (line, col) = pair.as_span().start_pos().line_col();
+    AssemblyError::InvalidValue(message, line, col)
+}
+
+
+/// Surrounds the given text with the given quote character, escaping any
+/// characters that cannot appear within the quotes as is.
+fn quote(text: &str, quote: char) -> String {
+    let mut quoted = String::from(quote);
+
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c == quote => {
+                quoted.push('\\');
+                quoted.push(c);
+            },
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push(quote);
+    quoted
+}
+
+
+fn format_list<T>(values: &[T], format: impl Fn(&T) -> String) -> String {
+    values.iter().map(format).collect::<Vec<_>>().join(", ")
+}
+
+
+pub(super) fn format_data(data: &Data) -> String {
+    match data {
+        Data::Null => String::from("Null"),
+        Data::Int(value) => format!("Int {}", value),
+        Data::Float(value) => format!("Float {:?}", value),
+        Data::String(value) => format!("String {}", quote(value, '"')),
+        Data::Char(value) => format!("Char {}", quote(&value.to_string(), '\'')),
+        Data::Bool(value) => format!("Bool {}", value),
+        Data::Error(message) => format!("Error {}", quote(message, '"')),
+        Data::Struct(value) => {
+            format!(
+                "Struct {} {{{}}}",
+                quote(&value.struct_type, '"'),
+                format_list(&value.fields, format_data)
+            )
+        },
+        Data::List(values) => format!("List [{}]", format_list(values, format_data)),
+        Data::Array(values) => format!("Array [{}]", format_list(values, format_data)),
+        Data::Option(value) => format!("Option ({})", format_data(value)),
+        Data::Result(value) => format!("Result ({})", format_data(value)),
+        Data::Tuple(values) => format!("Tuple ({})", format_list(values, format_data)),
+        Data::Dictionary(keys, values) => {
+            let entries = keys
+                .iter()
+                .zip(values)
+                .map(|(key, value)| format!("{}: {}", format_data(key), format_data(value)))
+                .collect::<Vec<_>>();
+            format!("Dictionary {{{}}}", entries.join(", "))
+        },
+        Data::Function(_) => String::from("Function"),
+    }
+}
+
+
+/// Formats the given data type the same way as it's display form, except that
+/// struct types include their fields.
+fn format_type(dtype: &IRDataType) -> String {
+    match dtype {
+        IRDataType::List(element) => format!("{}[]", format_type(element)),
+        IRDataType::Array(element, len) => format!("{}[{}]", format_type(element), len),
+        IRDataType::Option(element) => format!("{}?", format_type(element)),
+        IRDataType::Result(element) => format!("{}!", format_type(element)),
+        IRDataType::Tuple(elements) => format!("({})", format_list(elements, format_type)),
+        IRDataType::Dictionary(key, value) => {
+            format!("{{{}: {}}}", format_type(key), format_type(value))
+        },
+        IRDataType::Struct(name, fields) => {
+            let fields = format_list(fields, |(field, field_type)| {
+                format!("{}: {}", field, format_type(field_type))
+            });
+            format!("struct {} {{{}}}", quote(name, '"'), fields)
+        },
+        IRDataType::Function(params, output) => {
+            format!("fn({}) -> {}", format_list(params, format_type), format_type(output))
+        },
+        _ => dtype.to_string(),
+    }
+}
+
+
+pub(super) fn format_call(call: &FunctionCall) -> String {
+    match call {
+        FunctionCall::Internal(index) => format!("internal {}", index),
+        FunctionCall::External(index) => format!("external {}", index),
+        FunctionCall::Constant(index) => format!("constant {}", index),
+        FunctionCall::Field(index) => format!("field {}", index),
+        FunctionCall::Element(index) => format!("element {}", index),
+        FunctionCall::Operator(operator) => format!("operator {:?}", operator),
+        FunctionCall::Select => String::from("select"),
+        FunctionCall::Map(target) => format!("map({})", format_call(target)),
+        FunctionCall::Filter(target) => format!("filter({})", format_call(target)),
+        FunctionCall::Reduce(target) => format!("reduce({})", format_call(target)),
+        FunctionCall::Fold(target) => format!("fold({})", format_call(target)),
+        FunctionCall::Closure(target) => format!("closure({})", format_call(target)),
+        FunctionCall::Invoke => String::from("invoke"),
+        FunctionCall::BuildList => String::from("list"),
+        FunctionCall::BuildTuple => String::from("tuple"),
+        FunctionCall::BuildDictionary => String::from("dictionary"),
+        FunctionCall::Struct(index) => format!("struct {}", index),
+    }
+}
+
+
+fn format_input(input: &OperationInput) -> String {
+    match input {
+        OperationInput::Param(index) => format!("${}", index),
+        OperationInput::Hidden(index) => format!("%{}", index),
+    }
+}
+
+
+fn format_after(after: Option<usize>) -> String {
+    match after {
+        Some(index) => format!(" after %{}", index),
+        None => String::new(),
+    }
+}
+
+
+fn parse_number<T: FromStr>(pair: Pair<Rule>) -> Result<T> {
+    match pair.as_str().parse() {
+        Ok(value) => Ok(value),
+        Err(_) => Err(invalid(&pair, format!("invalid number '{}'", pair.as_str()))),
+    }
+}
+
+
+/// Checks that the given index matches the position of the entry it numbers.
+fn expect_index(pair: Pair<Rule>, expected: usize) -> Result<()> {
+    let index: usize = parse_number(pair.clone())?;
+    if index != expected {
+        let message = format!("expected index {}, but found {}", expected, index);
+        return Err(invalid(&pair, message));
+    }
+
+    Ok(())
+}
+
+
+/// Reads the contents of a quoted string or character, replacing each escape
+/// sequence with the character it represents.
+fn unescape(pair: Pair<Rule>) -> Result<String> {
+    let interior = pair.into_inner().next().unwrap();
+    let mut text = String::new();
+    let mut chars = interior.as_str().chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+
+        match chars.next().unwrap() {
+            'n' => text.push('\n'),
+            'r' => text.push('\r'),
+            't' => text.push('\t'),
+            'u' => {
+                let code = chars.by_ref().skip(1).take_while(|c| *c != '}').collect::<String>();
+                match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    Some(value) => text.push(value),
+                    None => {
+                        let message = format!("invalid character code '{}'", code);
+                        return Err(invalid(&interior, message));
+                    },
+                }
+            },
+            escaped => text.push(escaped),
+        }
+    }
+
+    Ok(text)
+}
+
+
+fn parse_string(pair: Pair<Rule>) -> Result<String> {
+    unescape(pair)
+}
+
+
+fn parse_data_list(pairs: Pairs<Rule>) -> Result<Vec<Data>> {
+    pairs.map(parse_data).collect()
+}
+
+
+fn parse_data(pair: Pair<Rule>) -> Result<Data> {
+    let pair = pair.into_inner().next().unwrap();
+    let rule = pair.as_rule();
+    let mut inner = pair.into_inner();
+
+    Ok(match rule {
+        Rule::NullData => Data::Null,
+        Rule::IntData => Data::Int(parse_number(inner.next().unwrap())?),
+        Rule::FloatData => Data::Float(parse_number(inner.next().unwrap())?),
+        Rule::StringData => Data::String(parse_string(inner.next().unwrap())?),
+        Rule::CharData => Data::Char(unescape(inner.next().unwrap())?.chars().next().unwrap()),
+        Rule::BoolData => Data::Bool(inner.next().unwrap().as_str() == "true"),
+        Rule::ErrorData => Data::Error(parse_string(inner.next().unwrap())?),
+        Rule::StructData => {
+            Data::Struct(StructData {
+                struct_type: parse_string(inner.next().unwrap())?,
+                fields:      parse_data_list(inner)?,
+            })
+        },
+        Rule::ListData => Data::List(parse_data_list(inner)?),
+        Rule::ArrayData => Data::Array(parse_data_list(inner)?),
+        Rule::OptionData => Data::Option(Box::new(parse_data(inner.next().unwrap())?)),
+        Rule::ResultData => Data::Result(Box::new(parse_data(inner.next().unwrap())?)),
+        Rule::TupleData => Data::Tuple(parse_data_list(inner)?),
+        Rule::DictionaryData => {
+            let mut keys = vec![];
+            let mut values = vec![];
+            for entry in inner {
+                let mut entry = entry.into_inner();
+                keys.push(parse_data(entry.next().unwrap())?);
+                values.push(parse_data(entry.next().unwrap())?);
+            }
+            Data::Dictionary(keys, values)
+        },
+        _ => panic!("Unexpected token: {:?}", rule),
+    })
+}
+
+
+fn parse_type(pair: Pair<Rule>) -> Result<IRDataType> {
+    let mut inner = pair.into_inner();
+    let base = inner.next().unwrap();
+
+    let mut dtype = match base.as_rule() {
+        Rule::PrimitiveType => IRDataType::from(base.as_str()),
+        Rule::TupleType => {
+            IRDataType::Tuple(base.into_inner().map(parse_type).collect::<Result<_>>()?)
+        },
+        Rule::DictionaryType => {
+            let mut types = base.into_inner();
+            let key = parse_type(types.next().unwrap())?;
+            let value = parse_type(types.next().unwrap())?;
+            IRDataType::Dictionary(Box::new(key), Box::new(value))
+        },
+        Rule::StructType => {
+            let mut inner = base.into_inner();
+            let name = parse_string(inner.next().unwrap())?;
+            let fields = inner
+                .map(|field| {
+                    let mut field = field.into_inner();
+                    let name = field.next().unwrap().as_str().to_owned();
+                    Ok((name, parse_type(field.next().unwrap())?))
+                })
+                .collect::<Result<_>>()?;
+            IRDataType::Struct(name, fields)
+        },
+        Rule::FunctionType => {
+            let mut types = base.into_inner().map(parse_type).collect::<Result<Vec<_>>>()?;
+            let output = types.pop().unwrap();
+            IRDataType::Function(types, Box::new(output))
+        },
+        _ => panic!("Unexpected token: {}", base),
+    };
+
+    for suffix in inner {
+        dtype = match suffix.as_rule() {
+            Rule::ListSuffix => IRDataType::List(Box::new(dtype)),
+            Rule::ArraySuffix => {
+                let len = parse_number(suffix.into_inner().next().unwrap())?;
+                IRDataType::Array(Box::new(dtype), len)
+            },
+            Rule::OptionSuffix => IRDataType::Option(Box::new(dtype)),
+            Rule::ResultSuffix => IRDataType::Result(Box::new(dtype)),
+            _ => panic!("Unexpected token: {}", suffix),
+        };
+    }
+
+    Ok(dtype)
+}
+
+
+fn parse_call(pair: Pair<Rule>) -> Result<FunctionCall> {
+    let pair = pair.into_inner().next().unwrap();
+    let rule = pair.as_rule();
+    let mut inner = pair.into_inner();
+
+    Ok(match rule {
+        Rule::InternalCall => FunctionCall::Internal(parse_number(inner.next().unwrap())?),
+        Rule::ExternalCall => FunctionCall::External(parse_number(inner.next().unwrap())?),
+        Rule::ConstantCall => FunctionCall::Constant(parse_number(inner.next().unwrap())?),
+        Rule::FieldCall => FunctionCall::Field(parse_number(inner.next().unwrap())?),
+        Rule::ElementCall => FunctionCall::Element(parse_number(inner.next().unwrap())?),
+        Rule::OperatorCall => {
+            let name = inner.next().unwrap();
+            match OPERATORS.iter().find(|o| format!("{:?}", o) == name.as_str()) {
+                Some(operator) => FunctionCall::Operator(*operator),
+                None => {
+                    let message = format!("unknown operator '{}'", name.as_str());
+                    return Err(invalid(&name, message));
+                },
+            }
+        },
+        Rule::SelectCall => FunctionCall::Select,
+        Rule::MapCall => FunctionCall::Map(Box::new(parse_call(inner.next().unwrap())?)),
+        Rule::FilterCall => FunctionCall::Filter(Box::new(parse_call(inner.next().unwrap())?)),
+        Rule::ReduceCall => FunctionCall::Reduce(Box::new(parse_call(inner.next().unwrap())?)),
+        Rule::FoldCall => FunctionCall::Fold(Box::new(parse_call(inner.next().unwrap())?)),
+        Rule::ClosureCall => FunctionCall::Closure(Box::new(parse_call(inner.next().unwrap())?)),
+        Rule::InvokeCall => FunctionCall::Invoke,
+        Rule::ListCall => FunctionCall::BuildList,
+        Rule::TupleCall => FunctionCall::BuildTuple,
+        Rule::DictionaryCall => FunctionCall::BuildDictionary,
+        Rule::StructCall => FunctionCall::Struct(parse_number(inner.next().unwrap())?),
+        _ => panic!("Unexpected token: {:?}", rule),
+    })
+}
+
+
+fn parse_input(pair: Pair<Rule>) -> Result<OperationInput> {
+    let input = pair.into_inner().next().unwrap();
+    let rule = input.as_rule();
+    let index = parse_number(input.into_inner().next().unwrap())?;
+
+    Ok(match rule {
+        Rule::Param => OperationInput::Param(index),
+        _ => OperationInput::Hidden(index),
+    })
+}
+
+
+fn parse_after(pair: Pair<Rule>) -> Result<usize> {
+    let hidden = pair.into_inner().next().unwrap();
+    parse_number(hidden.into_inner().next().unwrap())
+}
+
+
+fn parse_function(pair: Pair<Rule>, index: usize) -> Result<InternalFunction> {
+    let mut inner = pair.into_inner();
+    expect_index(inner.next().unwrap(), index)?;
+
+    let mut function = InternalFunction::new();
+    let path = parse_string(inner.next().unwrap())?;
+    if !path.is_empty() {
+        function.set_path(path.split('.').map(String::from).collect());
+    }
+    function.set_param_count(parse_number(inner.next().unwrap())?);
+
+    for pair in inner {
+        let rule = pair.as_rule();
+        let mut inner = pair.into_inner();
+
+        match rule {
+            Rule::Operation => {
+                let hidden = inner.next().unwrap();
+                expect_index(hidden.into_inner().next().unwrap(), function.get_operations().len())?;
+
+                let function_call = parse_call(inner.next().unwrap())?;
+                let inputs = inner.next().unwrap().into_inner().map(parse_input);
+                let mut operation = Operation::new(function_call, inputs.collect::<Result<_>>()?);
+                operation.set_after(inner.next().map(parse_after).transpose()?);
+                function.add_operation(operation);
+            },
+            Rule::Output => {
+                let output = inner.next().unwrap();
+                match output.as_rule() {
+                    Rule::Input => function.set_output(Some(parse_input(output)?)),
+                    _ => function.set_output(None),
+                }
+                function.set_after(inner.next().map(parse_after).transpose()?);
+            },
+            _ => panic!("Unexpected token: {:?}", rule),
+        }
+    }
+
+    Ok(function)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::bytecode::bytecode_from_ir;
+    use crate::compiler::grammar::parse;
+    use crate::compiler::ir::compile_context;
+    use crate::compiler::resolver::resolve_context;
+    use crate::compiler::typecheck::check_types;
+    use crate::compiler::vxb::write_bytecode;
+    use crate::multithreading::jobs::{JobScheduler, Scheduler};
+    use crate::registry::FuncMeta;
+    use crate::{multithreading, unwrap_data, vm};
+    use indoc::indoc;
+    use ntest::timeout;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+
+    fn add(inputs: Vec<Arc<Data>>) -> Data {
+        let a = unwrap_data!(inputs[0], Int);
+        let b = unwrap_data!(inputs[1], Int);
+        Data::Int(a + b)
+    }
+
+
+    fn registry() -> FunctionRegistry {
+        let inputs = vec![IRDataType::Int, IRDataType::Int];
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register(FuncMeta::new(String::from("Add"), add, inputs, IRDataType::Int).unwrap())
+            .unwrap();
+        registry
+    }
+
+
+    fn execute(bytecode: &VertexBytecode) -> Arc<Data> {
+        let scheduler = JobScheduler::new().into_async();
+        multithreading::build_workers(&scheduler, 2);
+        let output = vm::evaluate(&scheduler, vm::link(bytecode)[0].clone()).complete();
+        scheduler.terminate_workers();
+        output
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn round_trip() {
+        let registry = registry();
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Point = struct {
+                    x: Int
+                    y: Int
+                }
+
+                Main = function {
+                    params = ()
+                    return = (sum: Int)
+
+                    point = Point { x = 3, y = 4 }
+                    values = map([point.x, point.y], function {
+                        params = (v: Int)
+                        return = (value: Int)
+
+                        value = v * 10
+                    })
+                    sum = fold(values, 0, extern Add)
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+
+        let text = disassemble(&bytecode);
+        assert_eq!(text, indoc! {r#"
+            constants:
+                0 = Int 3
+                1 = Int 4
+                2 = Int 0
+                3 = Int 10
+
+            structs:
+                0 = "Main.Point"
+
+            externs:
+                0 = "Add": fn(Int, Int) -> Int
+
+            function 0 "Main.Main" (params: 0):
+                %0 = constant 0 ()
+                %1 = constant 1 ()
+                %2 = struct 0 (%0, %1)
+                %3 = field 0 (%2)
+                %4 = field 1 (%2)
+                %5 = list (%3, %4)
+                %6 = closure(internal 1) ()
+                %7 = map(invoke) (%5, %6)
+                %8 = constant 2 ()
+                %9 = closure(external 0) ()
+                %10 = fold(invoke) (%7, %8, %9)
+                output = %10
+
+            function 1 "Main.Main.lambda@12:42" (params: 1):
+                %0 = constant 3 ()
+                %1 = operator Multiply ($0, %0)
+                output = %1
+        "#});
+
+        let assembled = assemble(&text, &registry).unwrap();
+        assert_eq!(disassemble(&assembled), text);
+        assert_eq!(write_bytecode(&assembled).unwrap(), write_bytecode(&bytecode).unwrap());
+        assert_eq!(*execute(&assembled), Data::Int(70));
+    }
+
+
+    #[test]
+    fn round_trip_constants() {
+        let text = indoc! {r#"
+            constants:
+                0 = Null
+                1 = Int -9223372036854775808
+                2 = Float -0.0
+                3 = Float 1e-7
+                4 = Float inf
+                5 = String "say \"hi\"\n\u{7}"
+                6 = Char '\''
+                7 = Bool true
+                8 = Error "failed"
+                9 = Struct "Main.Point" {Float 1.5, Float 2.0}
+                10 = List [Int 1, List []]
+                11 = Array [Char 'a', Char '\\']
+                12 = Option (Result (Null))
+                13 = Tuple (Int 1, String "")
+                14 = Dictionary {String "a": Int 1, String "b": Int 2}
+
+            structs:
+
+            externs:
+        "#};
+
+        let bytecode = assemble(text, &FunctionRegistry::new()).unwrap();
+        assert_eq!(bytecode.get_constants()[5], Data::String(String::from("say \"hi\"\n\x07")));
+        assert_eq!(bytecode.get_constants()[6], Data::Char('\''));
+        assert_eq!(disassemble(&bytecode), text);
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn assemble_hand_written() {
+        let bytecode = assemble(
+            indoc! {r#"
+                ; Adds 5 to the doubled sum of 1, 2 and 3.
+                constants:
+                    0 = List [Int 1, Int 2, Int 3]
+                    1 = Int 5
+
+                structs:
+
+                externs:
+                    0 = "Add": fn(Int, Int) -> Int
+
+                function 0 "Main.Main" (params: 0):
+                    %0 = constant 0 ()
+                    %1 = reduce(external 0) (%0)  ; The sum of the list.
+                    %2 = constant 1 ()
+                    %3 = internal 1 (%1, %2) after %1
+                    output = %3 after %3
+
+                function 1 "Main.Add" (params: 2):
+                    %0 = operator Add ($0, $0)
+                    %1 = operator Add (%0, $1)
+                    output = %1
+            "#},
+            &registry(),
+        )
+        .unwrap();
+
+        let functions = bytecode.get_internal_functions();
+        assert_eq!(functions[0].get_operations()[3].get_after(), Some(1));
+        assert_eq!(functions[0].get_after(), Some(3));
+        assert_eq!(functions[1].get_path(), &vec!["Main", "Add"]);
+        assert_eq!(*execute(&bytecode), Data::Int(17));
+    }
+
+
+    #[test]
+    fn reject_invalid_assembly() {
+        let registry = registry();
+        let error = |text: &str| assemble(text, &registry).err().unwrap().to_string();
+        let header = "constants:\nstructs:\nexterns:\n";
+
+        assert_eq!(
+            error("constants:\n    0 = Int\n"),
+            "Syntax error, expected IntValue, at 2:12"
+        );
+        assert_eq!(
+            error("constants:\n    1 = Int 5\nstructs:\nexterns:\n"),
+            "expected index 0, but found 1, at 2:5"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}function 0 \"\" (params: 2):\n    %0 = operator Plus ($0, $1)\n    output = %0",
+                header
+            )),
+            "unknown operator 'Plus', at 5:19"
+        );
+        assert_eq!(
+            error("constants:\nstructs:\nexterns:\n    0 = \"Sub\": fn(Int) -> Int\n"),
+            "External function 'Sub' is not registered"
+        );
+        assert_eq!(
+            error(&format!("{}function 0 \"\" (params: 0):\n    output = $0\n", header)),
+            "Invalid bytecode: function 0 reads missing parameter 0"
+        );
+    }
+
+
+    #[test]
+    fn reject_unsafe_assembly() {
+        let registry = registry();
+        let error = |text: &str| assemble(text, &registry).err().unwrap().to_string();
+
+        assert_eq!(
+            error(indoc! {r#"
+                constants:
+                structs:
+                externs:
+                function 0 "Main.Main" (params: 0):
+                    %0 = list (%1)
+                    %1 = list ()
+                    output = %0
+            "#}),
+            "Invalid bytecode: function 0 operation 0 reads operation 1, which is not before it"
+        );
+        assert_eq!(
+            error(indoc! {r#"
+                constants:
+                structs:
+                externs:
+                function 0 "Main.Main" (params: 0):
+                    %0 = list () after %0
+                    output = %0
+            "#}),
+            "Invalid bytecode: function 0 operation 0 waits for operation 0, which is not \
+             before it"
+        );
+        assert_eq!(
+            error(indoc! {r#"
+                constants:
+                structs:
+                externs:
+                function 0 "Main.Main" (params: 0):
+                    %0 = list ()
+                    %1 = internal 1 (%0)
+                    output = %1
+
+                function 1 "Main.Add" (params: 2):
+                    %0 = operator Add ($0, $1)
+                    output = %0
+            "#}),
+            "Invalid bytecode: function 0 operation 1 passes 1 input(s) to internal 1, which \
+             expects 2"
+        );
+        assert_eq!(
+            error(indoc! {r#"
+                constants:
+                    0 = Int 1
+                structs:
+                externs:
+                    0 = "Add": fn(Int, Int) -> Int
+                function 0 "Main.Main" (params: 0):
+                    %0 = constant 0 ()
+                    %1 = external 0 (%0, %0, %0)
+                    output = %1
+            "#}),
+            "Invalid bytecode: function 0 operation 1 passes 3 input(s) to external 0, which \
+             expects 2"
+        );
+    }
+}
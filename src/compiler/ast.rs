@@ -2,14 +2,27 @@
 //! Vertex.
 
 
-/// Contains the line number and column number of a node within the source code.
+use std::fmt;
+
+/// Contains the source file and the range of source code that a node was
+/// parsed from. Lines and columns both start at 1.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NodePosition {
+    /// The id of the source file this node is defined in.
+    pub file: usize,
+
     /// The line number this node is defined on.
     pub line: usize,
 
     /// The column number this node is defined on.
     pub col: usize,
+
+    /// The line number that this node ends on.
+    pub end_line: usize,
+
+    /// The column number just after the last character of this node. Trailing
+    /// whitespace and line breaks are not included within the node.
+    pub end_col: usize,
 }
 
 
@@ -17,11 +30,26 @@ pub struct NodePosition {
 /// program execution instance.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ContextNode {
+    /// A list of other source files that are imported by this context node.
+    pub imports: Vec<ImportNode>,
+
     /// A list of modules that are contained within this context node.
     pub modules: Vec<ModuleNode>,
 }
 
 
+/// Imports the modules of another source file into the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The path of the imported source file, relative to the directory of the
+    /// file containing this import.
+    pub path: String,
+}
+
+
 /// A module acts like a namespace within Vertex, containing functions,
 /// structs, and other modules.
 #[derive(Debug, Clone, PartialEq)]
@@ -150,6 +178,18 @@ pub struct InnerVariableNode {
 }
 
 
+/// An expression that refers to an external function by name, without calling
+/// it, in order to use that function as a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalFunctionNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The name of the external function within the function registry.
+    pub function_name: String,
+}
+
+
 /// A function call expression. This is an expression that takes in a set of
 /// expression arguments and sends them to another function to be processed.
 #[derive(Debug, Clone, PartialEq)]
@@ -158,6 +198,10 @@ pub struct FunctionCallNode {
     pub position: NodePosition,
 
     /// The name of the function being called.
+    ///
+    /// Internal function names may be qualified with the names of the modules
+    /// or functions they are nested within, separated by periods. For example,
+    /// `Vector.Length`.
     pub function_name: String,
 
     /// Whether or not this function call is executed in serial.
@@ -173,6 +217,37 @@ pub struct FunctionCallNode {
 }
 
 
+/// A struct construction expression. This is an expression that builds a new
+/// struct value from a set of named field expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructInitNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The name of the struct being constructed. This may be qualified with the
+    /// names of the modules or functions the struct is nested within.
+    pub struct_name: String,
+
+    /// The list of fields being assigned, in the order they were written.
+    pub fields: Vec<FieldInitNode>,
+}
+
+
+/// Assigns the value of a single field within a struct construction
+/// expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInitNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The name of the field being assigned.
+    pub name: String,
+
+    /// The expression being assigned to the field.
+    pub expression: ExpressionNode,
+}
+
+
 /// A list of expressions, usually separated by commas.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExpressionListNode {
@@ -185,14 +260,18 @@ pub struct ExpressionListNode {
 
 
 /// Represents a statement within a function that evaluates an expression
-/// and stores the result of that expression into a variable.
+/// and stores the result of that expression into zero or more variables.
 #[derive(Debug, Clone, PartialEq)]
 pub struct AssignmentNode {
     /// The position of this node within the source code.
     pub position: NodePosition,
 
-    /// The variable being written to.
-    pub variable: Option<VariableNode>,
+    /// The variables being written to.
+    ///
+    /// If more than one variable is given, then the expression must evaluate to
+    /// a tuple, and each variable is assigned the tuple element at the same
+    /// index.
+    pub variables: Vec<VariableNode>,
 
     /// The expression being evaluated.
     pub expression: ExpressionNode,
@@ -243,6 +322,208 @@ pub struct BoolLiteralNode {
 }
 
 
+/// A list literal expression, containing a set of element expressions that
+/// must all be of the same data type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListLiteralNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The elements within this list, in order.
+    pub elements: Vec<ExpressionNode>,
+}
+
+
+/// A tuple literal expression, containing two or more element expressions of
+/// any data type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TupleLiteralNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The elements within this tuple, in order.
+    pub elements: Vec<ExpressionNode>,
+}
+
+
+/// A dictionary literal expression, containing a set of key-value pairs. All
+/// keys must be of the same data type, and all values must be of the same data
+/// type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DictionaryLiteralNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The key and value expressions within this dictionary, in order.
+    pub entries: Vec<(ExpressionNode, ExpressionNode)>,
+}
+
+
+/// A built-in operator that can be applied to one or two operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// Adds two numbers, or concatenates two strings.
+    Add,
+
+    /// Subtracts the second number from the first.
+    Subtract,
+
+    /// Multiplies two numbers.
+    Multiply,
+
+    /// Divides the first number by the second.
+    Divide,
+
+    /// Gets the remainder of dividing the first number by the second.
+    Remainder,
+
+    /// Checks if two values are equal.
+    Equal,
+
+    /// Checks if two values are not equal.
+    NotEqual,
+
+    /// Checks if the first value is less than the second.
+    Less,
+
+    /// Checks if the first value is less than or equal to the second.
+    LessEqual,
+
+    /// Checks if the first value is greater than the second.
+    Greater,
+
+    /// Checks if the first value is greater than or equal to the second.
+    GreaterEqual,
+
+    /// Checks if both booleans are true.
+    And,
+
+    /// Checks if either boolean is true.
+    Or,
+
+    /// Inverts a boolean.
+    Not,
+
+    /// Negates a number.
+    Negate,
+}
+
+impl Operator {
+    /// Gets the operator with the given source code symbol. Since `-` is both a
+    /// binary and a unary operator, it is always treated as a subtraction.
+    ///
+    /// If the symbol is not a known operator, then None is returned.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Some(match symbol {
+            "+" => Operator::Add,
+            "-" => Operator::Subtract,
+            "*" => Operator::Multiply,
+            "/" => Operator::Divide,
+            "%" => Operator::Remainder,
+            "==" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            "<" => Operator::Less,
+            "<=" => Operator::LessEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterEqual,
+            "and" => Operator::And,
+            "or" => Operator::Or,
+            "not" => Operator::Not,
+            _ => return None,
+        })
+    }
+
+
+    /// Gets the source code symbol of this operator.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Remainder => "%",
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+            Operator::And => "and",
+            Operator::Or => "or",
+            Operator::Not => "not",
+            Operator::Negate => "-",
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+
+/// An expression that applies a binary operator to two operands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryOperationNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The operator being applied.
+    pub operator: Operator,
+
+    /// The left-hand operand.
+    pub lhs: Box<ExpressionNode>,
+
+    /// The right-hand operand.
+    pub rhs: Box<ExpressionNode>,
+}
+
+
+/// An expression that applies a unary operator to a single operand.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnaryOperationNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The operator being applied.
+    pub operator: Operator,
+
+    /// The operand.
+    pub operand: Box<ExpressionNode>,
+}
+
+
+/// A conditional expression that evaluates to one of two branch expressions,
+/// based off a boolean condition. Only the branch that is taken is evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The boolean condition that decides which branch is taken.
+    pub condition: Box<ExpressionNode>,
+
+    /// The expression that is evaluated if the condition is true.
+    pub then_branch: Box<ExpressionNode>,
+
+    /// The expression that is evaluated if the condition is false.
+    pub else_branch: Box<ExpressionNode>,
+}
+
+
+/// A range of source code that could not be parsed. These nodes are only
+/// produced by the recovering parser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorNode {
+    /// The position of this node within the source code.
+    pub position: NodePosition,
+
+    /// The source code that could not be parsed.
+    pub source: String,
+}
+
+
 /// Contains an expression that can be resolved.
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExpressionNode {
@@ -258,6 +539,15 @@ pub enum ExpressionNode {
     /// A boolean literal value expression.
     BoolLiteral(BoolLiteralNode),
 
+    /// A list literal expression.
+    ListLiteral(ListLiteralNode),
+
+    /// A tuple literal expression.
+    TupleLiteral(TupleLiteralNode),
+
+    /// A dictionary literal expression.
+    DictionaryLiteral(DictionaryLiteralNode),
+
     /// A function call expression.
     FunctionCall(FunctionCallNode),
 
@@ -266,4 +556,27 @@ pub enum ExpressionNode {
 
     /// A nested variable value expression.
     InnerVariable(InnerVariableNode),
+
+    /// A struct construction expression.
+    StructInit(StructInitNode),
+
+    /// A binary operator expression.
+    BinaryOperation(BinaryOperationNode),
+
+    /// A unary operator expression.
+    UnaryOperation(UnaryOperationNode),
+
+    /// A conditional expression.
+    Conditional(ConditionalNode),
+
+    /// An anonymous function expression. The function is given a generated
+    /// name based on it's position within the source code, and may read the
+    /// parameters and variables of the function it is defined within.
+    Lambda(FunctionNode),
+
+    /// A reference to an external function, used as a value.
+    ExternalFunction(ExternalFunctionNode),
+
+    /// An expression that contains a syntax error.
+    Error(ErrorNode),
 }
@@ -2,23 +2,35 @@
 //! to loading or executing the program are discarded and no further
 //! optimizations or debug data are maintained.
 
-use super::ir::{IRContext, IRFuncCall, IRNodeInput};
+use super::ast::Operator;
+use super::ir::{IRContext, IRDataType, IRFuncCall, IRNodeInput};
 use crate::data::{Data, VertexFunction};
 use crate::registry::FunctionRegistry;
 
 
 /// A pointer to an external function that can be called from within Vertex.
+///
+/// The signature of the function is kept alongside the function pointer, so
+/// that the function can be bound again by name when the bytecode is loaded
+/// from a file.
 pub struct ExternalFunction {
     name:     String,
     function: VertexFunction,
+    inputs:   Vec<IRDataType>,
+    output:   IRDataType,
 }
 
 impl ExternalFunction {
-    /// Creates a new external function instance for the given function.
-    pub fn new(name: String, function: VertexFunction) -> Self {
+    /// Creates a new external function instance for the given function, with
+    /// the signature it was registered with.
+    pub fn new(
+        name: String, function: VertexFunction, inputs: Vec<IRDataType>, output: IRDataType,
+    ) -> Self {
         ExternalFunction {
             name,
             function,
+            inputs,
+            output,
         }
     }
 
@@ -33,6 +45,18 @@ impl ExternalFunction {
     pub fn get_function_exec(&self) -> &VertexFunction {
         &self.function
     }
+
+
+    /// Gets the input argument types of this function.
+    pub fn get_inputs(&self) -> &Vec<IRDataType> {
+        &self.inputs
+    }
+
+
+    /// Gets the output type of this function.
+    pub fn get_output(&self) -> &IRDataType {
+        &self.output
+    }
 }
 
 
@@ -46,6 +70,58 @@ pub enum FunctionCall {
 
     /// Points to the constant data value at the given index.
     Constant(usize),
+
+    /// Reads the field at the given index from the struct provided as the only
+    /// input.
+    Field(usize),
+
+    /// Reads the element at the given index from the tuple provided as the only
+    /// input.
+    Element(usize),
+
+    /// Applies a built-in operator to all inputs.
+    Operator(Operator),
+
+    /// Selects between the second and third inputs based off the first input,
+    /// only evaluating the selected input.
+    Select,
+
+    /// Applies the target function to each element of the list input.
+    Map(Box<FunctionCall>),
+
+    /// Keeps each element of the list input for which the target function
+    /// returns true.
+    Filter(Box<FunctionCall>),
+
+    /// Combines all elements of the list input using the associative target
+    /// function.
+    Reduce(Box<FunctionCall>),
+
+    /// Combines all elements of the list input, in order, using the target
+    /// function, starting from the initial value input.
+    Fold(Box<FunctionCall>),
+
+    /// Outputs the target function as a function value, capturing all inputs.
+    Closure(Box<FunctionCall>),
+
+    /// Calls the function value provided by the first input with all remaining
+    /// inputs. When used as the target of a higher-order function, the function
+    /// value is provided by the last input of that function instead.
+    Invoke,
+
+    /// Builds a list from all inputs.
+    BuildList,
+
+    /// Builds a tuple from all inputs.
+    BuildTuple,
+
+    /// Builds a dictionary from all inputs, alternating between keys and
+    /// values.
+    BuildDictionary,
+
+    /// Constructs a struct with the struct type name at the given index, where
+    /// each input is assigned to the field at the same index.
+    Struct(usize),
 }
 
 
@@ -64,6 +140,7 @@ pub enum OperationInput {
 pub struct Operation {
     function_call: FunctionCall,
     inputs:        Vec<OperationInput>,
+    after:         Option<usize>,
 }
 
 impl Operation {
@@ -73,6 +150,7 @@ impl Operation {
         Operation {
             function_call,
             inputs,
+            after: None,
         }
     }
 
@@ -87,24 +165,74 @@ impl Operation {
     pub fn get_inputs(&self) -> &Vec<OperationInput> {
         &self.inputs
     }
+
+
+    /// Gets the index of the operation within the same function that must
+    /// finish executing before this operation is executed, if any.
+    pub fn get_after(&self) -> Option<usize> {
+        self.after
+    }
+
+
+    /// Sets the index of the operation within the same function that must
+    /// finish executing before this operation is executed.
+    pub fn set_after(&mut self, after: Option<usize>) {
+        self.after = after;
+    }
 }
 
 
 /// A container for a set of operation instructions that need to be executed in
 /// order to evaluation this function's output value based on a set of inputs.
 pub struct InternalFunction {
-    operations: Vec<Operation>,
+    ident_path:  Vec<String>,
+    param_count: usize,
+    operations:  Vec<Operation>,
+    output:      Option<OperationInput>,
+    after:       Option<usize>,
 }
 
 impl InternalFunction {
-    /// Creates a new, empty internal function instance.
+    /// Creates a new, empty internal function instance, without a name or any
+    /// parameters.
     pub fn new() -> Self {
         InternalFunction {
-            operations: vec![],
+            ident_path:  vec![],
+            param_count: 0,
+            operations:  vec![],
+            output:      None,
+            after:       None,
         }
     }
 
 
+    /// Gets the full identifier path of the function this was compiled from.
+    ///
+    /// This is used to find functions by name, such as the entry function of a
+    /// program, and is empty if the function was not given a name.
+    pub fn get_path(&self) -> &Vec<String> {
+        &self.ident_path
+    }
+
+
+    /// Sets the full identifier path of the function this was compiled from.
+    pub fn set_path(&mut self, ident_path: Vec<String>) {
+        self.ident_path = ident_path;
+    }
+
+
+    /// Gets the number of parameters this function takes.
+    pub fn get_param_count(&self) -> usize {
+        self.param_count
+    }
+
+
+    /// Sets the number of parameters this function takes.
+    pub fn set_param_count(&mut self, param_count: usize) {
+        self.param_count = param_count;
+    }
+
+
     /// Appends a new operation to this function.
     pub fn add_operation(&mut self, operation: Operation) {
         self.operations.push(operation);
@@ -115,6 +243,34 @@ impl InternalFunction {
     pub fn get_operations(&self) -> &Vec<Operation> {
         &self.operations
     }
+
+
+    /// Gets the input pointer that provides the output value of this function.
+    ///
+    /// If this is None, then this function outputs a Null value.
+    pub fn get_output(&self) -> Option<&OperationInput> {
+        self.output.as_ref()
+    }
+
+
+    /// Sets the input pointer that provides the output value of this function.
+    pub fn set_output(&mut self, output: Option<OperationInput>) {
+        self.output = output;
+    }
+
+
+    /// Gets the index of the operation that must finish executing before this
+    /// function outputs it's value, if any.
+    pub fn get_after(&self) -> Option<usize> {
+        self.after
+    }
+
+
+    /// Sets the index of the operation that must finish executing before this
+    /// function outputs it's value.
+    pub fn set_after(&mut self, after: Option<usize>) {
+        self.after = after;
+    }
 }
 
 impl Default for InternalFunction {
@@ -129,6 +285,7 @@ pub struct VertexBytecode {
     external_functions: Vec<ExternalFunction>,
     internal_functions: Vec<InternalFunction>,
     constants:          Vec<Data>,
+    struct_types:       Vec<String>,
 }
 
 impl VertexBytecode {
@@ -138,6 +295,7 @@ impl VertexBytecode {
             external_functions: vec![],
             internal_functions: vec![],
             constants:          vec![],
+            struct_types:       vec![],
         }
     }
 
@@ -176,6 +334,19 @@ impl VertexBytecode {
     pub fn get_constants(&self) -> &Vec<Data> {
         &self.constants
     }
+
+
+    /// Adds a new struct type name to this bytecode.
+    pub fn add_struct_type(&mut self, struct_type: String) {
+        self.struct_types.push(struct_type);
+    }
+
+
+    /// Gets a list of all struct type names that are constructed within this
+    /// bytecode.
+    pub fn get_struct_types(&self) -> &Vec<String> {
+        &self.struct_types
+    }
 }
 
 impl Default for VertexBytecode {
@@ -194,33 +365,47 @@ pub fn bytecode_from_ir(context: IRContext, registry: &FunctionRegistry) -> Vert
 
     for function in context.get_functions() {
         let mut int_func = InternalFunction::new();
+        int_func.set_path(function.path().clone());
+        int_func.set_param_count(function.get_inputs().len());
+
         for statement in function.get_statements() {
             let func = match statement.get_function() {
                 IRFuncCall::External(f) => add_ext_func(&mut bytecode, f, registry),
                 IRFuncCall::Internal(f) => FunctionCall::Internal(*f),
+                IRFuncCall::Field(i) => FunctionCall::Field(*i),
+                IRFuncCall::Element(i, _) => FunctionCall::Element(*i),
+                IRFuncCall::Operator(o) => FunctionCall::Operator(*o),
+                IRFuncCall::Select => FunctionCall::Select,
+                IRFuncCall::Map(t) => FunctionCall::Map(target(&mut bytecode, t, registry)),
+                IRFuncCall::Filter(t) => FunctionCall::Filter(target(&mut bytecode, t, registry)),
+                IRFuncCall::Reduce(t) => FunctionCall::Reduce(target(&mut bytecode, t, registry)),
+                IRFuncCall::Fold(t) => FunctionCall::Fold(target(&mut bytecode, t, registry)),
+                IRFuncCall::Closure(t) => FunctionCall::Closure(target(&mut bytecode, t, registry)),
+                IRFuncCall::Invoke(_) => FunctionCall::Invoke,
+                IRFuncCall::BuildList => FunctionCall::BuildList,
+                IRFuncCall::BuildTuple => FunctionCall::BuildTuple,
+                IRFuncCall::BuildDictionary => FunctionCall::BuildDictionary,
+                IRFuncCall::Struct(s) => add_struct_type(&mut bytecode, s),
                 IRFuncCall::IntConstant(v) => add_const(&mut bytecode, Data::Int(*v)),
                 IRFuncCall::FloatConstant(v) => add_const(&mut bytecode, Data::Float(*v)),
                 IRFuncCall::StringConstant(v) => add_const(&mut bytecode, Data::String(v.clone())),
                 IRFuncCall::CharConstant(v) => add_const(&mut bytecode, Data::Char(*v)),
                 IRFuncCall::BoolConstant(v) => add_const(&mut bytecode, Data::Bool(*v)),
-                IRFuncCall::Unresolved(_) => {
+                IRFuncCall::Unresolved(_)
+                | IRFuncCall::UnresolvedStruct(..)
+                | IRFuncCall::UnresolvedField(_) => {
                     panic!("Cannot load bytecode from unresolved functions!")
                 },
             };
 
-            let mut inputs = vec![];
-            for input in statement.get_inputs() {
-                let input = match input {
-                    IRNodeInput::FunctionParam(i) => OperationInput::Param(*i as usize),
-                    IRNodeInput::HiddenNode(i) => OperationInput::Hidden(*i as usize),
-                };
-                inputs.push(input);
-            }
-
-            let operation = Operation::new(func, inputs);
+            let inputs = statement.get_inputs().iter().map(operation_input).collect();
+            let mut operation = Operation::new(func, inputs);
+            operation.set_after(statement.get_after().map(|index| index as usize));
             int_func.add_operation(operation);
         }
 
+        int_func.set_output(function.get_result().map(operation_input));
+        int_func.set_after(function.get_after().map(|index| index as usize));
         bytecode.add_internal_function(int_func);
     }
 
@@ -228,6 +413,26 @@ pub fn bytecode_from_ir(context: IRContext, registry: &FunctionRegistry) -> Vert
 }
 
 
+fn operation_input(input: &IRNodeInput) -> OperationInput {
+    match input {
+        IRNodeInput::FunctionParam(i) => OperationInput::Param(*i as usize),
+        IRNodeInput::HiddenNode(i) => OperationInput::Hidden(*i as usize),
+    }
+}
+
+
+fn target(
+    bytecode: &mut VertexBytecode, target: &IRFuncCall, registry: &FunctionRegistry,
+) -> Box<FunctionCall> {
+    Box::new(match target {
+        IRFuncCall::Internal(index) => FunctionCall::Internal(*index),
+        IRFuncCall::External(name) => add_ext_func(bytecode, name, registry),
+        IRFuncCall::Invoke(_) => FunctionCall::Invoke,
+        _ => panic!("Cannot load bytecode from unresolved functions!"),
+    })
+}
+
+
 fn add_const(bytecode: &mut VertexBytecode, constant: Data) -> FunctionCall {
     if let Some(index) = bytecode.get_constants().iter().position(|c| *c == constant) {
         FunctionCall::Constant(index)
@@ -238,6 +443,16 @@ fn add_const(bytecode: &mut VertexBytecode, constant: Data) -> FunctionCall {
 }
 
 
+fn add_struct_type(bytecode: &mut VertexBytecode, struct_type: &str) -> FunctionCall {
+    if let Some(index) = bytecode.get_struct_types().iter().position(|s| s == struct_type) {
+        FunctionCall::Struct(index)
+    } else {
+        bytecode.add_struct_type(struct_type.to_owned());
+        FunctionCall::Struct(bytecode.get_struct_types().len() - 1)
+    }
+}
+
+
 fn add_ext_func(
     bytecode: &mut VertexBytecode, function: &str, registry: &FunctionRegistry,
 ) -> FunctionCall {
@@ -248,8 +463,12 @@ fn add_ext_func(
     {
         FunctionCall::External(index)
     } else if let Some(func_meta) = registry.get_function(function) {
-        let func = func_meta.get_func();
-        bytecode.add_external_function(ExternalFunction::new(function.to_owned(), func));
+        bytecode.add_external_function(ExternalFunction::new(
+            function.to_owned(),
+            func_meta.get_func(),
+            func_meta.get_inputs().clone(),
+            func_meta.get_output().clone(),
+        ));
         FunctionCall::External(bytecode.get_external_functions().len() - 1)
     } else {
         panic!("Unknown function: {}", function);
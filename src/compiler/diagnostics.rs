@@ -0,0 +1,426 @@
+//! A common format for reporting problems found within Vertex source code.
+//!
+//! Diagnostics can be rendered as human-readable messages with an annotated
+//! snippet of the offending source code, or as JSON objects for use by editors
+//! and other tools.
+
+
+use super::ast::NodePosition;
+use super::errors::CompilerError;
+use super::sources::SourceSet;
+use std::fmt;
+use std::fmt::Write;
+
+
+/// The number of columns that a tab character is rendered as within source code
+/// snippets.
+const TAB_WIDTH: usize = 4;
+
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// A problem that prevents the program from being compiled.
+    Error,
+
+    /// A possible problem that does not prevent the program from being
+    /// compiled.
+    Warning,
+
+    /// Additional information about the program.
+    Note,
+}
+
+impl Severity {
+    /// Gets the lowercase name of this severity level.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+
+/// A message describing a problem found while loading or compiling a program,
+/// optionally pointing at the range of source code that caused it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    code:     Option<&'static str>,
+    message:  String,
+    position: Option<Box<NodePosition>>,
+    notes:    Vec<String>,
+    help:     Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic without any notes or help text.
+    pub fn new(
+        severity: Severity, code: Option<&'static str>, message: String,
+        position: Option<NodePosition>,
+    ) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message,
+            position: position.map(Box::new),
+            notes: vec![],
+            help: None,
+        }
+    }
+
+
+    /// Creates a new error diagnostic that is not tied to any source code or
+    /// error code.
+    pub fn error(message: String) -> Self {
+        Diagnostic::new(Severity::Error, None, message, None)
+    }
+
+
+    /// Gets the severity of this diagnostic.
+    pub fn get_severity(&self) -> Severity {
+        self.severity
+    }
+
+
+    /// Gets the error code of this diagnostic, such as `E0101`, if it has one.
+    pub fn get_code(&self) -> Option<&'static str> {
+        self.code
+    }
+
+
+    /// Gets the main message of this diagnostic.
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+
+    /// Gets the range of source code this diagnostic points at, if any.
+    pub fn get_position(&self) -> Option<&NodePosition> {
+        self.position.as_deref()
+    }
+
+
+    /// Gets the list of notes that give additional context for this
+    /// diagnostic.
+    pub fn get_notes(&self) -> &Vec<String> {
+        &self.notes
+    }
+
+
+    /// Adds a note that gives additional context for this diagnostic.
+    pub fn add_note(&mut self, note: String) {
+        self.notes.push(note);
+    }
+
+
+    /// Gets the help text that suggests how to fix this diagnostic, if any.
+    pub fn get_help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+
+    /// Sets the help text that suggests how to fix this diagnostic.
+    pub fn set_help(&mut self, help: Option<String>) {
+        self.help = help;
+    }
+
+
+    /// Renders this diagnostic as a human-readable message. If the diagnostic
+    /// points at a file within the given source set, then the offending line
+    /// is included, with carets underlining the offending range.
+    pub fn render(&self, sources: &SourceSet) -> String {
+        let mut out = String::new();
+
+        write!(out, "{}", self.severity).unwrap();
+        if let Some(code) = self.code {
+            write!(out, "[{}]", code).unwrap();
+        }
+        writeln!(out, ": {}", self.message).unwrap();
+
+        let mut gutter = String::from(" ");
+        if let Some(position) = &self.position {
+            let line_number = position.line.to_string();
+            gutter = " ".repeat(line_number.len() + 1);
+
+            match sources.get_file(position.file) {
+                Some(file) => {
+                    writeln!(
+                        out,
+                        "{}--> {}:{}:{}",
+                        &gutter[1..],
+                        file.get_path().display(),
+                        position.line,
+                        position.col
+                    )
+                    .unwrap();
+
+                    if let Some(line) = file.get_source().lines().nth(position.line - 1) {
+                        let (offset, width) = caret_range(line, position);
+                        writeln!(out, "{}|", gutter).unwrap();
+                        writeln!(out, "{} | {}", line_number, expand_tabs(line)).unwrap();
+                        writeln!(
+                            out,
+                            "{}| {}{}",
+                            gutter,
+                            " ".repeat(offset),
+                            "^".repeat(width)
+                        )
+                        .unwrap();
+                    }
+                },
+                None => {
+                    writeln!(
+                        out,
+                        "{}--> {}:{}",
+                        &gutter[1..],
+                        position.line,
+                        position.col
+                    )
+                    .unwrap();
+                },
+            }
+        }
+
+        for note in &self.notes {
+            writeln!(out, "{}= note: {}", gutter, note).unwrap();
+        }
+
+        if let Some(help) = &self.help {
+            writeln!(out, "{}= help: {}", gutter, help).unwrap();
+        }
+
+        out
+    }
+
+
+    /// Renders this diagnostic as a single-line JSON object, using the given
+    /// source set to look up the path of the file it points at.
+    pub fn to_json(&self, sources: &SourceSet) -> String {
+        let mut out = String::from("{");
+
+        write!(out, "\"severity\":{}", json_string(self.severity.name())).unwrap();
+        write!(
+            out,
+            ",\"code\":{}",
+            self.code.map_or(String::from("null"), json_string)
+        )
+        .unwrap();
+        write!(out, ",\"message\":{}", json_string(&self.message)).unwrap();
+
+        match &self.position {
+            Some(position) => {
+                let path = sources.get_file(position.file).map_or(String::from("null"), |file| {
+                    json_string(&file.get_path().display().to_string())
+                });
+
+                write!(
+                    out,
+                    ",\"file\":{},\"span\":{{\"line\":{},\"col\":{},\"end_line\":{},\"end_col\":{}}}",
+                    path, position.line, position.col, position.end_line, position.end_col
+                )
+                .unwrap();
+            },
+            None => out.push_str(",\"file\":null,\"span\":null"),
+        }
+
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+        write!(out, ",\"notes\":[{}]", notes.join(",")).unwrap();
+        write!(
+            out,
+            ",\"help\":{}",
+            self.help.as_deref().map_or(String::from("null"), json_string)
+        )
+        .unwrap();
+
+        out.push('}');
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+
+        if let Some(position) = &self.position {
+            write!(f, ", at {}:{}", position.line, position.col)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl From<CompilerError> for Diagnostic {
+    fn from(error: CompilerError) -> Self {
+        let source = error.get_source();
+        let mut diagnostic = Diagnostic::new(
+            Severity::Error,
+            Some(source.code()),
+            source.to_string(),
+            Some(error.get_position().clone()),
+        );
+
+        diagnostic.set_help(source.help());
+        diagnostic
+    }
+}
+
+
+/// Gets the column offset and the width of the carets that underline the given
+/// position within the given line of source code, accounting for tabs.
+///
+/// If the position spans multiple lines, then the carets continue to the end of
+/// the given line. At least one caret is always shown.
+fn caret_range(line: &str, position: &NodePosition) -> (usize, usize) {
+    let end_col = if position.end_line == position.line {
+        position.end_col
+    } else {
+        line.chars().count() + 1
+    };
+
+    let mut offset = 0;
+    let mut width = 0;
+    for (index, c) in line.chars().enumerate() {
+        let col = index + 1;
+        let size = if c == '\t' { TAB_WIDTH } else { 1 };
+
+        if col < position.col {
+            offset += size;
+        } else if col < end_col {
+            width += size;
+        }
+    }
+
+    (offset, width.max(1))
+}
+
+
+/// Replaces all tabs within the given line with spaces.
+fn expand_tabs(line: &str) -> String {
+    line.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+
+/// Formats the given text as a quoted JSON string.
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use std::path::Path;
+
+
+    #[test]
+    fn render_snippet() {
+        let mut sources = SourceSet::new();
+        sources
+            .add_source(
+                Path::new("main.vx"),
+                String::from(indoc! {r#"
+                    Main = mod {
+                        Main = function {
+                            params = ()
+                            return = (value: Int)
+
+                            value = "Hello" + 1
+                        }
+                    }
+                "#}),
+            )
+            .unwrap();
+
+        let mut diagnostic = Diagnostic::new(
+            Severity::Error,
+            Some("E0109"),
+            String::from("Operator '+' cannot be applied to types 'String' and 'Int'"),
+            Some(NodePosition {
+                file:     0,
+                line:     6,
+                col:      17,
+                end_line: 6,
+                end_col:  28,
+            }),
+        );
+        diagnostic.add_note(String::from("Both operands must have the same type"));
+        diagnostic.set_help(Some(String::from("Convert the \"Int\" to a string")));
+
+        assert_eq!(diagnostic.render(&sources), indoc! {r#"
+            error[E0109]: Operator '+' cannot be applied to types 'String' and 'Int'
+             --> main.vx:6:17
+              |
+            6 |         value = "Hello" + 1
+              |                 ^^^^^^^^^^^
+              = note: Both operands must have the same type
+              = help: Convert the "Int" to a string
+        "#});
+
+        assert_eq!(
+            diagnostic.to_json(&sources),
+            concat!(
+                r#"{"severity":"error","code":"E0109","#,
+                r#""message":"Operator '+' cannot be applied to types 'String' and 'Int'","#,
+                r#""file":"main.vx","span":{"line":6,"col":17,"end_line":6,"end_col":28},"#,
+                r#""notes":["Both operands must have the same type"],"#,
+                r#""help":"Convert the \"Int\" to a string"}"#
+            )
+        );
+
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[E0109]: Operator '+' cannot be applied to types 'String' and 'Int', at 6:17"
+        );
+    }
+
+
+    #[test]
+    fn render_without_position() {
+        let sources = SourceSet::new();
+        let diagnostic = Diagnostic::error(String::from("Cannot find entry function 'Main'"));
+
+        assert_eq!(
+            diagnostic.render(&sources),
+            "error: Cannot find entry function 'Main'\n"
+        );
+        assert_eq!(
+            diagnostic.to_json(&sources),
+            concat!(
+                r#"{"severity":"error","code":null,"#,
+                r#""message":"Cannot find entry function 'Main'","#,
+                r#""file":null,"span":null,"notes":[],"help":null}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,605 @@
+//! Exports the dataflow graphs of Vertex functions in the Graphviz DOT format,
+//! so that they can be rendered with tools such as `dot`.
+//!
+//! Each node within a function graph has a shape depending on what it is:
+//!
+//! - Function parameters are drawn as an inverted house.
+//! - Constant values are drawn as a box.
+//! - Calls to internal functions are drawn as a component.
+//! - Calls to external functions are drawn as a cds.
+//! - All other built-in calls, such as operators, are drawn as an ellipse.
+//! - The output of the function is drawn as a house.
+//!
+//! Each input edge is labelled with the index of the argument it provides.
+//! Within intermediate representation graphs, the label also includes the data
+//! type of the argument. Bytecode does not keep the data types of operations,
+//! so it's edges are only labelled with the argument index. Dashed edges point
+//! from a serial call to the node that must wait for it.
+
+
+use super::assembly::{format_call, format_data};
+use super::bytecode::{FunctionCall, InternalFunction, OperationInput, VertexBytecode};
+use super::ir::{IRContext, IRFuncCall, IRFunction, IRNodeInput};
+
+
+/// Renders the given function, from within the given context, as a DOT
+/// digraph.
+pub fn ir_function_to_dot(context: &IRContext, function: &IRFunction) -> String {
+    standalone_digraph(&ir_graph(context, function))
+}
+
+
+/// Renders all functions within the given context as a single DOT digraph,
+/// where each function is drawn within it's own cluster.
+///
+/// If `nested` is true, then the clusters are nested by identifier path, so
+/// that each module contains the clusters of it's functions, and each function
+/// contains the clusters of the functions defined within it.
+pub fn ir_context_to_dot(context: &IRContext, nested: bool) -> String {
+    let graphs = context.get_functions().iter().map(|f| ir_graph(context, f)).collect();
+    clustered_digraph(graphs, nested)
+}
+
+
+/// Renders the given internal function, from within the given bytecode, as a
+/// DOT digraph.
+pub fn bytecode_function_to_dot(bytecode: &VertexBytecode, function: &InternalFunction) -> String {
+    standalone_digraph(&bytecode_graph(bytecode, function))
+}
+
+
+/// Renders all internal functions within the given bytecode as a single DOT
+/// digraph, where each function is drawn within it's own cluster.
+///
+/// If `nested` is true, then the clusters are nested by identifier path, the
+/// same as [`ir_context_to_dot`].
+pub fn bytecode_to_dot(bytecode: &VertexBytecode, nested: bool) -> String {
+    let functions = bytecode.get_internal_functions();
+    let graphs = functions.iter().map(|f| bytecode_graph(bytecode, f)).collect();
+    clustered_digraph(graphs, nested)
+}
+
+
+/// The type of a node within a function graph, which determines it's shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Param,
+    Constant,
+    Internal,
+    External,
+    Builtin,
+    Output,
+}
+
+impl NodeKind {
+    fn shape(&self) -> &'static str {
+        match self {
+            NodeKind::Param => "invhouse",
+            NodeKind::Constant => "box",
+            NodeKind::Internal => "component",
+            NodeKind::External => "cds",
+            NodeKind::Builtin => "ellipse",
+            NodeKind::Output => "house",
+        }
+    }
+}
+
+
+/// An edge between two nodes within a function graph, where the nodes are
+/// referenced by their index. Edges without a label only define the order of
+/// serial calls.
+struct GraphEdge {
+    from:  usize,
+    to:    usize,
+    label: Option<String>,
+}
+
+
+/// The dataflow graph of a single function, where the parameter nodes come
+/// first, followed by one node per statement and finally the output node.
+struct FunctionGraph {
+    path:  Vec<String>,
+    nodes: Vec<(NodeKind, String)>,
+    edges: Vec<GraphEdge>,
+}
+
+
+fn ir_graph(context: &IRContext, function: &IRFunction) -> FunctionGraph {
+    let params = function.get_inputs();
+    let statements = function.get_statements();
+
+    let node_of = |input: &IRNodeInput| {
+        match *input {
+            IRNodeInput::FunctionParam(index) => index as usize,
+            IRNodeInput::HiddenNode(index) => params.len() + index as usize,
+        }
+    };
+
+    let type_of = |input: &IRNodeInput| {
+        match *input {
+            IRNodeInput::FunctionParam(index) => params[index as usize].to_string(),
+            IRNodeInput::HiddenNode(index) => statements[index as usize].get_output().to_string(),
+        }
+    };
+
+    let mut graph = FunctionGraph {
+        path:  function.path().clone(),
+        nodes: vec![],
+        edges: vec![],
+    };
+
+    for (index, dtype) in params.iter().enumerate() {
+        graph.nodes.push((NodeKind::Param, format!("${}: {}", index, dtype)));
+    }
+
+    for (index, statement) in statements.iter().enumerate() {
+        let call = statement.get_function();
+        let node = graph.nodes.len();
+        graph.nodes.push((ir_call_kind(call), ir_call_label(context, call)));
+
+        for (arg, input) in statement.get_inputs().iter().enumerate() {
+            let label = format!("{}: {}", arg, type_of(input));
+            graph.edges.push(GraphEdge {
+                from:  node_of(input),
+                to:    node,
+                label: Some(label),
+            });
+        }
+
+        if let Some(after) = statement.get_after() {
+            graph.edges.push(GraphEdge {
+                from:  params.len() + after as usize,
+                to:    params.len() + index,
+                label: None,
+            });
+        }
+    }
+
+    let output = graph.nodes.len();
+    graph.nodes.push((NodeKind::Output, String::from("output")));
+
+    if let Some(result) = function.get_result() {
+        graph.edges.push(GraphEdge {
+            from:  node_of(result),
+            to:    output,
+            label: Some(format!("0: {}", type_of(result))),
+        });
+    }
+
+    if let Some(after) = function.get_after() {
+        graph.edges.push(GraphEdge {
+            from:  params.len() + after as usize,
+            to:    output,
+            label: None,
+        });
+    }
+
+    graph
+}
+
+
+fn ir_call_kind(call: &IRFuncCall) -> NodeKind {
+    match call {
+        IRFuncCall::Internal(_) | IRFuncCall::Unresolved(_) => NodeKind::Internal,
+        IRFuncCall::External(_) => NodeKind::External,
+        IRFuncCall::IntConstant(_)
+        | IRFuncCall::FloatConstant(_)
+        | IRFuncCall::StringConstant(_)
+        | IRFuncCall::CharConstant(_)
+        | IRFuncCall::BoolConstant(_) => NodeKind::Constant,
+        call => call.get_target().map_or(NodeKind::Builtin, ir_call_kind),
+    }
+}
+
+
+/// Gets the label of the given function call, where functions are named by
+/// their identifier path rather than their index.
+fn ir_call_label(context: &IRContext, call: &IRFuncCall) -> String {
+    match call {
+        IRFuncCall::Internal(index) => {
+            match context.get_functions().get(*index) {
+                Some(function) => function.path().join("."),
+                None => call.to_string(),
+            }
+        },
+        IRFuncCall::External(name) | IRFuncCall::Unresolved(name) => name.clone(),
+        IRFuncCall::Operator(operator) => operator.to_string(),
+        IRFuncCall::Map(target) => format!("map({})", ir_call_label(context, target)),
+        IRFuncCall::Filter(target) => format!("filter({})", ir_call_label(context, target)),
+        IRFuncCall::Reduce(target) => format!("reduce({})", ir_call_label(context, target)),
+        IRFuncCall::Fold(target) => format!("fold({})", ir_call_label(context, target)),
+        IRFuncCall::Closure(target) => format!("closure({})", ir_call_label(context, target)),
+        call => call.to_string(),
+    }
+}
+
+
+fn bytecode_graph(bytecode: &VertexBytecode, function: &InternalFunction) -> FunctionGraph {
+    let params = function.get_param_count();
+    let node_of = |input: &OperationInput| {
+        match *input {
+            OperationInput::Param(index) => index,
+            OperationInput::Hidden(index) => params + index,
+        }
+    };
+
+    let mut graph = FunctionGraph {
+        path:  function.get_path().clone(),
+        nodes: vec![],
+        edges: vec![],
+    };
+
+    for index in 0..params {
+        graph.nodes.push((NodeKind::Param, format!("${}", index)));
+    }
+
+    for (index, operation) in function.get_operations().iter().enumerate() {
+        let call = operation.get_function();
+        let node = graph.nodes.len();
+        graph.nodes.push((bytecode_call_kind(call), bytecode_call_label(bytecode, call)));
+
+        for (arg, input) in operation.get_inputs().iter().enumerate() {
+            graph.edges.push(GraphEdge {
+                from:  node_of(input),
+                to:    node,
+                label: Some(arg.to_string()),
+            });
+        }
+
+        if let Some(after) = operation.get_after() {
+            graph.edges.push(GraphEdge {
+                from:  params + after,
+                to:    params + index,
+                label: None,
+            });
+        }
+    }
+
+    let output = graph.nodes.len();
+    graph.nodes.push((NodeKind::Output, String::from("output")));
+
+    if let Some(result) = function.get_output() {
+        graph.edges.push(GraphEdge {
+            from:  node_of(result),
+            to:    output,
+            label: Some(String::from("0")),
+        });
+    }
+
+    if let Some(after) = function.get_after() {
+        graph.edges.push(GraphEdge {
+            from:  params + after,
+            to:    output,
+            label: None,
+        });
+    }
+
+    graph
+}
+
+
+fn bytecode_call_kind(call: &FunctionCall) -> NodeKind {
+    match call {
+        FunctionCall::Internal(_) => NodeKind::Internal,
+        FunctionCall::External(_) => NodeKind::External,
+        FunctionCall::Constant(_) => NodeKind::Constant,
+        FunctionCall::Map(target)
+        | FunctionCall::Filter(target)
+        | FunctionCall::Reduce(target)
+        | FunctionCall::Fold(target)
+        | FunctionCall::Closure(target) => bytecode_call_kind(target),
+        _ => NodeKind::Builtin,
+    }
+}
+
+
+/// Gets the label of the given function call, where functions, constants and
+/// struct types are shown by their value rather than their index.
+fn bytecode_call_label(bytecode: &VertexBytecode, call: &FunctionCall) -> String {
+    let functions = bytecode.get_internal_functions();
+
+    match call {
+        FunctionCall::Internal(index) if !functions[*index].get_path().is_empty() => {
+            functions[*index].get_path().join(".")
+        },
+        FunctionCall::External(index) => {
+            bytecode.get_external_functions()[*index].get_function_name().to_owned()
+        },
+        FunctionCall::Constant(index) => format_data(&bytecode.get_constants()[*index]),
+        FunctionCall::Struct(index) => format!("struct {}", bytecode.get_struct_types()[*index]),
+        FunctionCall::Operator(operator) => operator.to_string(),
+        FunctionCall::Map(target) => format!("map({})", bytecode_call_label(bytecode, target)),
+        FunctionCall::Filter(target) => {
+            format!("filter({})", bytecode_call_label(bytecode, target))
+        },
+        FunctionCall::Reduce(target) => {
+            format!("reduce({})", bytecode_call_label(bytecode, target))
+        },
+        FunctionCall::Fold(target) => format!("fold({})", bytecode_call_label(bytecode, target)),
+        FunctionCall::Closure(target) => {
+            format!("closure({})", bytecode_call_label(bytecode, target))
+        },
+        call => format_call(call),
+    }
+}
+
+
+/// Surrounds the given text with double quotes, escaping it as a DOT string.
+fn quote(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+
+/// Writes the nodes and edges of the given function graph, where each node
+/// identifier is prefixed by the given function index.
+fn write_graph(lines: &mut Vec<String>, graph: &FunctionGraph, index: usize, indent: &str) {
+    for (node, (kind, label)) in graph.nodes.iter().enumerate() {
+        lines.push(format!(
+            "{}f{}_{} [label={}, shape={}];",
+            indent,
+            index,
+            node,
+            quote(label),
+            kind.shape()
+        ));
+    }
+
+    for edge in &graph.edges {
+        let attributes = match &edge.label {
+            Some(label) => format!("label={}", quote(label)),
+            None => String::from("style=dashed"),
+        };
+        lines.push(format!(
+            "{}f{}_{} -> f{}_{} [{}];",
+            indent, index, edge.from, index, edge.to, attributes
+        ));
+    }
+}
+
+
+fn standalone_digraph(graph: &FunctionGraph) -> String {
+    let mut lines = vec![format!("digraph {} {{", quote(&graph.path.join(".")))];
+    write_graph(&mut lines, graph, 0, "    ");
+    lines.push(String::from("}"));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+
+fn clustered_digraph(graphs: Vec<FunctionGraph>, nested: bool) -> String {
+    let mut lines = vec![String::from("digraph {")];
+
+    if nested {
+        let mut root = Cluster::default();
+        for (index, graph) in graphs.iter().enumerate() {
+            root.insert(&graph.path, index, graph);
+        }
+        root.write(&mut lines, "", 1);
+    } else {
+        for (index, graph) in graphs.iter().enumerate() {
+            lines.push(format!("    subgraph \"cluster_{}\" {{", index));
+            lines.push(format!("        label = {};", quote(&graph.path.join("."))));
+            write_graph(&mut lines, graph, index, "        ");
+            lines.push(String::from("    }"));
+        }
+    }
+
+    lines.push(String::from("}"));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+
+/// A group of function graphs with the same identifier path, along with the
+/// groups of all paths nested within that path.
+#[derive(Default)]
+struct Cluster<'a> {
+    graphs:   Vec<(usize, &'a FunctionGraph)>,
+    children: Vec<(String, Cluster<'a>)>,
+}
+
+impl<'a> Cluster<'a> {
+    /// Adds the given function graph to the cluster at the given path, relative
+    /// to this cluster.
+    fn insert(&mut self, path: &[String], index: usize, graph: &'a FunctionGraph) {
+        let (name, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return self.graphs.push((index, graph)),
+        };
+
+        let position = match self.children.iter().position(|(child, _)| child == name) {
+            Some(position) => position,
+            None => {
+                self.children.push((name.clone(), Cluster::default()));
+                self.children.len() - 1
+            },
+        };
+
+        self.children[position].1.insert(rest, index, graph);
+    }
+
+
+    /// Writes the contents of this cluster, where each child cluster is written
+    /// as a subgraph named after it's full path.
+    fn write(&self, lines: &mut Vec<String>, path: &str, depth: usize) {
+        let indent = "    ".repeat(depth);
+
+        for (index, graph) in &self.graphs {
+            write_graph(lines, graph, *index, &indent);
+        }
+
+        for (name, child) in &self.children {
+            let child_path = match path {
+                "" => name.clone(),
+                path => format!("{}.{}", path, name),
+            };
+
+            let cluster = quote(&format!("cluster_{}", child_path));
+            lines.push(format!("{}subgraph {} {{", indent, cluster));
+            lines.push(format!("{}    label = {};", indent, quote(name)));
+            child.write(lines, &child_path, depth + 1);
+            lines.push(format!("{}}}", indent));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::bytecode::bytecode_from_ir;
+    use crate::compiler::grammar::parse;
+    use crate::compiler::ir::{compile_context, IRDataType};
+    use crate::compiler::resolver::resolve_context;
+    use crate::compiler::typecheck::check_types;
+    use crate::data::Data;
+    use crate::registry::{FuncMeta, FunctionRegistry};
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+
+    fn print(_: Vec<Arc<Data>>) -> Data {
+        Data::Null
+    }
+
+
+    fn registry() -> FunctionRegistry {
+        let inputs = vec![IRDataType::Int];
+        let meta = FuncMeta::new(String::from("Print"), print, inputs, IRDataType::Null).unwrap();
+        let mut registry = FunctionRegistry::new();
+        registry.register(meta).unwrap();
+        registry
+    }
+
+
+    fn compile(registry: &FunctionRegistry) -> IRContext {
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Main = serial function {
+                    params = (count: Int)
+                    return = (total: Int[])
+
+                    serial extern Print(count)
+                    total = Sum(count, 1)
+                }
+
+                Sum = function {
+                    params = (a: Int, b: Int)
+                    return = (sum: Int[])
+
+                    sum = map([a, b], function {
+                        params = (n: Int)
+                        return = (m: Int)
+
+                        m = -n
+                    })
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, registry).unwrap();
+        context
+    }
+
+
+    #[test]
+    fn export_ir_function() {
+        let context = compile(&registry());
+        assert_eq!(ir_function_to_dot(&context, &context.get_functions()[0]), indoc! {r#"
+            digraph "Main.Main" {
+                f0_0 [label="$0: Int", shape=invhouse];
+                f0_1 [label="Print", shape=cds];
+                f0_2 [label="int 1", shape=box];
+                f0_3 [label="Main.Sum", shape=component];
+                f0_4 [label="output", shape=house];
+                f0_0 -> f0_1 [label="0: Int"];
+                f0_0 -> f0_3 [label="0: Int"];
+                f0_2 -> f0_3 [label="1: Int"];
+                f0_3 -> f0_4 [label="0: Int[]"];
+                f0_1 -> f0_4 [style=dashed];
+            }
+        "#});
+    }
+
+
+    #[test]
+    fn export_bytecode_function() {
+        let registry = registry();
+        let bytecode = bytecode_from_ir(compile(&registry), &registry);
+        let function = &bytecode.get_internal_functions()[1];
+        assert_eq!(bytecode_function_to_dot(&bytecode, function), indoc! {r#"
+            digraph "Main.Sum" {
+                f0_0 [label="$0", shape=invhouse];
+                f0_1 [label="$1", shape=invhouse];
+                f0_2 [label="list", shape=ellipse];
+                f0_3 [label="closure(Main.Sum.lambda@14:27)", shape=component];
+                f0_4 [label="map(invoke)", shape=ellipse];
+                f0_5 [label="output", shape=house];
+                f0_0 -> f0_2 [label="0"];
+                f0_1 -> f0_2 [label="1"];
+                f0_2 -> f0_4 [label="0"];
+                f0_3 -> f0_4 [label="1"];
+                f0_4 -> f0_5 [label="0"];
+            }
+        "#});
+
+        let dot = bytecode_to_dot(&bytecode, false);
+        assert!(dot.contains(
+            "    subgraph \"cluster_2\" {\n        label = \"Main.Sum.lambda@14:27\";"
+        ));
+        assert!(dot.contains("        f0_2 [label=\"Int 1\", shape=box];"));
+    }
+
+
+    #[test]
+    fn cluster_nested_functions() {
+        let context = compile(&registry());
+        assert_eq!(ir_context_to_dot(&context, true), indoc! {r#"
+            digraph {
+                subgraph "cluster_Main" {
+                    label = "Main";
+                    subgraph "cluster_Main.Main" {
+                        label = "Main";
+                        f0_0 [label="$0: Int", shape=invhouse];
+                        f0_1 [label="Print", shape=cds];
+                        f0_2 [label="int 1", shape=box];
+                        f0_3 [label="Main.Sum", shape=component];
+                        f0_4 [label="output", shape=house];
+                        f0_0 -> f0_1 [label="0: Int"];
+                        f0_0 -> f0_3 [label="0: Int"];
+                        f0_2 -> f0_3 [label="1: Int"];
+                        f0_3 -> f0_4 [label="0: Int[]"];
+                        f0_1 -> f0_4 [style=dashed];
+                    }
+                    subgraph "cluster_Main.Sum" {
+                        label = "Sum";
+                        f1_0 [label="$0: Int", shape=invhouse];
+                        f1_1 [label="$1: Int", shape=invhouse];
+                        f1_2 [label="list", shape=ellipse];
+                        f1_3 [label="closure(Main.Sum.lambda@14:27)", shape=component];
+                        f1_4 [label="map(invoke lambda@14:27)", shape=ellipse];
+                        f1_5 [label="output", shape=house];
+                        f1_0 -> f1_2 [label="0: Int"];
+                        f1_1 -> f1_2 [label="1: Int"];
+                        f1_2 -> f1_4 [label="0: Int[]"];
+                        f1_3 -> f1_4 [label="1: fn(Int) -> Int"];
+                        f1_4 -> f1_5 [label="0: Int[]"];
+                        subgraph "cluster_Main.Sum.lambda@14:27" {
+                            label = "lambda@14:27";
+                            f2_0 [label="$0: Int", shape=invhouse];
+                            f2_1 [label="-", shape=ellipse];
+                            f2_2 [label="output", shape=house];
+                            f2_0 -> f2_1 [label="0: Int"];
+                            f2_1 -> f2_2 [label="0: Int"];
+                        }
+                    }
+                }
+            }
+        "#});
+    }
+}
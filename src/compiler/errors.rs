@@ -6,17 +6,29 @@ use thiserror::Error as ThisError;
 
 #[derive(ThisError, Debug)]
 pub struct CompilerError {
-    source:   IRError,
+    source:   Box<IRError>,
     position: NodePosition,
 }
 
 impl CompilerError {
     pub(crate) fn new(position: NodePosition, source: IRError) -> Self {
         CompilerError {
-            source,
+            source: Box::new(source),
             position,
         }
     }
+
+
+    /// Gets the position within the source code that this error occurred at.
+    pub fn get_position(&self) -> &NodePosition {
+        &self.position
+    }
+
+
+    /// Gets the underlying error that occurred.
+    pub(crate) fn get_source(&self) -> &IRError {
+        &self.source
+    }
 }
 
 impl Display for CompilerError {
@@ -37,4 +49,159 @@ pub enum IRError {
 
     #[error("Cannot find element '{0}' within the current scope")]
     UnknownIdentifier(String),
+
+    #[error("Element '{1}' is not accessible from '{0}'")]
+    InaccessibleIdentifier(String, String),
+
+    #[error("Struct '{0}' cannot contain itself")]
+    RecursiveStruct(String),
+
+    #[error("Function '{0}' expects {1} argument(s), but {2} were given")]
+    IncorrectArgumentCount(String, usize, usize),
+
+    #[error("Argument {1} of function '{0}' expects type '{2}', but found '{3}'")]
+    ArgumentTypeMismatch(String, usize, String, String),
+
+    #[error("Type '{0}' does not have a field named '{1}'")]
+    UnknownField(String, String),
+
+    #[error("Struct '{0}' is missing a value for field '{1}'")]
+    MissingField(String, String),
+
+    #[error("Field '{1}' of struct '{0}' expects type '{2}', but found '{3}'")]
+    FieldTypeMismatch(String, String, String, String),
+
+    #[error("Operator '{0}' cannot be applied to types '{1}' and '{2}'")]
+    InvalidOperands(String, String, String),
+
+    #[error("Operator '{0}' cannot be applied to type '{1}'")]
+    InvalidOperand(String, String),
+
+    #[error("Condition expects type 'Bool', but found '{0}'")]
+    ConditionTypeMismatch(String),
+
+    #[error("Branches of conditional expression have different types '{0}' and '{1}'")]
+    BranchTypeMismatch(String, String),
+
+    #[error("The last argument of '{0}' must be the name of a function")]
+    ExpectedFunctionName(String),
+
+    #[error("Function '{0}' expects a list, but found '{1}'")]
+    ExpectedList(String, String),
+
+    #[error("Function '{0}' must return type '{1}', but returns '{2}'")]
+    FunctionOutputMismatch(String, String, String),
+
+    #[error("Cannot call '{0}' of type '{1}', as it is not a function")]
+    NotAFunction(String, String),
+
+    #[error("Collection elements expect type '{0}', but found '{1}'")]
+    ElementTypeMismatch(String, String),
+
+    #[error("Circular dependency between variables {}", format_cycle(.0))]
+    CircularDependency(Vec<(String, NodePosition)>),
+
+    #[error("Return value '{0}' expects type '{1}', but found '{2}'")]
+    ReturnTypeMismatch(String, String, String),
+
+    #[error("Return value '{0}' is never assigned")]
+    UnassignedReturn(String),
+
+    #[error("Cannot assign type '{0}' to {1} variables, as it is not a tuple of {1} elements")]
+    DestructureMismatch(String, usize),
+
+    #[error("Serial call to '{0}' is only allowed within a serial function")]
+    SerialCallInNonSerialFunction(String),
+
+    #[error("Serial call to '{0}' reads a value that is only available after a later serial call")]
+    SerialOrderConflict(String),
+
+    #[error("Nested function '{0}' cannot capture '{1}' from an enclosing function")]
+    NestedFunctionCapture(String, String),
+}
+
+
+impl IRError {
+    /// Gets the diagnostic error code of this error. Compiler errors use the
+    /// codes `E0101` and above, in the order they are declared.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            IRError::IdentifierAlreadyExists(..) => "E0101",
+            IRError::UnknownIdentifier(..) => "E0102",
+            IRError::InaccessibleIdentifier(..) => "E0103",
+            IRError::RecursiveStruct(..) => "E0104",
+            IRError::IncorrectArgumentCount(..) => "E0105",
+            IRError::ArgumentTypeMismatch(..) => "E0106",
+            IRError::UnknownField(..) => "E0107",
+            IRError::MissingField(..) => "E0108",
+            IRError::FieldTypeMismatch(..) => "E0109",
+            IRError::InvalidOperands(..) => "E0110",
+            IRError::InvalidOperand(..) => "E0111",
+            IRError::ConditionTypeMismatch(..) => "E0112",
+            IRError::BranchTypeMismatch(..) => "E0113",
+            IRError::ExpectedFunctionName(..) => "E0114",
+            IRError::ExpectedList(..) => "E0115",
+            IRError::FunctionOutputMismatch(..) => "E0116",
+            IRError::NotAFunction(..) => "E0117",
+            IRError::ElementTypeMismatch(..) => "E0118",
+            IRError::CircularDependency(..) => "E0119",
+            IRError::ReturnTypeMismatch(..) => "E0120",
+            IRError::UnassignedReturn(..) => "E0121",
+            IRError::DestructureMismatch(..) => "E0122",
+            IRError::SerialCallInNonSerialFunction(..) => "E0123",
+            IRError::SerialOrderConflict(..) => "E0124",
+            IRError::NestedFunctionCapture(..) => "E0125",
+        }
+    }
+
+
+    /// Gets a suggestion for how to fix this error, if there is one.
+    pub(crate) fn help(&self) -> Option<String> {
+        match self {
+            IRError::InaccessibleIdentifier(_, element) => {
+                Some(format!(
+                    "Mark '{}' as 'export' to make it accessible",
+                    element
+                ))
+            },
+            IRError::UnassignedReturn(name) => {
+                Some(format!(
+                    "Assign a value to '{}' within the function body",
+                    name
+                ))
+            },
+            IRError::SerialCallInNonSerialFunction(_) => {
+                Some(String::from(
+                    "Mark the function as 'serial' to allow serial calls",
+                ))
+            },
+            IRError::NestedFunctionCapture(name, _) => {
+                Some(format!(
+                    "Declare '{0}' as a lambda, as in '{0} = (function {{ ... }})', to capture \
+                     values",
+                    name
+                ))
+            },
+            _ => None,
+        }
+    }
+}
+
+
+/// Formats a cycle of variables as a path that leads back to the first
+/// variable, including the position each variable is assigned at.
+fn format_cycle(cycle: &[(String, NodePosition)]) -> String {
+    let mut path = String::new();
+    for (name, position) in cycle {
+        path.push_str(&format!(
+            "'{}' ({}:{}) -> ",
+            name, position.line, position.col
+        ));
+    }
+
+    if let Some((name, _)) = cycle.first() {
+        path.push_str(&format!("'{}'", name));
+    }
+
+    path
 }
@@ -12,42 +12,288 @@ mod peg {
 }
 
 use super::ast::*;
+use super::diagnostics::{Diagnostic, Severity};
 use peg::*;
+use pest::error::{Error, ErrorVariant, LineColLocation};
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
-use std::error::Error;
 
 
-type Result<T> = std::result::Result<T, Box<dyn Error>>;
+type Result<T> = std::result::Result<T, Diagnostic>;
 
 
 /// Parse the given source code into an abstract syntax tree of grammar nodes.
+///
+/// All node positions are assigned to the file id 0. Use [`parse_file`] when
+/// parsing multiple source files.
 pub fn parse(source: &str) -> Result<ContextNode> {
-    let mut pairs = VertexLangParser::parse(Rule::Program, source)?;
-    let context = parse_context(pairs.next().unwrap());
-    Ok(context)
+    parse_file(source, 0)
 }
 
 
-fn parse_context(pair: Pair<Rule>) -> ContextNode {
+/// Parse the given source code into an abstract syntax tree of grammar nodes,
+/// where all node positions are assigned to the given file id.
+pub fn parse_file(source: &str, file: usize) -> Result<ContextNode> {
+    let mut pairs = match VertexLangParser::parse(Rule::Program, source) {
+        Ok(pairs) => pairs,
+        Err(e) => return Err(syntax_error(e, file)),
+    };
+
+    let mut diagnostics = vec![];
+    let context = parse_context(pairs.next().unwrap(), file, &mut diagnostics);
+
+    match diagnostics.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(context),
+    }
+}
+
+
+/// Parse the given source code into an abstract syntax tree of grammar nodes,
+/// recovering from any syntax errors that are found, where all node positions
+/// are assigned to the given file id.
+///
+/// Source code that cannot be parsed is skipped up to the end of the line or
+/// the next closing brace, and missing closing braces at the end of the file
+/// are ignored. Assignments that cannot be parsed are kept within their
+/// function as error expressions, so that the names of their variables are
+/// still known. A diagnostic is returned for each syntax error.
+///
+/// This is intended for editor tooling, where a partially written file should
+/// still produce as much of the syntax tree as possible.
+pub fn parse_file_recovering(source: &str, file: usize) -> (ContextNode, Vec<Diagnostic>) {
+    let mut diagnostics = vec![];
+    let error = match VertexLangParser::parse(Rule::Program, source) {
+        Ok(mut pairs) => {
+            let context = parse_context(pairs.next().unwrap(), file, &mut diagnostics);
+            return (context, diagnostics);
+        },
+        Err(e) => syntax_error(e, file),
+    };
+
+    // The recovering grammar is written to accept any input, but an empty
+    // syntax tree is still returned if it does not.
+    let pair = match VertexLangParser::parse(Rule::RecoverProgram, source) {
+        Ok(mut pairs) => pairs.next().unwrap(),
+        Err(_) => {
+            let context = ContextNode {
+                imports: vec![],
+                modules: vec![],
+            };
+            return (context, vec![error]);
+        },
+    };
+
+    let mut regions = vec![];
+    collect_invalid_regions(pair.clone(), file, &mut regions);
+
+    // The error found by the strict grammar is the most precise description of
+    // the first syntax error, so it replaces the region it is found within.
+    let mut error = Some(error);
+    for (position, text) in regions {
+        let covers = error.as_ref().and_then(|e| e.get_position()).is_some_and(|strict| {
+            strict.line == position.line
+                || (strict.col == 1 && strict.line == position.end_line + 1)
+        });
+
+        if covers {
+            diagnostics.push(error.take().unwrap());
+        } else {
+            let message = format!("Syntax error, unexpected '{}'", text);
+            let position = Some(position);
+            diagnostics.push(Diagnostic::new(Severity::Error, Some("E0001"), message, position));
+        }
+    }
+
+    if let Some(error) = error {
+        diagnostics.push(error);
+    }
+
+    let context = parse_context(pair, file, &mut diagnostics);
+    diagnostics.sort_by_key(|d| d.get_position().map(|p| (p.line, p.col)));
+    (context, diagnostics)
+}
+
+
+/// Collects the position and source code of every region that was skipped by
+/// the recovering grammar, in the order they appear.
+fn collect_invalid_regions(
+    pair: Pair<Rule>, file: usize, regions: &mut Vec<(NodePosition, String)>,
+) {
+    match pair.as_rule() {
+        Rule::Invalid | Rule::UnmatchedBrace | Rule::InvalidAssignment => {
+            regions.push((node_position(&pair, file), pair.as_str().trim().to_string()));
+        },
+        _ => {
+            for inner in pair.into_inner() {
+                collect_invalid_regions(inner, file, regions);
+            }
+        },
+    }
+}
+
+
+/// Converts a Pest parsing error into a diagnostic within the given file.
+fn syntax_error(error: Error<Rule>, file: usize) -> Diagnostic {
+    let ((line, col), (end_line, end_col)) = match error.line_col {
+        LineColLocation::Pos((line, col)) => ((line, col), (line, col + 1)),
+        LineColLocation::Span(start, end) => (start, end),
+    };
+
+    let position = NodePosition {
+        file,
+        line,
+        col,
+        end_line,
+        end_col,
+    };
+
+    let message = match &error.variant {
+        ErrorVariant::ParsingError {
+            positives,
+            negatives,
+        } => {
+            match (describe_rules(negatives), describe_rules(positives)) {
+                (Some(unexpected), Some(expected)) => {
+                    format!("unexpected {}; expected {}", unexpected, expected)
+                },
+                (Some(unexpected), None) => format!("unexpected {}", unexpected),
+                (None, Some(expected)) => format!("expected {}", expected),
+                (None, None) => String::from("unknown parsing error"),
+            }
+        },
+        ErrorVariant::CustomError { message } => message.clone(),
+    };
+
+    let message = format!("Syntax error, {}", message);
+    Diagnostic::new(Severity::Error, Some("E0001"), message, Some(position))
+}
+
+
+/// Lists the user-facing descriptions of the given grammar rules, such as
+/// "expression or end of line". Rules with the same description are only
+/// listed once. If there are no rules, then None is returned.
+fn describe_rules(rules: &[Rule]) -> Option<String> {
+    let mut descriptions: Vec<String> = vec![];
+    for rule in rules {
+        let description = describe_rule(*rule);
+        if !descriptions.contains(&description) {
+            descriptions.push(description);
+        }
+    }
+
+    match descriptions.as_slice() {
+        [] => None,
+        [single] => Some(single.clone()),
+        [first, second] => Some(format!("{} or {}", first, second)),
+        [rest @ .., last] => Some(format!("{}, or {}", rest.join(", "), last)),
+    }
+}
+
+
+/// Gets a description of the given grammar rule that can be shown to users,
+/// in place of the name of the rule within the grammar.
+fn describe_rule(rule: Rule) -> String {
+    let description = match rule {
+        Rule::Expr
+        | Rule::Conditional
+        | Rule::IfKeyword
+        | Rule::Disjunction
+        | Rule::Conjunction
+        | Rule::Negation
+        | Rule::NotOp
+        | Rule::Comparison
+        | Rule::Sum
+        | Rule::Product
+        | Rule::Unary
+        | Rule::NegateOp
+        | Rule::Term
+        | Rule::ExprList => "expression",
+        Rule::OrOp | Rule::AndOp | Rule::CompareOp | Rule::AddOp | Rule::MulOp => "operator",
+        Rule::ThenKeyword => "'then'",
+        Rule::ElseKeyword => "'else'",
+        Rule::ExportKeyword => "'export'",
+        Rule::SerialKeyword => "'serial'",
+        Rule::ExternKeyword => "'extern'",
+        Rule::Identifier | Rule::QualifiedName | Rule::InnerVar => "name",
+        Rule::Int => "integer",
+        Rule::Float | Rule::ENotation => "float",
+        Rule::Bool => "boolean",
+        Rule::DataType => "data type",
+        Rule::Arg | Rule::ArgList => "argument",
+        Rule::Params => "'params'",
+        Rule::Return => "'return'",
+        Rule::FieldInit => "field",
+        Rule::EndLine => "end of line",
+        Rule::EOI => "end of file",
+        _ => {
+            // Other rules are named after what they describe, such as
+            // `ListLiteral`, so their names are split into lowercase words.
+            let name = format!("{:?}", rule);
+            let mut description = String::new();
+            for c in name.chars() {
+                if c.is_uppercase() && !description.is_empty() {
+                    description.push(' ');
+                }
+                description.extend(c.to_lowercase());
+            }
+            return description;
+        },
+    };
+
+    description.to_string()
+}
+
+
+/// Creates a diagnostic for a pair that is not expected at its position within
+/// the syntax tree.
+fn unexpected_token(pair: &Pair<Rule>, file: usize) -> Diagnostic {
+    let message = format!("Syntax error, unexpected '{}'", pair.as_str().trim());
+    let position = Some(node_position(pair, file));
+    Diagnostic::new(Severity::Error, Some("E0001"), message, position)
+}
+
+
+/// Parses the root of a syntax tree. Parts of the tree that cannot be converted
+/// into grammar nodes are skipped, and a diagnostic is recorded for each.
+fn parse_context(pair: Pair<Rule>, file: usize, diagnostics: &mut Vec<Diagnostic>) -> ContextNode {
+    let mut imports = vec![];
     let mut modules = vec![];
 
     for pair in pair.into_inner() {
         match pair.as_rule() {
-            Rule::Module => modules.push(parse_module(pair)),
-            Rule::EOI => {},
-            _ => panic!("Unexpected token: {}", pair),
+            Rule::Import => imports.push(parse_import(pair, file)),
+            Rule::Module | Rule::RecoverModule => {
+                modules.push(parse_module(pair, file, diagnostics))
+            },
+            Rule::Invalid | Rule::UnmatchedBrace | Rule::EOI => {},
+            _ => diagnostics.push(unexpected_token(&pair, file)),
         };
     }
 
     ContextNode {
+        imports,
         modules,
     }
 }
 
 
-fn parse_module(pair: Pair<Rule>) -> ModuleNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_import(pair: Pair<Rule>, file: usize) -> ImportNode {
+    let position = node_position(&pair, file);
+    let mut pairs = pair.into_inner();
+
+    let path = get_rule_consume(&mut pairs, Rule::String).unwrap();
+    let path = parse_string(path, file).value;
+
+    ImportNode {
+        position,
+        path,
+    }
+}
+
+
+fn parse_module(pair: Pair<Rule>, file: usize, diagnostics: &mut Vec<Diagnostic>) -> ModuleNode {
+    let position = node_position(&pair, file);
     let mut pairs = pair.into_inner();
 
     let name = get_rule_consume(&mut pairs, Rule::Identifier).unwrap();
@@ -59,21 +305,25 @@ fn parse_module(pair: Pair<Rule>) -> ModuleNode {
     let mut functions = vec![];
     let mut structs = vec![];
 
-    let module_body = get_rule_consume(&mut pairs, Rule::ModuleBody).unwrap();
-    for pair in module_body.into_inner() {
+    let module_body = get_body_consume(&mut pairs, Rule::ModuleBody, Rule::RecoverModuleBody);
+    for pair in module_body.unwrap().into_inner() {
         match pair.as_rule() {
-            Rule::Module => modules.push(parse_module(pair)),
-            Rule::Function => functions.push(parse_function(pair)),
-            Rule::Struct => structs.push(parse_struct(pair)),
-            _ => panic!("Unexpected token: {}", pair),
+            Rule::Module | Rule::RecoverModule => {
+                modules.push(parse_module(pair, file, diagnostics))
+            },
+            Rule::Function | Rule::RecoverFunction => {
+                functions.push(parse_function(pair, file, diagnostics))
+            },
+            Rule::Struct | Rule::RecoverStruct => {
+                structs.push(parse_struct(pair, file, diagnostics))
+            },
+            Rule::Invalid => {},
+            _ => diagnostics.push(unexpected_token(&pair, file)),
         };
     }
 
     ModuleNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+        position,
         name,
         export,
         modules,
@@ -83,43 +333,86 @@ fn parse_module(pair: Pair<Rule>) -> ModuleNode {
 }
 
 
-fn parse_function(pair: Pair<Rule>) -> FunctionNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_function(
+    pair: Pair<Rule>, file: usize, diagnostics: &mut Vec<Diagnostic>,
+) -> FunctionNode {
+    let position = node_position(&pair, file);
     let mut pairs = pair.into_inner();
 
     let name = get_rule_consume(&mut pairs, Rule::Identifier).unwrap();
     let name = name.as_str().to_string();
 
     let export = is_rule_consume(&mut pairs, Rule::ExportKeyword);
-    let serial = is_rule_consume(&mut pairs, Rule::SerialKeyword);
+    parse_function_definition(pairs, name, export, position, diagnostics)
+}
+
+
+/// Parses an anonymous function expression. Anonymous functions are named
+/// after their position within the source code, which is not a valid
+/// identifier, and so cannot collide with any user-defined names.
+///
+/// If any part of the function body cannot be parsed, then the first error
+/// found is returned.
+fn parse_lambda(pair: Pair<Rule>, file: usize) -> Result<FunctionNode> {
+    let position = node_position(&pair, file);
+    let name = format!("lambda@{}:{}", position.line, position.col);
+
+    let mut diagnostics = vec![];
+    let lambda =
+        parse_function_definition(pair.into_inner(), name, false, position, &mut diagnostics);
 
-    let params = get_rule_consume(&mut pairs, Rule::Params).unwrap();
-    let params = params.into_inner().next().unwrap();
-    let params = parse_arg_list(params);
+    match diagnostics.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(lambda),
+    }
+}
+
+
+/// Parses the serial keyword, parameters, returns and body of a function.
+///
+/// When recovering from syntax errors, the parameters or returns may be
+/// missing, in which case they are left empty. Assignments that cannot be
+/// parsed are kept as error expressions, and a diagnostic is recorded for each.
+fn parse_function_definition(
+    mut pairs: Pairs<Rule>, name: String, export: bool, position: NodePosition,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> FunctionNode {
+    let file = position.file;
+    let serial = is_rule_consume(&mut pairs, Rule::SerialKeyword);
 
-    let returns = get_rule_consume(&mut pairs, Rule::Return).unwrap();
-    let returns = returns.into_inner().next().unwrap();
-    let returns = parse_arg_list(returns);
+    let params = parse_function_header(&mut pairs, Rule::Params, &position, diagnostics);
+    let returns = parse_function_header(&mut pairs, Rule::Return, &position, diagnostics);
 
     let mut functions = vec![];
     let mut structs = vec![];
     let mut assignments = vec![];
 
-    let function_body = get_rule_consume(&mut pairs, Rule::FunctionBody).unwrap();
-    for pair in function_body.into_inner() {
+    let function_body =
+        get_body_consume(&mut pairs, Rule::FunctionBody, Rule::RecoverFunctionBody);
+    for pair in function_body.unwrap().into_inner() {
         match pair.as_rule() {
-            Rule::Function => functions.push(parse_function(pair)),
-            Rule::Struct => structs.push(parse_struct(pair)),
-            Rule::Assignment => assignments.push(parse_assignment(pair)),
-            _ => panic!("Unexpected token: {}", pair),
+            Rule::Function | Rule::RecoverFunction => {
+                functions.push(parse_function(pair, file, diagnostics))
+            },
+            Rule::Struct | Rule::RecoverStruct => {
+                structs.push(parse_struct(pair, file, diagnostics))
+            },
+            Rule::Assignment => {
+                match parse_assignment(pair.clone(), file) {
+                    Ok(assignment) => assignments.push(assignment),
+                    Err(error) => {
+                        diagnostics.push(error);
+                        assignments.push(parse_invalid_assignment(pair, file));
+                    },
+                }
+            },
+            Rule::InvalidAssignment => assignments.push(parse_invalid_assignment(pair, file)),
+            _ => diagnostics.push(unexpected_token(&pair, file)),
         }
     }
 
     FunctionNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+        position,
         name,
         export,
         serial,
@@ -132,8 +425,32 @@ fn parse_function(pair: Pair<Rule>) -> FunctionNode {
 }
 
 
-fn parse_struct(pair: Pair<Rule>) -> StructNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+/// Parses the argument list of the given parameters or returns rule of a
+/// function. If the rule is missing, or could not be parsed, then an empty
+/// argument list is returned, positioned at the start of the function.
+fn parse_function_header(
+    pairs: &mut Pairs<Rule>, rule: Rule, position: &NodePosition,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> ArgumentListNode {
+    if let Some(header) = get_rule_consume(pairs, rule) {
+        let arg_list = header.into_inner().next().unwrap();
+        return parse_arg_list(arg_list, position.file, diagnostics);
+    }
+
+    is_rule_consume(pairs, Rule::Invalid);
+    ArgumentListNode {
+        position:  NodePosition {
+            end_line: position.line,
+            end_col: position.col,
+            ..position.clone()
+        },
+        arguments: vec![],
+    }
+}
+
+
+fn parse_struct(pair: Pair<Rule>, file: usize, diagnostics: &mut Vec<Diagnostic>) -> StructNode {
+    let position = node_position(&pair, file);
     let mut pairs = pair.into_inner();
 
     let name = get_rule_consume(&mut pairs, Rule::Identifier).unwrap();
@@ -141,14 +458,11 @@ fn parse_struct(pair: Pair<Rule>) -> StructNode {
 
     let export = is_rule_consume(&mut pairs, Rule::ExportKeyword);
 
-    let fields = get_rule_consume(&mut pairs, Rule::StructBody).unwrap();
-    let fields = parse_arg_list(fields);
+    let fields = get_body_consume(&mut pairs, Rule::StructBody, Rule::RecoverStructBody);
+    let fields = parse_arg_list(fields.unwrap(), file, diagnostics);
 
     StructNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+        position,
         name,
         export,
         fields,
@@ -156,29 +470,29 @@ fn parse_struct(pair: Pair<Rule>) -> StructNode {
 }
 
 
-fn parse_arg_list(pair: Pair<Rule>) -> ArgumentListNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_arg_list(
+    pair: Pair<Rule>, file: usize, diagnostics: &mut Vec<Diagnostic>,
+) -> ArgumentListNode {
+    let position = node_position(&pair, file);
     let mut arguments = vec![];
 
     for pair in pair.into_inner() {
         match pair.as_rule() {
-            Rule::Arg => arguments.push(parse_argument(pair)),
-            _ => panic!("Unexpected token: {}", pair),
+            Rule::Arg => arguments.push(parse_argument(pair, file)),
+            Rule::Invalid => {},
+            _ => diagnostics.push(unexpected_token(&pair, file)),
         };
     }
 
     ArgumentListNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+        position,
         arguments,
     }
 }
 
 
-fn parse_argument(pair: Pair<Rule>) -> ArgumentNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_argument(pair: Pair<Rule>, file: usize) -> ArgumentNode {
+    let position = node_position(&pair, file);
     let mut pairs = pair.into_inner();
 
     let name = get_rule_consume(&mut pairs, Rule::Identifier).unwrap();
@@ -188,172 +502,386 @@ fn parse_argument(pair: Pair<Rule>) -> ArgumentNode {
     let dtype = dtype.as_str().to_string();
 
     ArgumentNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+        position,
         name,
         dtype,
     }
 }
 
 
-fn parse_assignment(pair: Pair<Rule>) -> AssignmentNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_assignment(pair: Pair<Rule>, file: usize) -> Result<AssignmentNode> {
+    let position = node_position(&pair, file);
     let mut pairs = pair.into_inner();
 
-    let variable;
-    if let Some(variable_pair) = get_rule_consume(&mut pairs, Rule::Identifier) {
-        variable = Some(parse_variable(variable_pair));
-    } else {
-        variable = None;
+    let mut variables = vec![];
+    while let Some(variable_pair) = get_rule_consume(&mut pairs, Rule::Identifier) {
+        variables.push(parse_variable(variable_pair, file));
     }
 
     let expression = get_rule_consume(&mut pairs, Rule::Expr).unwrap();
-    let expression = parse_expression(expression);
+    let expression = parse_expression(expression, file)?;
 
-    AssignmentNode {
-        position: NodePosition {
-            line,
-            col,
-        },
-        variable,
+    Ok(AssignmentNode {
+        position,
+        variables,
         expression,
+    })
+}
+
+
+/// Parses an assignment that could not be parsed by the strict grammar, or
+/// whose expression could not be converted into grammar nodes. The variables
+/// of the assignment are kept if they could be parsed, while the expression is
+/// replaced with an error expression.
+fn parse_invalid_assignment(pair: Pair<Rule>, file: usize) -> AssignmentNode {
+    let position = node_position(&pair, file);
+    let mut pairs = pair.into_inner();
+
+    let mut variables = vec![];
+    while let Some(variable_pair) = get_rule_consume(&mut pairs, Rule::Identifier) {
+        variables.push(parse_variable(variable_pair, file));
+    }
+
+    let invalid = get_rule_consume(&mut pairs, Rule::Invalid);
+    let error = match invalid.or_else(|| get_rule_consume(&mut pairs, Rule::Expr)) {
+        Some(invalid) => {
+            ErrorNode {
+                position: node_position(&invalid, file),
+                source:   invalid.as_str().trim_end().to_string(),
+            }
+        },
+        None => {
+            ErrorNode {
+                position: NodePosition {
+                    line: position.end_line,
+                    col: position.end_col,
+                    ..position.clone()
+                },
+                source:   String::new(),
+            }
+        },
+    };
+
+    AssignmentNode {
+        position,
+        variables,
+        expression: ExpressionNode::Error(error),
     }
 }
 
 
-fn parse_function_call(pair: Pair<Rule>) -> FunctionCallNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_function_call(pair: Pair<Rule>, file: usize) -> Result<FunctionCallNode> {
+    let position = node_position(&pair, file);
     let mut pairs = pair.into_inner();
 
     let serial = is_rule_consume(&mut pairs, Rule::SerialKeyword);
     let external = is_rule_consume(&mut pairs, Rule::ExternKeyword);
 
-    let name = get_rule_consume(&mut pairs, Rule::Identifier).unwrap();
+    let name = get_rule_consume(&mut pairs, Rule::QualifiedName).unwrap();
     let name = name.as_str().to_string();
 
-    let expr_list = get_rule_consume(&mut pairs, Rule::ExprList).unwrap();
-    let expr_list = parse_expression_list(expr_list);
-
-    FunctionCallNode {
-        position: NodePosition {
-            line,
-            col,
+    let expr_list = match get_rule_consume(&mut pairs, Rule::ExprList) {
+        Some(expr_list) => parse_expression_list(expr_list, file)?,
+        None => {
+            ExpressionListNode {
+                position:    position.clone(),
+                expressions: vec![],
+            }
         },
+    };
+
+    Ok(FunctionCallNode {
+        position,
         function_name: name,
         serial,
         external,
         arguments: expr_list,
+    })
+}
+
+
+fn parse_extern_func_ref(pair: Pair<Rule>, file: usize) -> ExternalFunctionNode {
+    let position = node_position(&pair, file);
+    let name = pair.into_inner().find(|p| p.as_rule() == Rule::QualifiedName).unwrap();
+
+    ExternalFunctionNode {
+        position,
+        function_name: name.as_str().to_string(),
     }
 }
 
 
-fn parse_expression_list(pair: Pair<Rule>) -> ExpressionListNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_struct_init(pair: Pair<Rule>, file: usize) -> Result<StructInitNode> {
+    let position = node_position(&pair, file);
+    let mut pairs = pair.into_inner();
+
+    let name = get_rule_consume(&mut pairs, Rule::QualifiedName).unwrap();
+    let name = name.as_str().to_string();
+
+    let mut fields = vec![];
+    for pair in pairs {
+        if pair.as_rule() == Rule::FieldInit {
+            fields.push(parse_field_init(pair, file)?);
+        } else {
+            return Err(unexpected_token(&pair, file));
+        }
+    }
+
+    Ok(StructInitNode {
+        position,
+        struct_name: name,
+        fields,
+    })
+}
+
+
+fn parse_field_init(pair: Pair<Rule>, file: usize) -> Result<FieldInitNode> {
+    let position = node_position(&pair, file);
+    let mut pairs = pair.into_inner();
+
+    let name = get_rule_consume(&mut pairs, Rule::Identifier).unwrap();
+    let name = name.as_str().to_string();
+    let expression = parse_expression(pairs.next().unwrap(), file)?;
+
+    Ok(FieldInitNode {
+        position,
+        name,
+        expression,
+    })
+}
+
+
+fn parse_expression_list(pair: Pair<Rule>, file: usize) -> Result<ExpressionListNode> {
+    let position = node_position(&pair, file);
 
     let mut expressions = vec![];
     for pair in pair.into_inner() {
-        expressions.push(parse_expression(pair));
+        expressions.push(parse_expression(pair, file)?);
     }
 
-    ExpressionListNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+    Ok(ExpressionListNode {
+        position,
         expressions,
+    })
+}
+
+
+fn parse_expression(pair: Pair<Rule>, file: usize) -> Result<ExpressionNode> {
+    Ok(match pair.as_rule() {
+        Rule::Expr | Rule::Term => parse_expression(pair.into_inner().next().unwrap(), file)?,
+        Rule::Disjunction | Rule::Conjunction | Rule::Comparison | Rule::Sum | Rule::Product => {
+            parse_binary_operation(pair, file)?
+        },
+        Rule::Negation | Rule::Unary => parse_unary_operation(pair, file)?,
+        Rule::Conditional => ExpressionNode::Conditional(parse_conditional(pair, file)?),
+        Rule::Int => ExpressionNode::IntLiteral(parse_integer(pair, file)?),
+        Rule::Float | Rule::ENotation => ExpressionNode::FloatLiteral(parse_float(pair, file)?),
+        Rule::String => ExpressionNode::StringLiteral(parse_string(pair, file)),
+        Rule::Bool => ExpressionNode::BoolLiteral(parse_bool(pair, file)),
+        Rule::ListLiteral => ExpressionNode::ListLiteral(parse_list_literal(pair, file)?),
+        Rule::TupleLiteral => ExpressionNode::TupleLiteral(parse_tuple_literal(pair, file)?),
+        Rule::DictionaryLiteral => {
+            ExpressionNode::DictionaryLiteral(parse_dictionary_literal(pair, file)?)
+        },
+        Rule::FuncCall => ExpressionNode::FunctionCall(parse_function_call(pair, file)?),
+        Rule::Identifier => ExpressionNode::Variable(parse_variable(pair, file)),
+        Rule::InnerVar => ExpressionNode::InnerVariable(parse_inner_variable(pair, file)?),
+        Rule::StructInit => ExpressionNode::StructInit(parse_struct_init(pair, file)?),
+        Rule::Lambda => ExpressionNode::Lambda(parse_lambda(pair, file)?),
+        Rule::ExternFuncRef => ExpressionNode::ExternalFunction(parse_extern_func_ref(pair, file)),
+        _ => return Err(unexpected_token(&pair, file)),
+    })
+}
+
+
+/// Parses a chain of left-associative binary operations of the same precedence
+/// level. If there is only a single operand, then that operand is returned
+/// directly.
+fn parse_binary_operation(pair: Pair<Rule>, file: usize) -> Result<ExpressionNode> {
+    let mut pairs = pair.into_inner();
+    let mut lhs = parse_expression(pairs.next().unwrap(), file)?;
+
+    while let Some(op) = pairs.next() {
+        let position = node_position(&op, file);
+        let operator = match Operator::from_symbol(op.as_str()) {
+            Some(operator) => operator,
+            None => return Err(unexpected_token(&op, file)),
+        };
+        let rhs = parse_expression(pairs.next().unwrap(), file)?;
+
+        lhs = ExpressionNode::BinaryOperation(BinaryOperationNode {
+            position,
+            operator,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        });
     }
+
+    Ok(lhs)
 }
 
 
-fn parse_expression(pair: Pair<Rule>) -> ExpressionNode {
-    match pair.as_rule() {
-        Rule::Expr => parse_expression(pair.into_inner().next().unwrap()),
-        Rule::Int => ExpressionNode::IntLiteral(parse_integer(pair)),
-        Rule::Float | Rule::ENotation => ExpressionNode::FloatLiteral(parse_float(pair)),
-        Rule::String => ExpressionNode::StringLiteral(parse_string(pair)),
-        Rule::Bool => ExpressionNode::BoolLiteral(parse_bool(pair)),
-        Rule::FuncCall => ExpressionNode::FunctionCall(parse_function_call(pair)),
-        Rule::Identifier => ExpressionNode::Variable(parse_variable(pair)),
-        Rule::InnerVar => ExpressionNode::InnerVariable(parse_inner_variable(pair)),
-        _ => panic!("Unexpected token: {}", pair),
+/// Parses a chain of prefix unary operations applied to a single operand. If
+/// there are no operators, then the operand is returned directly.
+fn parse_unary_operation(pair: Pair<Rule>, file: usize) -> Result<ExpressionNode> {
+    let mut operators = vec![];
+    let mut operand = None;
+
+    for pair in pair.into_inner() {
+        let position = node_position(&pair, file);
+
+        match pair.as_rule() {
+            Rule::NotOp => operators.push((Operator::Not, position)),
+            Rule::NegateOp => operators.push((Operator::Negate, position)),
+            _ => operand = Some(parse_expression(pair, file)?),
+        }
     }
+
+    let mut operand = operand.unwrap();
+    for (operator, position) in operators.into_iter().rev() {
+        operand = ExpressionNode::UnaryOperation(UnaryOperationNode {
+            position,
+            operator,
+            operand: Box::new(operand),
+        });
+    }
+
+    Ok(operand)
 }
 
 
-fn parse_integer(pair: Pair<Rule>) -> IntLiteralNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
-    let value = pair.as_str().parse::<i64>().unwrap();
+fn parse_conditional(pair: Pair<Rule>, file: usize) -> Result<ConditionalNode> {
+    let position = node_position(&pair, file);
+    let mut pairs = pair.into_inner();
 
-    IntLiteralNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+    get_rule_consume(&mut pairs, Rule::IfKeyword).unwrap();
+    let condition = parse_expression(pairs.next().unwrap(), file)?;
+    get_rule_consume(&mut pairs, Rule::ThenKeyword).unwrap();
+    let then_branch = parse_expression(pairs.next().unwrap(), file)?;
+    get_rule_consume(&mut pairs, Rule::ElseKeyword).unwrap();
+    let else_branch = parse_expression(pairs.next().unwrap(), file)?;
+
+    Ok(ConditionalNode {
+        position,
+        condition: Box::new(condition),
+        then_branch: Box::new(then_branch),
+        else_branch: Box::new(else_branch),
+    })
+}
+
+
+/// Parses an integer literal. Literals that do not fit within a 64-bit signed
+/// integer cannot be represented, and are reported as an error.
+fn parse_integer(pair: Pair<Rule>, file: usize) -> Result<IntLiteralNode> {
+    let position = node_position(&pair, file);
+    let value = pair.as_str().parse::<i64>().map_err(|_| invalid_literal(&pair, file, "Int"))?;
+
+    Ok(IntLiteralNode {
+        position,
         value,
-    }
+    })
 }
 
 
-fn parse_float(pair: Pair<Rule>) -> FloatLiteralNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
-    let value = pair.as_str().parse::<f64>().unwrap();
+fn parse_float(pair: Pair<Rule>, file: usize) -> Result<FloatLiteralNode> {
+    let position = node_position(&pair, file);
+    let value = pair.as_str().parse::<f64>().map_err(|_| invalid_literal(&pair, file, "Float"))?;
 
-    FloatLiteralNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+    Ok(FloatLiteralNode {
+        position,
         value,
-    }
+    })
 }
 
 
-fn parse_string(pair: Pair<Rule>) -> StringLiteralNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+/// Creates a diagnostic for a literal that cannot be represented by the given
+/// data type.
+fn invalid_literal(pair: &Pair<Rule>, file: usize, dtype: &str) -> Diagnostic {
+    let message = format!("Literal '{}' is out of range for type '{}'", pair.as_str(), dtype);
+    let position = Some(node_position(pair, file));
+    Diagnostic::new(Severity::Error, Some("E0005"), message, position)
+}
+
+
+fn parse_string(pair: Pair<Rule>, file: usize) -> StringLiteralNode {
+    let position = node_position(&pair, file);
     let mut pairs = pair.into_inner();
     let inner = get_rule_consume(&mut pairs, Rule::StringInterior).unwrap();
 
     StringLiteralNode {
-        position: NodePosition {
-            line,
-            col,
-        },
-        value:    inner.as_str().to_string(),
+        position,
+        value: inner.as_str().to_string(),
     }
 }
 
 
-fn parse_bool(pair: Pair<Rule>) -> BoolLiteralNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_bool(pair: Pair<Rule>, file: usize) -> BoolLiteralNode {
+    let position = node_position(&pair, file);
     let value = pair.as_str().parse::<bool>().unwrap();
 
     BoolLiteralNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+        position,
         value,
     }
 }
 
 
-fn parse_variable(pair: Pair<Rule>) -> VariableNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_list_literal(pair: Pair<Rule>, file: usize) -> Result<ListLiteralNode> {
+    let position = node_position(&pair, file);
+    let elements = pair.into_inner().map(|pair| parse_expression(pair, file));
+
+    Ok(ListLiteralNode {
+        position,
+        elements: elements.collect::<Result<_>>()?,
+    })
+}
+
+
+fn parse_tuple_literal(pair: Pair<Rule>, file: usize) -> Result<TupleLiteralNode> {
+    let position = node_position(&pair, file);
+    let elements = pair.into_inner().map(|pair| parse_expression(pair, file));
+
+    Ok(TupleLiteralNode {
+        position,
+        elements: elements.collect::<Result<_>>()?,
+    })
+}
+
+
+fn parse_dictionary_literal(pair: Pair<Rule>, file: usize) -> Result<DictionaryLiteralNode> {
+    let position = node_position(&pair, file);
+
+    let mut entries = vec![];
+    for pair in pair.into_inner() {
+        if pair.as_rule() == Rule::DictionaryEntry {
+            let mut pairs = pair.into_inner();
+            let key = parse_expression(pairs.next().unwrap(), file)?;
+            let value = parse_expression(pairs.next().unwrap(), file)?;
+            entries.push((key, value));
+        } else {
+            return Err(unexpected_token(&pair, file));
+        }
+    }
+
+    Ok(DictionaryLiteralNode {
+        position,
+        entries,
+    })
+}
+
+
+fn parse_variable(pair: Pair<Rule>, file: usize) -> VariableNode {
+    let position = node_position(&pair, file);
 
     VariableNode {
-        position: NodePosition {
-            line,
-            col,
-        },
-        name:     pair.as_str().to_string(),
+        position,
+        name: pair.as_str().to_string(),
     }
 }
 
 
-fn parse_inner_variable(pair: Pair<Rule>) -> InnerVariableNode {
-    let (line, col) = pair.as_span().start_pos().line_col();
+fn parse_inner_variable(pair: Pair<Rule>, file: usize) -> Result<InnerVariableNode> {
+    let position = node_position(&pair, file);
 
     let mut path = vec![];
 
@@ -361,16 +889,39 @@ fn parse_inner_variable(pair: Pair<Rule>) -> InnerVariableNode {
         if pair.as_rule() == Rule::Identifier {
             path.push(pair.as_str().to_string());
         } else {
-            panic!("Unexpected token: {}", pair);
+            return Err(unexpected_token(&pair, file));
         }
     }
 
-    InnerVariableNode {
-        position: NodePosition {
-            line,
-            col,
-        },
+    Ok(InnerVariableNode {
+        position,
         path,
+    })
+}
+
+
+/// Gets the range of source code that the given pair covers within the given
+/// file, excluding any trailing whitespace.
+fn node_position(pair: &Pair<Rule>, file: usize) -> NodePosition {
+    let (line, col) = pair.as_span().start_pos().line_col();
+
+    let mut end_line = line;
+    let mut end_col = col;
+    for c in pair.as_str().trim_end().chars() {
+        if c == '\n' {
+            end_line += 1;
+            end_col = 1;
+        } else {
+            end_col += 1;
+        }
+    }
+
+    NodePosition {
+        file,
+        line,
+        col,
+        end_line,
+        end_col,
     }
 }
 
@@ -379,15 +930,11 @@ fn parse_inner_variable(pair: Pair<Rule>) -> InnerVariableNode {
 /// type. If it is, then this function will automatically consume that rule.
 fn is_rule_consume(pair: &mut Pairs<Rule>, rule: Rule) -> bool {
     match pair.peek() {
-        Some(p) => {
-            if p.as_rule() == rule {
-                pair.next(); // Skip since we just checked it.
-                true
-            } else {
-                false
-            }
+        Some(p) if p.as_rule() == rule => {
+            pair.next(); // Skip since we just checked it.
+            true
         },
-        None => false,
+        _ => false,
     }
 }
 
@@ -395,21 +942,27 @@ fn is_rule_consume(pair: &mut Pairs<Rule>, rule: Rule) -> bool {
 /// Checks if the next element within the pairs iterator is of the given rule
 /// type. If it is, then that rule is returned. Otherwise, this function returns
 /// None.
-fn get_rule_consume<'a>(pair: &'a mut Pairs<Rule>, rule: Rule) -> Option<Pair<'a, Rule>> {
+fn get_rule_consume<'a>(pair: &mut Pairs<'a, Rule>, rule: Rule) -> Option<Pair<'a, Rule>> {
     match pair.peek() {
-        Some(p) => {
-            if p.as_rule() == rule {
-                pair.next(); // Skip since we just checked it.
-                Some(p)
-            } else {
-                None
-            }
+        Some(p) if p.as_rule() == rule => {
+            pair.next(); // Skip since we just checked it.
+            Some(p)
         },
-        None => None,
+        _ => None,
     }
 }
 
 
+/// Consumes the next element within the pairs iterator if it is either the
+/// given body rule of the strict grammar, or the respective body rule of the
+/// recovering grammar.
+fn get_body_consume<'a>(
+    pair: &mut Pairs<'a, Rule>, strict: Rule, recovering: Rule,
+) -> Option<Pair<'a, Rule>> {
+    get_rule_consume(pair, strict).or_else(|| get_rule_consume(pair, recovering))
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -436,33 +989,46 @@ mod tests {
         }
 
         assert_eq!(ast.unwrap(), ContextNode {
+            imports: vec![],
             modules: vec![ModuleNode {
                 position:  NodePosition {
-                    line: 1,
-                    col:  1,
+                    file:     0,
+                    line:     1,
+                    col:      1,
+                    end_line: 8,
+                    end_col:  2,
                 },
                 name:      String::from("HelloWorld"),
                 export:    false,
                 modules:   vec![],
                 functions: vec![FunctionNode {
                     position:    NodePosition {
-                        line: 2,
-                        col:  5,
+                        file:     0,
+                        line:     2,
+                        col:      5,
+                        end_line: 7,
+                        end_col:  6,
                     },
                     name:        String::from("Main"),
                     export:      true,
                     serial:      true,
                     params:      ArgumentListNode {
                         position:  NodePosition {
-                            line: 3,
-                            col:  19,
+                            file:     0,
+                            line:     3,
+                            col:      19,
+                            end_line: 3,
+                            end_col:  19,
                         },
                         arguments: vec![],
                     },
                     returns:     ArgumentListNode {
                         position:  NodePosition {
-                            line: 4,
-                            col:  19,
+                            file:     0,
+                            line:     4,
+                            col:      19,
+                            end_line: 4,
+                            end_col:  19,
                         },
                         arguments: vec![],
                     },
@@ -470,28 +1036,40 @@ mod tests {
                     structs:     vec![],
                     assignments: vec![AssignmentNode {
                         position:   NodePosition {
-                            line: 6,
-                            col:  9,
+                            file:     0,
+                            line:     6,
+                            col:      9,
+                            end_line: 6,
+                            end_col:  40,
                         },
-                        variable:   None,
+                        variables:  vec![],
                         expression: ExpressionNode::FunctionCall(FunctionCallNode {
                             position:      NodePosition {
-                                line: 6,
-                                col:  9,
+                                file:     0,
+                                line:     6,
+                                col:      9,
+                                end_line: 6,
+                                end_col:  40,
                             },
                             function_name: String::from("Println"),
                             serial:        true,
                             external:      false,
                             arguments:     ExpressionListNode {
                                 position:    NodePosition {
-                                    line: 6,
-                                    col:  24,
+                                    file:     0,
+                                    line:     6,
+                                    col:      24,
+                                    end_line: 6,
+                                    end_col:  39,
                                 },
                                 expressions: vec![ExpressionNode::StringLiteral(
                                     StringLiteralNode {
                                         position: NodePosition {
-                                            line: 6,
-                                            col:  24,
+                                            file:     0,
+                                            line:     6,
+                                            col:      24,
+                                            end_line: 6,
+                                            end_col:  39,
                                         },
                                         value:    String::from("Hello, world!"),
                                     }
@@ -525,33 +1103,46 @@ mod tests {
         }
 
         assert_eq!(ast.unwrap(), ContextNode {
+            imports: vec![],
             modules: vec![ModuleNode {
                 position:  NodePosition {
-                    line: 1,
-                    col:  1,
+                    file:     0,
+                    line:     1,
+                    col:      1,
+                    end_line: 8,
+                    end_col:  2,
                 },
                 name:      String::from("Module"),
                 export:    false,
                 modules:   vec![],
                 functions: vec![FunctionNode {
                     position:    NodePosition {
-                        line: 2,
-                        col:  5,
+                        file:     0,
+                        line:     2,
+                        col:      5,
+                        end_line: 7,
+                        end_col:  6,
                     },
                     name:        String::from("Main"),
                     export:      false,
                     serial:      false,
                     params:      ArgumentListNode {
                         position:  NodePosition {
-                            line: 3,
-                            col:  19,
+                            file:     0,
+                            line:     3,
+                            col:      19,
+                            end_line: 3,
+                            end_col:  19,
                         },
                         arguments: vec![],
                     },
                     returns:     ArgumentListNode {
                         position:  NodePosition {
-                            line: 4,
-                            col:  19,
+                            file:     0,
+                            line:     4,
+                            col:      19,
+                            end_line: 4,
+                            end_col:  19,
                         },
                         arguments: vec![],
                     },
@@ -559,28 +1150,40 @@ mod tests {
                     structs:     vec![],
                     assignments: vec![AssignmentNode {
                         position:   NodePosition {
-                            line: 6,
-                            col:  9,
+                            file:     0,
+                            line:     6,
+                            col:      9,
+                            end_line: 6,
+                            end_col:  32,
                         },
-                        variable:   None,
+                        variables:  vec![],
                         expression: ExpressionNode::FunctionCall(FunctionCallNode {
                             position:      NodePosition {
-                                line: 6,
-                                col:  9,
+                                file:     0,
+                                line:     6,
+                                col:      9,
+                                end_line: 6,
+                                end_col:  32,
                             },
                             function_name: String::from("Println"),
                             serial:        false,
                             external:      true,
                             arguments:     ExpressionListNode {
                                 position:    NodePosition {
-                                    line: 6,
-                                    col:  24,
+                                    file:     0,
+                                    line:     6,
+                                    col:      24,
+                                    end_line: 6,
+                                    end_col:  31,
                                 },
                                 expressions: vec![ExpressionNode::StringLiteral(
                                     StringLiteralNode {
                                         position: NodePosition {
-                                            line: 6,
-                                            col:  24,
+                                            file:     0,
+                                            line:     6,
+                                            col:      24,
+                                            end_line: 6,
+                                            end_col:  31,
                                         },
                                         value:    String::from("Apple"),
                                     }
@@ -593,4 +1196,219 @@ mod tests {
             }],
         })
     }
+
+
+    #[test]
+    fn operator_precedence() {
+        fn render(expr: &ExpressionNode) -> String {
+            match expr {
+                ExpressionNode::IntLiteral(v) => v.value.to_string(),
+                ExpressionNode::Variable(v) => v.name.clone(),
+                ExpressionNode::BinaryOperation(o) => {
+                    format!("({} {} {})", o.operator, render(&o.lhs), render(&o.rhs))
+                },
+                ExpressionNode::UnaryOperation(o) => {
+                    format!("({} {})", o.operator, render(&o.operand))
+                },
+                _ => panic!("Unexpected expression: {:?}", expr),
+            }
+        }
+
+        let ast = parse(indoc! {r#"
+            Module = mod {
+                Main = function {
+                    params = ()
+                    return = ()
+
+                    a or b and not c == d + e * -f
+                    1 - 2 - 3 / (4 % 5)
+                }
+            }
+        "#})
+        .unwrap();
+
+        let assignments = &ast.modules[0].functions[0].assignments;
+        assert_eq!(
+            render(&assignments[0].expression),
+            "(or a (and b (not (== c (+ d (* e (- f)))))))"
+        );
+        assert_eq!(
+            render(&assignments[1].expression),
+            "(- (- 1 2) (/ 3 (% 4 5)))"
+        );
+    }
+
+
+    #[test]
+    fn syntax_error() {
+        let error = parse_file(
+            indoc! {r#"
+                Module = mod {
+                    Main = function {
+                        params = ()
+                        return = (value: Int)
+
+                        value = 1 +
+                    }
+                }
+            "#},
+            3,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.get_code(), Some("E0001"));
+        assert_eq!(error.get_message(), "Syntax error, expected expression");
+        assert_eq!(
+            error.get_position(),
+            Some(&NodePosition {
+                file:     3,
+                line:     6,
+                col:      20,
+                end_line: 6,
+                end_col:  21,
+            })
+        );
+    }
+
+
+    #[test]
+    fn recover_from_syntax_errors() {
+        let (context, diagnostics) = parse_file_recovering(
+            indoc! {r#"
+                Module = mod {
+                    Point = struct {
+                        x: Float
+                        y Float
+                    }
+
+                    Main = function {
+                        params = ()
+                        return = (value: Int)
+
+                        a = 1
+                        value = a +
+                        ) b c
+                    }
+
+                    Other = function {
+                        params = (a: Int)
+                        return = ()
+                    }
+                }
+            "#},
+            2,
+        );
+
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages, vec![
+            "error[E0001]: Syntax error, expected argument, at 4:9",
+            "error[E0001]: Syntax error, unexpected 'value = a +', at 12:9",
+            "error[E0001]: Syntax error, unexpected ') b c', at 13:9",
+        ]);
+
+        let module = &context.modules[0];
+        assert_eq!(module.structs[0].fields.arguments.len(), 1);
+
+        let names: Vec<&str> = module.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["Main", "Other"]);
+
+        let assignments = &module.functions[0].assignments;
+        assert_eq!(assignments.len(), 3);
+        assert_eq!(assignments[1].variables[0].name, "value");
+        assert_eq!(
+            assignments[1].expression,
+            ExpressionNode::Error(ErrorNode {
+                position: NodePosition {
+                    file:     2,
+                    line:     12,
+                    col:      17,
+                    end_line: 12,
+                    end_col:  20,
+                },
+                source:   String::from("a +"),
+            })
+        );
+    }
+
+
+    #[test]
+    fn recover_unfinished_file() {
+        let source = indoc! {r#"
+            Module = mod {
+                Main = function {
+                    params = (text: String)
+                    return = (size: Int)
+
+                    size = extern Length(text
+        "#};
+
+        let (context, diagnostics) = parse_file_recovering(source, 0);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get_position(), parse(source).unwrap_err().get_position());
+
+        let function = &context.modules[0].functions[0];
+        assert_eq!(function.name, "Main");
+        assert_eq!(function.params.arguments[0].name, "text");
+        assert_eq!(function.assignments[0].variables[0].name, "size");
+
+        let (context, diagnostics) = parse_file_recovering("Module = mod {\n}\n", 0);
+        assert_eq!(diagnostics, vec![]);
+        assert_eq!(context, parse("Module = mod {\n}\n").unwrap());
+    }
+
+
+    #[test]
+    fn recover_from_out_of_range_literal() {
+        let source = indoc! {r#"
+            Module = mod {
+                Main = function {
+                    params = ()
+                    return = (value: Int)
+
+                    a = 99999999999999999999
+                    b = map([1], function {
+                        params = (x: Int)
+                        return = (y: Int)
+
+                        y = x + 18446744073709551616
+                    })
+                    value = a
+                    VALUE
+                }
+            }
+        "#};
+
+        let error = parse(source).unwrap_err();
+        assert_eq!(error.get_code(), Some("E0005"));
+
+        // Both the strict grammar and the recovering grammar are used here.
+        for source in [source.replace("VALUE", ""), source.replace("VALUE", "c = )")] {
+            let (context, diagnostics) = parse_file_recovering(&source, 0);
+
+            let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+            assert_eq!(messages[..2], [
+                "error[E0005]: Literal '99999999999999999999' is out of range for type 'Int', at \
+                 6:13",
+                "error[E0005]: Literal '18446744073709551616' is out of range for type 'Int', at \
+                 11:21",
+            ]);
+
+            let assignments = &context.modules[0].functions[0].assignments;
+            assert_eq!(assignments[0].variables[0].name, "a");
+            assert_eq!(
+                assignments[0].expression,
+                ExpressionNode::Error(ErrorNode {
+                    position: NodePosition {
+                        file:     0,
+                        line:     6,
+                        col:      13,
+                        end_line: 6,
+                        end_col:  33,
+                    },
+                    source:   String::from("99999999999999999999"),
+                })
+            );
+            assert_eq!(assignments[2].variables[0].name, "value");
+        }
+    }
 }
@@ -11,7 +11,7 @@ use crate::compiler::ast::*;
 use crate::registry::FunctionRegistry;
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::cmp::Ordering;
+use std::fmt;
 
 
 /// Represents an intermediate-level Vertex representation of a program context.
@@ -55,6 +55,18 @@ impl IRContext {
     }
 
 
+    /// Gets a list of all structures within this context.
+    pub fn get_structs(&self) -> &Vec<IRStruct> {
+        &self.structs
+    }
+
+
+    /// Gets a mutable list of all structures within this context.
+    pub(super) fn get_structs_mut(&mut self) -> &mut Vec<IRStruct> {
+        &mut self.structs
+    }
+
+
     /// Adds a new function to this program context.
     pub(super) fn add_function(&mut self, function: IRFunction) {
         self.functions.push(function);
@@ -65,6 +77,12 @@ impl IRContext {
     pub fn get_functions(&self) -> &Vec<IRFunction> {
         &self.functions
     }
+
+
+    /// Gets a mutable list of all functions within this context.
+    pub(super) fn get_functions_mut(&mut self) -> &mut Vec<IRFunction> {
+        &mut self.functions
+    }
 }
 
 impl Default for IRContext {
@@ -84,16 +102,18 @@ pub struct IRStruct {
     ident_path:    Vec<String>,
     accessability: u32,
     fields:        Vec<(String, IRDataType)>,
+    position:      NodePosition,
 }
 
 impl IRStruct {
     /// Creates a new intermediate representation of a structure with the given
     /// identifier path.
-    pub(super) fn new(ident_path: Vec<String>, accessability: u32) -> Self {
+    pub(super) fn new(ident_path: Vec<String>, accessability: u32, position: NodePosition) -> Self {
         Self {
             ident_path,
             accessability,
             fields: vec![],
+            position,
         }
     }
 
@@ -153,6 +173,32 @@ impl IRStruct {
 
         None
     }
+
+
+    /// Gets a list of all fields within this struct, in the order they were
+    /// defined.
+    pub fn get_fields(&self) -> &Vec<(String, IRDataType)> {
+        &self.fields
+    }
+
+
+    /// Gets a mutable list of all fields within this struct.
+    pub(super) fn get_fields_mut(&mut self) -> &mut Vec<(String, IRDataType)> {
+        &mut self.fields
+    }
+
+
+    /// Gets the position within the source code where this struct is defined.
+    pub fn get_position(&self) -> &NodePosition {
+        &self.position
+    }
+
+
+    /// Checks whether or not this struct can be accessed from the element with
+    /// the given identifier path, based off this struct's accessability level.
+    pub fn is_accessible_from(&self, path: &[String]) -> bool {
+        is_accessible(&self.ident_path, self.accessability, path)
+    }
 }
 
 
@@ -185,6 +231,79 @@ pub enum IRFuncCall {
     /// be loaded within the context yet.
     Unresolved(String),
 
+    /// Constructs a struct with the given name, where each input is assigned to
+    /// the field with the respective name within the list of field names. The
+    /// struct may or may not be loaded within the context yet.
+    UnresolvedStruct(String, Vec<String>),
+
+    /// Constructs a struct of the given struct type, where each input is
+    /// assigned to the field at the same index within the struct definition.
+    Struct(String),
+
+    /// Applies a built-in operator to the inputs.
+    Operator(Operator),
+
+    /// Takes a boolean condition followed by two branch inputs, and outputs
+    /// the first branch if the condition is true, or the second branch
+    /// otherwise. Only the branch that is selected is evaluated.
+    Select,
+
+    /// Applies the target function to each element within the list input,
+    /// and outputs a list of the results.
+    Map(Box<IRFuncCall>),
+
+    /// Outputs a list of all elements within the list input for which the
+    /// target function returns true.
+    Filter(Box<IRFuncCall>),
+
+    /// Combines all elements within the list input into a single value using
+    /// the target function, which is assumed to be associative.
+    Reduce(Box<IRFuncCall>),
+
+    /// Combines all elements within the list input, the first input, into a
+    /// single value using the target function, in order, starting from the
+    /// initial value given by the second input.
+    Fold(Box<IRFuncCall>),
+
+    /// Outputs the target function as a function value. All inputs are
+    /// captured within the function value, and are passed to the target
+    /// function as it's first arguments whenever the function value is called.
+    Closure(Box<IRFuncCall>),
+
+    /// Calls the function value provided by the first input, passing all
+    /// remaining inputs as arguments. When used as the target of a higher-order
+    /// function call, the function value is provided by the last input of that
+    /// call instead.
+    ///
+    /// The name is the name of the function value, as written in the source
+    /// code.
+    Invoke(String),
+
+    /// Builds a list from all inputs, in order.
+    BuildList,
+
+    /// Builds a tuple from all inputs, in order.
+    BuildTuple,
+
+    /// Builds a dictionary from all inputs, where the inputs alternate between
+    /// each key and it's corresponding value.
+    BuildDictionary,
+
+    /// Reads the field with the given name from the struct provided as the only
+    /// input. The field index is not known until the data type of the input has
+    /// been inferred.
+    UnresolvedField(String),
+
+    /// Reads the field at the given index from the struct provided as the only
+    /// input.
+    Field(usize),
+
+    /// Reads the element at the given index from the tuple provided as the
+    /// only input, where the tuple is expected to have the given number of
+    /// elements. This is used to assign the elements of a tuple to multiple
+    /// variables.
+    Element(usize, usize),
+
     /// An empty function that takes no inputs and returns an integer constant
     /// value.
     IntConstant(i64),
@@ -207,6 +326,40 @@ pub enum IRFuncCall {
 }
 
 
+impl IRFuncCall {
+    /// Gets the function that is applied by this higher-order function call,
+    /// or the function that is captured by this closure.
+    ///
+    /// If this is not a higher-order function call or a closure, then None is
+    /// returned.
+    pub fn get_target(&self) -> Option<&IRFuncCall> {
+        match self {
+            IRFuncCall::Map(target)
+            | IRFuncCall::Filter(target)
+            | IRFuncCall::Reduce(target)
+            | IRFuncCall::Fold(target)
+            | IRFuncCall::Closure(target) => Some(target),
+            _ => None,
+        }
+    }
+
+
+    /// Gets a mutable reference to the function that is applied by this
+    /// higher-order function call, or the function that is captured by this
+    /// closure.
+    pub(super) fn get_target_mut(&mut self) -> Option<&mut IRFuncCall> {
+        match self {
+            IRFuncCall::Map(target)
+            | IRFuncCall::Filter(target)
+            | IRFuncCall::Reduce(target)
+            | IRFuncCall::Fold(target)
+            | IRFuncCall::Closure(target) => Some(target),
+            _ => None,
+        }
+    }
+}
+
+
 /// A node is a function call within a function graph that takes in a set of
 /// inputs and outputs a given data type.
 #[derive(Debug, Clone, PartialEq)]
@@ -214,15 +367,23 @@ pub struct IRNode {
     function: IRFuncCall,
     inputs:   Vec<IRNodeInput>,
     output:   IRDataType,
+    position: NodePosition,
+    serial:   bool,
+    after:    Option<u32>,
 }
 
 impl IRNode {
     /// Creates a new function call node instance.
-    pub fn new(function: IRFuncCall, inputs: Vec<IRNodeInput>, output: IRDataType) -> Self {
+    pub fn new(
+        function: IRFuncCall, inputs: Vec<IRNodeInput>, output: IRDataType, position: NodePosition,
+    ) -> Self {
         Self {
             function,
             inputs,
             output,
+            position,
+            serial: false,
+            after: None,
         }
     }
 
@@ -243,6 +404,109 @@ impl IRNode {
     pub fn get_function(&self) -> &IRFuncCall {
         &self.function
     }
+
+
+    /// Gets the position within the source code of the expression this node
+    /// was compiled from.
+    pub fn get_position(&self) -> &NodePosition {
+        &self.position
+    }
+
+
+    /// Checks whether or not this node is part of the chain of serial calls
+    /// within it's function. Each node in this chain is executed after the
+    /// previous node in the chain.
+    pub fn is_serial(&self) -> bool {
+        self.serial
+    }
+
+
+    /// Gets the index of the hidden node that must finish executing before
+    /// this node is executed, if any. This ordering is independent of the
+    /// inputs of this node, and is used to execute serial calls in order.
+    pub fn get_after(&self) -> Option<u32> {
+        self.after
+    }
+
+
+    /// Gets a mutable reference to the function that is executed by this node.
+    pub(super) fn get_function_mut(&mut self) -> &mut IRFuncCall {
+        &mut self.function
+    }
+
+
+    /// Replaces the inputs of this node.
+    pub(super) fn set_inputs(&mut self, inputs: Vec<IRNodeInput>) {
+        self.inputs = inputs;
+    }
+
+
+    /// Replaces the output data type of this node.
+    pub(super) fn set_output(&mut self, output: IRDataType) {
+        self.output = output;
+    }
+
+
+    /// Replaces the function that is executed by this node, along with the
+    /// output data type of that function.
+    pub(super) fn set_function(&mut self, function: IRFuncCall, output: IRDataType) {
+        self.function = function;
+        self.output = output;
+    }
+
+
+    /// Sets whether or not this node is part of the chain of serial calls
+    /// within it's function.
+    pub(super) fn set_serial(&mut self, serial: bool) {
+        self.serial = serial;
+    }
+
+
+    /// Sets the index of the hidden node that must finish executing before this
+    /// node is executed.
+    pub(super) fn set_after(&mut self, after: Option<u32>) {
+        self.after = after;
+    }
+}
+
+
+/// A named variable within a function, pointing to the node input that
+/// provides the value of that variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IRVariable {
+    name:     String,
+    value:    IRNodeInput,
+    position: NodePosition,
+}
+
+impl IRVariable {
+    /// Creates a new named variable that points to the given node input.
+    pub fn new(name: String, value: IRNodeInput, position: NodePosition) -> Self {
+        Self {
+            name,
+            value,
+            position,
+        }
+    }
+
+
+    /// Gets the name of this variable.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+
+    /// Gets the node input that provides the value of this variable.
+    pub fn get_value(&self) -> &IRNodeInput {
+        &self.value
+    }
+
+
+    /// Gets the position within the source code where this variable is
+    /// assigned.
+    pub fn get_position(&self) -> &NodePosition {
+        &self.position
+    }
 }
 
 
@@ -255,6 +519,12 @@ pub struct IRFunction {
     statements:    Vec<IRNode>,
     inputs:        Vec<IRDataType>,
     output:        IRDataType,
+    variables:     Vec<IRVariable>,
+    returns:       Vec<String>,
+    result:        Option<IRNodeInput>,
+    after:         Option<u32>,
+    position:      NodePosition,
+    serial:        bool,
 }
 
 impl IRFunction {
@@ -262,7 +532,7 @@ impl IRFunction {
     /// with the given identifier path.
     pub fn new(
         ident_path: Vec<String>, accessability: u32, inputs: Vec<IRDataType>, output: IRDataType,
-        statements: Vec<IRNode>,
+        statements: Vec<IRNode>, position: NodePosition,
     ) -> Self {
         Self {
             ident_path,
@@ -270,6 +540,12 @@ impl IRFunction {
             statements,
             inputs,
             output,
+            variables: vec![],
+            returns: vec![],
+            result: None,
+            after: None,
+            position,
+            serial: false,
         }
     }
 
@@ -309,6 +585,12 @@ impl IRFunction {
     }
 
 
+    /// Gets a mutable list of all statements within this function.
+    pub(super) fn get_statements_mut(&mut self) -> &mut Vec<IRNode> {
+        &mut self.statements
+    }
+
+
     /// Gets the input data types for this function.
     pub fn get_inputs(&self) -> &Vec<IRDataType> {
         &self.inputs
@@ -319,6 +601,121 @@ impl IRFunction {
     pub fn get_output(&self) -> &IRDataType {
         &self.output
     }
+
+
+    /// Gets a list of all named variables within this function.
+    pub fn get_variables(&self) -> &Vec<IRVariable> {
+        &self.variables
+    }
+
+
+    /// Gets the named variable within this function with the given name.
+    ///
+    /// If there is no variable within this function with the given name, then
+    /// None is returned.
+    pub fn get_variable(&self, name: &str) -> Option<&IRVariable> {
+        self.variables.iter().find(|variable| variable.name == name)
+    }
+
+
+    /// Gets a mutable list of all named variables within this function.
+    pub(super) fn get_variables_mut(&mut self) -> &mut Vec<IRVariable> {
+        &mut self.variables
+    }
+
+
+    /// Adds a new named variable to this function.
+    pub(super) fn add_variable(&mut self, variable: IRVariable) {
+        self.variables.push(variable);
+    }
+
+
+    /// Gets the names of the variables that are returned from this function, in
+    /// the order they were declared.
+    pub fn get_returns(&self) -> &Vec<String> {
+        &self.returns
+    }
+
+
+    /// Adds a new return variable name to this function.
+    pub(super) fn add_return(&mut self, name: String) {
+        self.returns.push(name);
+    }
+
+
+    /// Gets the node input that provides the output value of this function.
+    ///
+    /// If this function has multiple return values, then this input is a tuple
+    /// of those values, in the order they were declared. If this function does
+    /// not return any values, then None is returned.
+    pub fn get_result(&self) -> Option<&IRNodeInput> {
+        self.result.as_ref()
+    }
+
+
+    /// Sets the node input that provides the output value of this function.
+    pub(super) fn set_result(&mut self, result: Option<IRNodeInput>) {
+        self.result = result;
+    }
+
+
+    /// Gets the index of the hidden node that must finish executing before this
+    /// function outputs it's value, if any. This is the last serial call within
+    /// the function, ensuring that all serial calls are executed even if their
+    /// results are not used.
+    pub fn get_after(&self) -> Option<u32> {
+        self.after
+    }
+
+
+    /// Sets the index of the hidden node that must finish executing before this
+    /// function outputs it's value.
+    pub(super) fn set_after(&mut self, after: Option<u32>) {
+        self.after = after;
+    }
+
+
+    /// Replaces the input and output data types of this function.
+    pub(super) fn set_signature(&mut self, inputs: Vec<IRDataType>, output: IRDataType) {
+        self.inputs = inputs;
+        self.output = output;
+    }
+
+
+    /// Gets the position within the source code where this function is
+    /// defined.
+    pub fn get_position(&self) -> &NodePosition {
+        &self.position
+    }
+
+
+    /// Checks whether or not this function is a serial function, which is
+    /// allowed to contain serial calls.
+    pub fn is_serial(&self) -> bool {
+        self.serial
+    }
+
+
+    /// Sets whether or not this function is a serial function.
+    pub(super) fn set_serial(&mut self, serial: bool) {
+        self.serial = serial;
+    }
+
+
+    /// Checks whether or not this function can be accessed from the element
+    /// with the given identifier path, based off this function's accessability
+    /// level.
+    pub fn is_accessible_from(&self, path: &[String]) -> bool {
+        is_accessible(&self.ident_path, self.accessability, path)
+    }
+}
+
+
+/// Checks whether the first `accessability` sections of the accessor path
+/// match the respective sections of the target path.
+fn is_accessible(target: &[String], accessability: u32, accessor: &[String]) -> bool {
+    let level = accessability as usize;
+    accessor.len() >= level && target.len() >= level && accessor[..level] == target[..level]
 }
 
 
@@ -381,6 +778,10 @@ pub enum IRDataType {
     /// A named structure data type that contains a set of named fields, each
     /// with a defined data type.
     Struct(String, Vec<(String, IRDataType)>),
+
+    /// A function value that takes in arguments of the given data types, and
+    /// returns the given output data type.
+    Function(Vec<IRDataType>, Box<IRDataType>),
 }
 
 impl IRDataType {
@@ -397,35 +798,44 @@ impl IRDataType {
             "Error" => IRDataType::Error,
             "Null" => IRDataType::Null,
 
+            other if other.starts_with("fn(") => {
+                let (params, output) = split_function_type(other);
+                let params = match params.trim() {
+                    "" => vec![],
+                    params => {
+                        split_top_level(params, ',').into_iter().map(IRDataType::from).collect()
+                    },
+                };
+
+                IRDataType::Function(params, Box::new(IRDataType::from(output)))
+            },
+
             other => {
                 lazy_static! {
-                    static ref LIST_RE: Regex = Regex::new("(.+)\\[\\]").unwrap();
-                    static ref ARRAY_RE: Regex = Regex::new("(.+)\\[(0-9)+\\]").unwrap();
-                    static ref OPTION_RE: Regex = Regex::new("(.+)\\?").unwrap();
-                    static ref RESULT_RE: Regex = Regex::new("(.+)!").unwrap();
-                    static ref TUPLE_RE: Regex = Regex::new("\\(\\s*,?\\s*(.+)\\s*\\)").unwrap();
-                    static ref DICTIONARY_RE: Regex =
-                        Regex::new("\\{\\s*(.+)\\s*:\\s*(.+)\\s*\\}").unwrap();
+                    static ref LIST_RE: Regex = Regex::new("^(.+)\\[\\]$").unwrap();
+                    static ref ARRAY_RE: Regex = Regex::new("^(.+)\\[([0-9]+)\\]$").unwrap();
+                    static ref OPTION_RE: Regex = Regex::new("^(.+)\\?$").unwrap();
+                    static ref RESULT_RE: Regex = Regex::new("^(.+)!$").unwrap();
+                    static ref TUPLE_RE: Regex = Regex::new("^\\((.+)\\)$").unwrap();
+                    static ref DICTIONARY_RE: Regex = Regex::new("^\\{(.+)\\}$").unwrap();
                 }
 
                 if let Some(caps) = LIST_RE.captures(other) {
-                    IRDataType::List(Box::new(IRDataType::from(&caps[0])))
+                    IRDataType::List(Box::new(IRDataType::from(&caps[1])))
                 } else if let Some(caps) = ARRAY_RE.captures(other) {
-                    let count = caps[1].parse::<u32>().unwrap();
-                    IRDataType::Array(Box::new(IRDataType::from(&caps[0])), count)
+                    let count = caps[2].parse::<u32>().unwrap();
+                    IRDataType::Array(Box::new(IRDataType::from(&caps[1])), count)
                 } else if let Some(caps) = OPTION_RE.captures(other) {
-                    IRDataType::Option(Box::new(IRDataType::from(&caps[0])))
+                    IRDataType::Option(Box::new(IRDataType::from(&caps[1])))
                 } else if let Some(caps) = RESULT_RE.captures(other) {
-                    IRDataType::Result(Box::new(IRDataType::from(&caps[0])))
+                    IRDataType::Result(Box::new(IRDataType::from(&caps[1])))
                 } else if let Some(caps) = TUPLE_RE.captures(other) {
-                    let mut elements = vec![];
-                    for cap in caps.iter() {
-                        elements.push(IRDataType::from(cap.unwrap().as_str()));
-                    }
-                    IRDataType::Tuple(elements)
+                    let elements = split_top_level(&caps[1], ',');
+                    IRDataType::Tuple(elements.into_iter().map(IRDataType::from).collect())
                 } else if let Some(caps) = DICTIONARY_RE.captures(other) {
-                    let key = Box::new(IRDataType::from(&caps[0]));
-                    let value = Box::new(IRDataType::from(&caps[1]));
+                    let parts = split_top_level(&caps[1], ':');
+                    let key = Box::new(IRDataType::from(parts[0]));
+                    let value = Box::new(IRDataType::from(parts[1]));
                     IRDataType::Dictionary(key, value)
                 } else {
                     IRDataType::Unresolved(String::from(other))
@@ -454,279 +864,1531 @@ impl IRDataType {
             IRDataType::Tuple(v) => v.iter().all(|e| e.is_resolved()),
             IRDataType::Dictionary(k, v) => k.is_resolved() && v.is_resolved(),
             IRDataType::Struct(_, f) => f.iter().all(|(_, e)| e.is_resolved()),
+            IRDataType::Function(p, o) => p.iter().all(|e| e.is_resolved()) && o.is_resolved(),
         }
     }
 }
 
+impl fmt::Display for IRDataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IRDataType::Unresolved(name) => write!(f, "{}", name),
+            IRDataType::Unknown => write!(f, "Unknown"),
+            IRDataType::Int => write!(f, "Int"),
+            IRDataType::Float => write!(f, "Float"),
+            IRDataType::String => write!(f, "String"),
+            IRDataType::Char => write!(f, "Char"),
+            IRDataType::Bool => write!(f, "Bool"),
+            IRDataType::Error => write!(f, "Error"),
+            IRDataType::Null => write!(f, "Null"),
+            IRDataType::List(e) => write!(f, "{}[]", e),
+            IRDataType::Array(e, len) => write!(f, "{}[{}]", e, len),
+            IRDataType::Option(e) => write!(f, "{}?", e),
+            IRDataType::Result(e) => write!(f, "{}!", e),
+            IRDataType::Dictionary(k, v) => write!(f, "{{{}: {}}}", k, v),
+            IRDataType::Struct(name, _) => write!(f, "{}", name),
+            IRDataType::Tuple(elements) => {
+                write!(f, "(")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, ")")
+            },
+            IRDataType::Function(params, output) => {
+                write!(f, "fn(")?;
+                for (index, param) in params.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                write!(f, ") -> {}", output)
+            },
+        }
+    }
+}
 
-/// Compiles an AST ContextNode into an intermediate representation.
-pub fn compile_context(
-    context: ContextNode, function_registry: &FunctionRegistry,
-) -> Result<IRContext, CompilerError> {
-    let mut ir_context = IRContext::new();
-    let path = vec![];
 
-    // Load all structs and function headers, first
-    for module in context.modules {
-        load_module(&mut ir_context, &path, &module, 0, 0, function_registry)?;
+/// Writes each of the given values, separated by commas.
+fn write_separated<T: fmt::Display>(f: &mut fmt::Formatter<'_>, values: &[T]) -> fmt::Result {
+    for (index, value) in values.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", value)?;
     }
 
-    Ok(ir_context)
+    Ok(())
 }
 
 
-fn load_module(
-    context: &mut IRContext, path: &[String], module: &ModuleNode, mut depth: u32,
-    mut accessability: u32, function_registry: &FunctionRegistry,
-) -> Result<(), CompilerError> {
-    let mut path = path.to_owned();
-    path.push(module.name.clone());
-
-    depth += 1;
-    if !module.export {
-        accessability = depth;
+impl fmt::Display for IRNodeInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IRNodeInput::FunctionParam(index) => write!(f, "${}", index),
+            IRNodeInput::HiddenNode(index) => write!(f, "%{}", index),
+        }
     }
+}
 
-    for nested_module in &module.modules {
-        load_module(
-            context,
-            &path,
-            nested_module,
-            depth,
-            accessability,
-            function_registry,
-        )?;
-    }
 
-    for nested_function in &module.functions {
-        load_function(
-            context,
-            &path,
-            nested_function,
-            depth,
-            accessability,
-            function_registry,
-        )?;
+impl fmt::Display for IRFuncCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IRFuncCall::External(name) => write!(f, "external {}", name),
+            IRFuncCall::Internal(index) => write!(f, "internal {}", index),
+            IRFuncCall::Unresolved(name) => write!(f, "unresolved {}", name),
+            IRFuncCall::UnresolvedStruct(name, fields) => {
+                write!(f, "unresolved struct {} {{", name)?;
+                write_separated(f, fields)?;
+                write!(f, "}}")
+            },
+            IRFuncCall::Struct(name) => write!(f, "struct {}", name),
+            IRFuncCall::Operator(operator) => write!(f, "operator {:?}", operator),
+            IRFuncCall::Select => write!(f, "select"),
+            IRFuncCall::Map(target) => write!(f, "map({})", target),
+            IRFuncCall::Filter(target) => write!(f, "filter({})", target),
+            IRFuncCall::Reduce(target) => write!(f, "reduce({})", target),
+            IRFuncCall::Fold(target) => write!(f, "fold({})", target),
+            IRFuncCall::Closure(target) => write!(f, "closure({})", target),
+            IRFuncCall::Invoke(name) => write!(f, "invoke {}", name),
+            IRFuncCall::BuildList => write!(f, "list"),
+            IRFuncCall::BuildTuple => write!(f, "tuple"),
+            IRFuncCall::BuildDictionary => write!(f, "dictionary"),
+            IRFuncCall::UnresolvedField(name) => write!(f, "unresolved field {}", name),
+            IRFuncCall::Field(index) => write!(f, "field {}", index),
+            IRFuncCall::Element(index, len) => write!(f, "element {} of {}", index, len),
+            IRFuncCall::IntConstant(value) => write!(f, "int {}", value),
+            IRFuncCall::FloatConstant(value) => write!(f, "float {:?}", value),
+            IRFuncCall::StringConstant(value) => write!(f, "string {:?}", value),
+            IRFuncCall::CharConstant(value) => write!(f, "char {:?}", value),
+            IRFuncCall::BoolConstant(value) => write!(f, "bool {}", value),
+        }
     }
+}
 
-    for nested_struct in &module.structs {
-        load_struct(context, &path, nested_struct, accessability)?;
-    }
 
-    Ok(())
-}
+/// Formats a node as it's function call, followed by it's inputs, the node it
+/// waits for, and it's output data type. For example,
+/// `serial external Print (%2) after %1: Null`.
+impl fmt::Display for IRNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.serial {
+            write!(f, "serial ")?;
+        }
 
+        write!(f, "{}", self.function)?;
 
-fn load_function(
-    context: &mut IRContext, path: &[String], function: &FunctionNode, mut depth: u32,
-    mut accessability: u32, function_registry: &FunctionRegistry,
-) -> Result<(), CompilerError> {
-    let mut path = path.to_owned();
-    path.push(function.name.clone());
+        if !self.inputs.is_empty() {
+            write!(f, " (")?;
+            write_separated(f, &self.inputs)?;
+            write!(f, ")")?;
+        }
 
-    depth += 1;
-    if !function.export {
-        accessability = depth;
+        if let Some(after) = self.after {
+            write!(f, " after %{}", after)?;
+        }
+
+        write!(f, ": {}", self.output)
     }
+}
 
-    for nested_function in &function.functions {
-        load_function(
-            context,
-            &path,
-            nested_function,
-            depth,
-            accessability,
-            function_registry,
+
+impl IRFunction {
+    /// Writes this function, where the header includes the given index of this
+    /// function within it's context, if any.
+    fn write(&self, f: &mut fmt::Formatter<'_>, index: Option<usize>) -> fmt::Result {
+        if self.serial {
+            write!(f, "serial ")?;
+        }
+
+        write!(f, "function ")?;
+        if let Some(index) = index {
+            write!(f, "{} ", index)?;
+        }
+
+        let signature = IRDataType::Function(self.inputs.clone(), Box::new(self.output.clone()));
+        writeln!(
+            f,
+            "{} (access {}): {}",
+            self.ident_path.join("."),
+            self.accessability,
+            signature
         )?;
-    }
 
-    for nested_struct in &function.structs {
-        load_struct(context, &path, nested_struct, accessability)?;
-    }
+        for (index, statement) in self.statements.iter().enumerate() {
+            writeln!(f, "    %{} = {}", index, statement)?;
+        }
 
-    let mut inputs = vec![];
-    for param in &function.params.arguments {
-        inputs.push(IRDataType::from(&param.dtype));
-    }
+        match &self.result {
+            Some(result) => write!(f, "    result = {}", result)?,
+            None => write!(f, "    result = none")?,
+        }
 
-    let mut outputs = vec![];
-    for returned in &function.returns.arguments {
-        outputs.push(IRDataType::from(&returned.dtype));
-    }
+        if let Some(after) = self.after {
+            write!(f, " after %{}", after)?;
+        }
 
-    let output;
-    if outputs.is_empty() {
-        output = IRDataType::Null;
-    } else if outputs.len() == 1 {
-        output = outputs.pop().unwrap();
-    } else {
-        output = IRDataType::Tuple(outputs);
+        writeln!(f)
     }
+}
 
-    let statements = parse_function_statements(function, function_registry)?;
-    let ir_function = IRFunction::new(path, accessability, inputs, output, statements);
-    context.add_function(ir_function);
 
-    Ok(())
+/// Formats a function as a header containing it's path, accessability and
+/// signature, followed by one numbered line per statement and a final line for
+/// it's result.
+impl fmt::Display for IRFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, None)
+    }
 }
 
 
-fn load_struct(
-    context: &mut IRContext, path: &[String], structure: &StructNode, accessability: u32,
-) -> Result<(), CompilerError> {
+impl fmt::Display for IRStruct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "struct {} (access {}):", self.ident_path.join("."), self.accessability)?;
+        for (name, dtype) in &self.fields {
+            writeln!(f, "    {}: {}", name, dtype)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Formats a context as all of it's structs, followed by all of it's functions,
+/// separated by blank lines. Each function is numbered by it's index, which is
+/// used by internal function calls.
+impl fmt::Display for IRContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, structure) in self.structs.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", structure)?;
+        }
+
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 || !self.structs.is_empty() {
+                writeln!(f)?;
+            }
+            function.write(f, Some(index))?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Splits the given data type name at each instance of the given separator that
+/// is not nested within brackets, trimming any surrounding whitespace.
+fn split_top_level(name: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, c) in name.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(name[start..index].trim());
+                start = index + 1;
+            },
+            _ => {},
+        }
+    }
+
+    parts.push(name[start..].trim());
+    parts
+}
+
+
+/// Splits the given function data type name, such as `fn(Int, Int) -> Int`,
+/// into the list of parameter data type names and the output data type name.
+fn split_function_type(name: &str) -> (&str, &str) {
+    let mut depth = 0;
+    for (index, c) in name.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    let output = name[index + 1..].trim_start();
+                    let output = output.strip_prefix("->").unwrap_or(output);
+                    return (&name["fn(".len()..index], output.trim());
+                }
+            },
+            _ => {},
+        }
+    }
+
+    (&name["fn(".len()..], "")
+}
+
+
+/// Compiles an AST ContextNode into an intermediate representation.
+///
+/// If any errors are found, then all of them are returned, in the order they
+/// were found.
+pub fn compile_context(
+    context: ContextNode, function_registry: &FunctionRegistry,
+) -> Result<IRContext, Vec<CompilerError>> {
+    let (ir_context, errors) = compile_context_partial(context, function_registry);
+
+    if errors.is_empty() {
+        Ok(ir_context)
+    } else {
+        Err(errors)
+    }
+}
+
+
+/// Compiles an AST ContextNode into an intermediate representation, continuing
+/// past any errors that are found.
+///
+/// Assignments that cannot be compiled are left out of their function, along
+/// with every assignment that reads from them, and struct fields that cannot be
+/// compiled are left out of their struct. The intermediate representation of
+/// everything else is returned, along with a list of all errors that were
+/// found.
+pub fn compile_context_partial(
+    context: ContextNode, function_registry: &FunctionRegistry,
+) -> (IRContext, Vec<CompilerError>) {
+    let mut ir_context = IRContext::new();
+    let mut errors = vec![];
+    let path = vec![];
+
+    // Load all structs and function headers, first
+    for module in context.modules {
+        load_module(
+            &mut ir_context,
+            &path,
+            &module,
+            0,
+            0,
+            function_registry,
+            &mut errors,
+        );
+    }
+
+    (ir_context, errors)
+}
+
+
+fn load_module(
+    context: &mut IRContext, path: &[String], module: &ModuleNode, mut depth: u32,
+    mut accessability: u32, function_registry: &FunctionRegistry,
+    errors: &mut Vec<CompilerError>,
+) {
+    let mut path = path.to_owned();
+    path.push(module.name.clone());
+
+    // Elements that are not exported are only accessible from within their
+    // parent element.
+    if !module.export {
+        accessability = depth;
+    }
+    depth += 1;
+
+    for nested_module in &module.modules {
+        load_module(
+            context,
+            &path,
+            nested_module,
+            depth,
+            accessability,
+            function_registry,
+            errors,
+        );
+    }
+
+    for nested_function in &module.functions {
+        load_function(
+            context,
+            &path,
+            nested_function,
+            &[],
+            accessability,
+            function_registry,
+            errors,
+        );
+    }
+
+    for nested_struct in &module.structs {
+        load_struct(context, &path, nested_struct, accessability, errors);
+    }
+}
+
+
+/// Loads the given function, along with all functions, structs and anonymous
+/// functions nested within it.
+///
+/// The outer scope contains the names of all parameters and variables of the
+/// enclosing functions. Named nested functions cannot capture these, so any
+/// assignment that reads one of them is reported and skipped.
+fn load_function(
+    context: &mut IRContext, path: &[String], function: &FunctionNode, outer_scope: &[String],
+    mut accessability: u32, function_registry: &FunctionRegistry,
+    errors: &mut Vec<CompilerError>,
+) {
+    if !function.export {
+        accessability = path.len() as u32;
+    }
+
+    let mut path = path.to_owned();
+    path.push(function.name.clone());
+
+    let scope: Vec<&str> = function
+        .params
+        .arguments
+        .iter()
+        .map(|param| param.name.as_str())
+        .chain(function.assignments.iter().flat_map(|a| &a.variables).map(|v| v.name.as_str()))
+        .collect();
+
+    let mut nested_scope = outer_scope.to_vec();
+    nested_scope.extend(scope.iter().map(|name| name.to_string()));
+
+    for nested_function in &function.functions {
+        load_function(
+            context,
+            &path,
+            nested_function,
+            &nested_scope,
+            accessability,
+            function_registry,
+            errors,
+        );
+    }
+
+    for nested_struct in &function.structs {
+        load_struct(context, &path, nested_struct, accessability, errors);
+    }
+
+    let mut inputs = vec![];
+    for param in &function.params.arguments {
+        inputs.push(IRDataType::from(&param.dtype));
+    }
+
+    let mut outputs = vec![];
+    for returned in &function.returns.arguments {
+        outputs.push(IRDataType::from(&returned.dtype));
+    }
+
+    let output;
+    if outputs.is_empty() {
+        output = IRDataType::Null;
+    } else if outputs.len() == 1 {
+        output = outputs.pop().unwrap();
+    } else {
+        output = IRDataType::Tuple(outputs);
+    }
+
+    let (mut statements, variables, skipped) =
+        parse_function_statements(function, outer_scope, function_registry, errors);
+    let result = parse_function_result(function, &mut statements, &variables, &skipped, errors);
+    let after = last_serial(&statements);
+    let mut ir_function = IRFunction::new(
+        path.clone(),
+        accessability,
+        inputs,
+        output,
+        statements,
+        function.position.clone(),
+    );
+
+    for variable in variables {
+        ir_function.add_variable(variable);
+    }
+
+    for returned in &function.returns.arguments {
+        ir_function.add_return(returned.name.clone());
+    }
+
+    ir_function.set_result(result);
+    ir_function.set_after(after);
+    ir_function.set_serial(function.serial);
+
+    context.add_function(ir_function);
+
+    // Anonymous functions are loaded as private functions nested within this
+    // function. Each variable they capture is passed in as an additional
+    // parameter, before the declared parameters, and has an unknown data type
+    // until the closure that captures it has been type checked.
+    let mut lambdas = vec![];
+    for assignment in &function.assignments {
+        collect_lambdas(&assignment.expression, &mut lambdas);
+    }
+
+    for lambda in lambdas {
+        let captures = lambda_captures(lambda, |name| scope.contains(&name));
+        let mut lifted = lambda.clone();
+        lifted.params.arguments = captures
+            .iter()
+            .map(|name| {
+                ArgumentNode {
+                    position: lambda.position.clone(),
+                    name:     name.clone(),
+                    dtype:    String::new(),
+                }
+            })
+            .chain(lambda.params.arguments.iter().cloned())
+            .collect();
+
+        // The lifted function is added before any lambdas nested within it.
+        let index = context.get_functions().len();
+        load_function(
+            context,
+            &path,
+            &lifted,
+            &[],
+            accessability,
+            function_registry,
+            errors,
+        );
+
+        let ir_lambda = &mut context.get_functions_mut()[index];
+        let mut inputs = ir_lambda.get_inputs().clone();
+        inputs[..captures.len()].fill(IRDataType::Unknown);
+        let output = ir_lambda.get_output().clone();
+        ir_lambda.set_signature(inputs, output);
+    }
+}
+
+
+fn load_struct(
+    context: &mut IRContext, path: &[String], structure: &StructNode, mut accessability: u32,
+    errors: &mut Vec<CompilerError>,
+) {
+    if !structure.export {
+        accessability = path.len() as u32;
+    }
+
     let mut path = path.to_owned();
     path.push(structure.name.clone());
 
-    let mut ir_struct = IRStruct::new(path, accessability);
+    let mut ir_struct = IRStruct::new(path, accessability, structure.position.clone());
     for field in &structure.fields.arguments {
         let res = ir_struct.add_field(field.name.clone(), IRDataType::from(field.dtype.as_str()));
         if let Err(e) = res {
-            return Err(CompilerError::new(field.position.clone(), e));
+            errors.push(CompilerError::new(field.position.clone(), e));
         }
     }
 
     context.add_struct(ir_struct);
-    Ok(())
 }
 
 
-fn parse_function_statements(
-    function: &FunctionNode, function_registry: &FunctionRegistry,
-) -> Result<Vec<IRNode>, CompilerError> {
-    let mut nodes = vec![];
-    verify_no_circular_deps(&function.assignments)?;
-
-    let mut assignments = function.assignments.clone();
-    assignments.sort_by(|a, b| {
-        if a.variable.is_none() || b.variable.is_none() {
-            Ordering::Equal
-        } else if expression_contains_variable(&b.expression, a.variable.as_ref().unwrap()) {
-            Ordering::Less
-        } else if expression_contains_variable(&a.expression, b.variable.as_ref().unwrap()) {
-            Ordering::Greater
-        } else {
-            Ordering::Equal
-        }
-    });
-
-    let params = &function.params.arguments;
-
-    for assignment in &function.assignments {
-        parse_expression_into_nodes(
-            &assignment.expression,
-            &mut nodes,
-            &assignments,
+/// Parses the assignments within the given function into a list of nodes and
+/// the named variables that point to them.
+///
+/// If an assignment cannot be parsed, then the error is recorded and the
+/// assignment is skipped, along with every assignment that reads from it.
+/// Assignments containing syntax errors are skipped without recording an
+/// error. The names of the variables that were skipped are returned alongside
+/// the nodes, so that they are not reported again as missing.
+fn parse_function_statements(
+    function: &FunctionNode, outer_scope: &[String], function_registry: &FunctionRegistry,
+    errors: &mut Vec<CompilerError>,
+) -> (Vec<IRNode>, Vec<IRVariable>, Vec<String>) {
+    if !function.serial {
+        let mut calls = vec![];
+        for assignment in &function.assignments {
+            collect_serial_calls(&assignment.expression, &mut calls);
+        }
+
+        for call in calls {
+            errors.push(CompilerError::new(
+                call.position.clone(),
+                IRError::SerialCallInNonSerialFunction(call.function_name.clone()),
+            ));
+        }
+    }
+
+    let mut nodes = vec![];
+    let mut variables: Vec<IRVariable> = vec![];
+    let mut skipped: Vec<String> = function
+        .assignments
+        .iter()
+        .flat_map(|assignment| &assignment.variables)
+        .map(|variable| variable.name.clone())
+        .collect();
+
+    let assignments = sort_assignments(&function.assignments, errors);
+    skipped.retain(|name| {
+        !assignments.iter().any(|a| a.variables.iter().any(|v| v.name == *name))
+    });
+
+    let params = &function.params.arguments;
+    let is_local = |name: &str| {
+        params.iter().any(|p| p.name == name)
+            || function.assignments.iter().any(|a| a.variables.iter().any(|v| v.name == name))
+    };
+
+    for assignment in assignments {
+        let mut names = vec![];
+        expression_variables(&assignment.expression, &mut names);
+
+        let captured = names.iter().find(|name| {
+            !is_local(name) && outer_scope.iter().any(|outer| outer == *name)
+        });
+
+        let start = nodes.len();
+        // Assignments containing syntax errors have already been reported by
+        // the parser.
+        let value = if contains_error(&assignment.expression)
+            || names.iter().any(|name| skipped.iter().any(|s| s == name))
+        {
+            None
+        } else if let Some(name) = captured {
+            errors.push(CompilerError::new(
+                assignment.position.clone(),
+                IRError::NestedFunctionCapture(function.name.clone(), (*name).to_owned()),
+            ));
+            None
+        } else {
+            let value = parse_expression_into_nodes(
+                &assignment.expression,
+                &mut nodes,
+                &variables,
+                params,
+                function_registry,
+            );
+
+            value.map_err(|e| errors.push(e)).ok()
+        };
+
+        let value = match value {
+            Some(value) => value,
+            None => {
+                nodes.truncate(start);
+                skipped.extend(assignment.variables.iter().map(|v| v.name.clone()));
+                continue;
+            },
+        };
+
+        let count = assignment.variables.len();
+        for (index, variable) in assignment.variables.iter().enumerate() {
+            let exists = params.iter().any(|p| p.name == variable.name)
+                || variables.iter().any(|v| v.get_name() == variable.name);
+
+            if exists {
+                errors.push(CompilerError::new(
+                    variable.position.clone(),
+                    IRError::IdentifierAlreadyExists(variable.name.clone()),
+                ));
+                continue;
+            }
+
+            let value = if count > 1 {
+                nodes.push(IRNode::new(
+                    IRFuncCall::Element(index, count),
+                    vec![value.clone()],
+                    IRDataType::Unknown,
+                    variable.position.clone(),
+                ));
+                IRNodeInput::HiddenNode(nodes.len() as u32 - 1)
+            } else {
+                value.clone()
+            };
+
+            variables.push(IRVariable::new(
+                variable.name.clone(),
+                value,
+                variable.position.clone(),
+            ));
+        }
+    }
+
+    (nodes, variables, skipped)
+}
+
+
+/// Finds the variable assigned to each return value of the given function, and
+/// gets the node input that provides the output value of the function. If the
+/// function has multiple return values, then a node is added that builds a
+/// tuple of those values, in the order they were declared.
+///
+/// If a return value is never assigned, then an error is recorded and the
+/// function has no output value. Return values that were assigned by a skipped
+/// assignment are not reported.
+fn parse_function_result(
+    function: &FunctionNode, nodes: &mut Vec<IRNode>, variables: &[IRVariable],
+    skipped: &[String], errors: &mut Vec<CompilerError>,
+) -> Option<IRNodeInput> {
+    let mut results = vec![];
+    let mut missing = false;
+    for returned in &function.returns.arguments {
+        match variables.iter().find(|v| v.get_name() == returned.name) {
+            Some(variable) => results.push(variable.get_value().clone()),
+            None if skipped.contains(&returned.name) => missing = true,
+            None => {
+                missing = true;
+                errors.push(CompilerError::new(
+                    returned.position.clone(),
+                    IRError::UnassignedReturn(returned.name.clone()),
+                ));
+            },
+        }
+    }
+
+    if missing {
+        return None;
+    }
+
+    match results.len() {
+        0 => None,
+        1 => results.pop(),
+        _ => {
+            let position = function.returns.position.clone();
+            nodes.push(IRNode::new(
+                IRFuncCall::BuildTuple,
+                results,
+                IRDataType::Unknown,
+                position,
+            ));
+            Some(IRNodeInput::HiddenNode(nodes.len() as u32 - 1))
+        },
+    }
+}
+
+
+fn parse_expression_into_nodes(
+    expr: &ExpressionNode, nodes: &mut Vec<IRNode>, variables: &[IRVariable],
+    params: &[ArgumentNode], function_registry: &FunctionRegistry,
+) -> Result<IRNodeInput, CompilerError> {
+    let mut node = match expr {
+        ExpressionNode::IntLiteral(v) => {
+            IRNode::new(
+                IRFuncCall::IntConstant(v.value),
+                vec![],
+                IRDataType::Int,
+                v.position.clone(),
+            )
+        },
+        ExpressionNode::FloatLiteral(v) => {
+            IRNode::new(
+                IRFuncCall::FloatConstant(v.value),
+                vec![],
+                IRDataType::Float,
+                v.position.clone(),
+            )
+        },
+        ExpressionNode::StringLiteral(v) => {
+            IRNode::new(
+                IRFuncCall::StringConstant(v.value.clone()),
+                vec![],
+                IRDataType::String,
+                v.position.clone(),
+            )
+        },
+        ExpressionNode::BoolLiteral(v) => {
+            IRNode::new(
+                IRFuncCall::BoolConstant(v.value),
+                vec![],
+                IRDataType::Bool,
+                v.position.clone(),
+            )
+        },
+        ExpressionNode::Variable(v) => {
+            let param_pos = params.iter().position(|a| a.name.eq(&v.name));
+            let variable = variables.iter().find(|a| a.get_name() == v.name);
+
+            if let Some(p) = param_pos {
+                return Ok(IRNodeInput::FunctionParam(p as u32));
+            } else if let Some(variable) = variable {
+                return Ok(variable.get_value().clone());
+            }
+
+            // Names that are not parameters or variables refer to internal
+            // functions, which are used as function values.
+            IRNode::new(
+                IRFuncCall::Closure(Box::new(IRFuncCall::Unresolved(v.name.clone()))),
+                vec![],
+                IRDataType::Unknown,
+                v.position.clone(),
+            )
+        },
+        ExpressionNode::Lambda(l) => {
+            let inputs = parse_captures(l, nodes, variables, params, function_registry)?;
+            IRNode::new(
+                IRFuncCall::Closure(Box::new(IRFuncCall::Unresolved(l.name.clone()))),
+                inputs,
+                IRDataType::Unknown,
+                l.position.clone(),
+            )
+        },
+        ExpressionNode::ExternalFunction(e) => {
+            match function_registry.get_function(&e.function_name) {
+                Some(ext_func) => {
+                    IRNode::new(
+                        IRFuncCall::Closure(Box::new(IRFuncCall::External(
+                            e.function_name.clone(),
+                        ))),
+                        vec![],
+                        IRDataType::Function(
+                            ext_func.get_inputs().clone(),
+                            Box::new(ext_func.get_output().clone()),
+                        ),
+                        e.position.clone(),
+                    )
+                },
+                None => {
+                    return Err(CompilerError::new(
+                        e.position.clone(),
+                        IRError::UnknownIdentifier(e.function_name.clone()),
+                    ))
+                },
+            }
+        },
+        ExpressionNode::InnerVariable(v) => {
+            let root = ExpressionNode::Variable(VariableNode {
+                position: v.position.clone(),
+                name:     v.path[0].clone(),
+            });
+
+            let mut input =
+                parse_expression_into_nodes(&root, nodes, variables, params, function_registry)?;
+
+            for field in &v.path[1..] {
+                nodes.push(IRNode::new(
+                    IRFuncCall::UnresolvedField(field.clone()),
+                    vec![input],
+                    IRDataType::Unknown,
+                    v.position.clone(),
+                ));
+                input = IRNodeInput::HiddenNode(nodes.len() as u32 - 1);
+            }
+
+            return Ok(input);
+        },
+        ExpressionNode::BinaryOperation(o) => {
+            let operands = [(*o.lhs).clone(), (*o.rhs).clone()];
+            let inputs = parse_expressions_into_nodes(
+                &operands,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?;
+
+            IRNode::new(
+                IRFuncCall::Operator(o.operator),
+                inputs,
+                IRDataType::Unknown,
+                o.position.clone(),
+            )
+        },
+        ExpressionNode::UnaryOperation(o) => {
+            let input = parse_expression_into_nodes(
+                &o.operand,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?;
+
+            IRNode::new(
+                IRFuncCall::Operator(o.operator),
+                vec![input],
+                IRDataType::Unknown,
+                o.position.clone(),
+            )
+        },
+        ExpressionNode::Conditional(c) => {
+            let mut inputs = vec![parse_expression_into_nodes(
+                &c.condition,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?];
+
+            // Only one branch is executed, so the serial calls within each
+            // branch are ordered after the serial calls before this expression.
+            // The selection then takes the place of those calls in the chain.
+            let before = last_serial(nodes);
+            let mut serial = false;
+            for branch in [&c.then_branch, &c.else_branch] {
+                let start = nodes.len();
+                inputs.push(parse_expression_into_nodes(
+                    branch,
+                    nodes,
+                    variables,
+                    params,
+                    function_registry,
+                )?);
+
+                for node in &mut nodes[start..] {
+                    serial |= node.is_serial();
+                    node.set_serial(false);
+                }
+            }
+
+            let mut node = IRNode::new(
+                IRFuncCall::Select,
+                inputs,
+                IRDataType::Unknown,
+                c.position.clone(),
+            );
+
+            if serial {
+                node.set_serial(true);
+                node.set_after(before);
+            }
+
+            node
+        },
+        ExpressionNode::ListLiteral(l) => {
+            let inputs = parse_expressions_into_nodes(
+                &l.elements,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?;
+
+            IRNode::new(
+                IRFuncCall::BuildList,
+                inputs,
+                IRDataType::Unknown,
+                l.position.clone(),
+            )
+        },
+        ExpressionNode::TupleLiteral(t) => {
+            let inputs = parse_expressions_into_nodes(
+                &t.elements,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?;
+
+            IRNode::new(
+                IRFuncCall::BuildTuple,
+                inputs,
+                IRDataType::Unknown,
+                t.position.clone(),
+            )
+        },
+        ExpressionNode::DictionaryLiteral(d) => {
+            let entries: Vec<ExpressionNode> =
+                d.entries.iter().flat_map(|(key, value)| [key.clone(), value.clone()]).collect();
+
+            let inputs = parse_expressions_into_nodes(
+                &entries,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?;
+
+            IRNode::new(
+                IRFuncCall::BuildDictionary,
+                inputs,
+                IRDataType::Unknown,
+                d.position.clone(),
+            )
+        },
+        ExpressionNode::StructInit(s) => {
+            let mut inputs = vec![];
+            let mut field_names = vec![];
+            for field in &s.fields {
+                inputs.push(parse_expression_into_nodes(
+                    &field.expression,
+                    nodes,
+                    variables,
+                    params,
+                    function_registry,
+                )?);
+                field_names.push(field.name.clone());
+            }
+
+            IRNode::new(
+                IRFuncCall::UnresolvedStruct(s.struct_name.clone(), field_names),
+                inputs,
+                IRDataType::Unknown,
+                s.position.clone(),
+            )
+        },
+        ExpressionNode::Error(_) => {
+            unreachable!("Assignments containing syntax errors are skipped")
+        },
+        ExpressionNode::FunctionCall(f) if higher_order_arguments(f).is_some() => {
+            parse_higher_order_call(f, nodes, variables, params, function_registry)?
+        },
+        ExpressionNode::FunctionCall(f) => {
+            let mut inputs = vec![];
+            for arg_expr in &f.arguments.expressions {
+                inputs.push(parse_expression_into_nodes(
+                    arg_expr,
+                    nodes,
+                    variables,
+                    params,
+                    function_registry,
+                )?);
+            }
+
+            if f.external {
+                if let Some(ext_func) = function_registry.get_function(&f.function_name) {
+                    IRNode::new(
+                        IRFuncCall::External(f.function_name.clone()),
+                        inputs,
+                        ext_func.get_output().clone(),
+                        f.position.clone(),
+                    )
+                } else {
+                    return Err(CompilerError::new(
+                        f.position.clone(),
+                        IRError::UnknownIdentifier(f.function_name.clone()),
+                    ));
+                }
+            } else if is_in_scope(&f.function_name, variables, params) {
+                // Calling a parameter or variable calls the function value it
+                // holds, which is passed to the call as the first input.
+                let callee = ExpressionNode::Variable(VariableNode {
+                    position: f.position.clone(),
+                    name:     f.function_name.clone(),
+                });
+                let callee = parse_expression_into_nodes(
+                    &callee,
+                    nodes,
+                    variables,
+                    params,
+                    function_registry,
+                )?;
+                inputs.insert(0, callee);
+
+                IRNode::new(
+                    IRFuncCall::Invoke(f.function_name.clone()),
+                    inputs,
+                    IRDataType::Unknown,
+                    f.position.clone(),
+                )
+            } else {
+                IRNode::new(
+                    IRFuncCall::Unresolved(f.function_name.clone()),
+                    inputs,
+                    IRDataType::Unknown,
+                    f.position.clone(),
+                )
+            }
+        },
+    };
+
+    if let ExpressionNode::FunctionCall(f) = expr {
+        if f.serial {
+            node.set_after(last_serial(nodes));
+            node.set_serial(true);
+        }
+    }
+
+    let len = nodes.len();
+    nodes.push(node);
+    Ok(IRNodeInput::HiddenNode(len as u32))
+}
+
+
+/// Gets the index of the last node within the chain of serial calls, if any.
+fn last_serial(nodes: &[IRNode]) -> Option<u32> {
+    nodes.iter().rposition(|node| node.is_serial()).map(|index| index as u32)
+}
+
+
+/// Gets the number of arguments that the given function call expects, if the
+/// function call is to a built-in higher-order function, such as `map`. These
+/// built-in functions take precedence over internal functions with the same
+/// name.
+fn higher_order_arguments(f: &FunctionCallNode) -> Option<usize> {
+    if f.external {
+        return None;
+    }
+
+    match f.function_name.as_str() {
+        "map" | "filter" | "reduce" => Some(2),
+        "fold" => Some(3),
+        _ => None,
+    }
+}
+
+
+/// Parses a call to a built-in higher-order function. The last argument of the
+/// call must be the name of the internal function to apply, a function value,
+/// or an anonymous function, while all other arguments are parsed as normal
+/// inputs.
+fn parse_higher_order_call(
+    f: &FunctionCallNode, nodes: &mut Vec<IRNode>, variables: &[IRVariable],
+    params: &[ArgumentNode], function_registry: &FunctionRegistry,
+) -> Result<IRNode, CompilerError> {
+    let expected = higher_order_arguments(f).unwrap();
+    let arguments = &f.arguments.expressions;
+    if arguments.len() != expected {
+        return Err(CompilerError::new(
+            f.position.clone(),
+            IRError::IncorrectArgumentCount(f.function_name.clone(), expected, arguments.len()),
+        ));
+    }
+
+    let (target, arguments) = arguments.split_last().unwrap();
+    let mut inputs =
+        parse_expressions_into_nodes(arguments, nodes, variables, params, function_registry)?;
+
+    // Function values are passed to the call as the last input.
+    let target = match target {
+        ExpressionNode::Variable(v) if is_in_scope(&v.name, variables, params) => {
+            inputs.push(parse_expression_into_nodes(
+                target,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?);
+            Box::new(IRFuncCall::Invoke(v.name.clone()))
+        },
+        ExpressionNode::Lambda(l) => {
+            inputs.push(parse_expression_into_nodes(
+                target,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?);
+            Box::new(IRFuncCall::Invoke(l.name.clone()))
+        },
+        ExpressionNode::ExternalFunction(e) => {
+            inputs.push(parse_expression_into_nodes(
+                target,
+                nodes,
+                variables,
+                params,
+                function_registry,
+            )?);
+            Box::new(IRFuncCall::Invoke(e.function_name.clone()))
+        },
+        ExpressionNode::Variable(v) => Box::new(IRFuncCall::Unresolved(v.name.clone())),
+        ExpressionNode::InnerVariable(v) => Box::new(IRFuncCall::Unresolved(v.path.join("."))),
+        _ => {
+            return Err(CompilerError::new(
+                f.position.clone(),
+                IRError::ExpectedFunctionName(f.function_name.clone()),
+            ))
+        },
+    };
+
+    let function = match f.function_name.as_str() {
+        "map" => IRFuncCall::Map(target),
+        "filter" => IRFuncCall::Filter(target),
+        "reduce" => IRFuncCall::Reduce(target),
+        _ => IRFuncCall::Fold(target),
+    };
+
+    Ok(IRNode::new(
+        function,
+        inputs,
+        IRDataType::Unknown,
+        f.position.clone(),
+    ))
+}
+
+
+/// Parses the variables that are captured by the given anonymous function into
+/// nodes, in the order they are captured.
+fn parse_captures(
+    lambda: &FunctionNode, nodes: &mut Vec<IRNode>, variables: &[IRVariable],
+    params: &[ArgumentNode], function_registry: &FunctionRegistry,
+) -> Result<Vec<IRNodeInput>, CompilerError> {
+    let captures = lambda_captures(lambda, |name| is_in_scope(name, variables, params));
+    let captures: Vec<ExpressionNode> = captures
+        .into_iter()
+        .map(|name| {
+            ExpressionNode::Variable(VariableNode {
+                position: lambda.position.clone(),
+                name,
+            })
+        })
+        .collect();
+
+    parse_expressions_into_nodes(&captures, nodes, variables, params, function_registry)
+}
+
+
+/// Checks whether the given name refers to a parameter or variable within the
+/// current function.
+fn is_in_scope(name: &str, variables: &[IRVariable], params: &[ArgumentNode]) -> bool {
+    params.iter().any(|p| p.name == name) || variables.iter().any(|v| v.get_name() == name)
+}
+
+
+/// Gets the names of all variables that are read within the given anonymous
+/// function, but not defined within it, for which the given function returns
+/// true. Each name is listed once, in the order it is first read.
+fn lambda_captures(lambda: &FunctionNode, in_scope: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut names = vec![];
+    lambda_variables(lambda, &mut names);
+
+    let mut captures: Vec<String> = vec![];
+    for name in names {
+        if in_scope(name) && !captures.iter().any(|c| c == name) {
+            captures.push(name.to_owned());
+        }
+    }
+
+    captures
+}
+
+
+/// Collects the names of all variables that are read within the given
+/// anonymous function, excluding it's own parameters and variables.
+fn lambda_variables<'a>(lambda: &'a FunctionNode, names: &mut Vec<&'a str>) {
+    let mut inner = vec![];
+    for assignment in &lambda.assignments {
+        expression_variables(&assignment.expression, &mut inner);
+    }
+
+    let is_local = |name: &str| {
+        lambda.params.arguments.iter().any(|p| p.name == name)
+            || lambda.assignments.iter().any(|a| a.variables.iter().any(|v| v.name == name))
+    };
+
+    names.extend(inner.into_iter().filter(|name| !is_local(name)));
+}
+
+
+/// Collects all anonymous functions within the given expression, excluding
+/// those nested within other anonymous functions.
+fn collect_lambdas<'a>(expr: &'a ExpressionNode, lambdas: &mut Vec<&'a FunctionNode>) {
+    let mut collect = |exprs: &mut dyn Iterator<Item = &'a ExpressionNode>| {
+        for expr in exprs {
+            collect_lambdas(expr, lambdas);
+        }
+    };
+
+    match expr {
+        ExpressionNode::Lambda(l) => lambdas.push(l),
+        ExpressionNode::BinaryOperation(o) => collect(&mut [&*o.lhs, &*o.rhs].into_iter()),
+        ExpressionNode::UnaryOperation(o) => collect(&mut [&*o.operand].into_iter()),
+        ExpressionNode::Conditional(c) => {
+            collect(&mut [&*c.condition, &*c.then_branch, &*c.else_branch].into_iter())
+        },
+        ExpressionNode::ListLiteral(l) => collect(&mut l.elements.iter()),
+        ExpressionNode::TupleLiteral(t) => collect(&mut t.elements.iter()),
+        ExpressionNode::DictionaryLiteral(d) => {
+            collect(&mut d.entries.iter().flat_map(|(key, value)| [key, value]))
+        },
+        ExpressionNode::FunctionCall(f) => collect(&mut f.arguments.expressions.iter()),
+        ExpressionNode::StructInit(s) => collect(&mut s.fields.iter().map(|f| &f.expression)),
+        ExpressionNode::IntLiteral(_)
+        | ExpressionNode::FloatLiteral(_)
+        | ExpressionNode::StringLiteral(_)
+        | ExpressionNode::BoolLiteral(_)
+        | ExpressionNode::Variable(_)
+        | ExpressionNode::InnerVariable(_)
+        | ExpressionNode::ExternalFunction(_)
+        | ExpressionNode::Error(_) => {},
+    }
+}
+
+
+/// Collects all serial function calls within the given expression, in the
+/// order they are written, excluding those within anonymous functions.
+fn collect_serial_calls<'a>(expr: &'a ExpressionNode, calls: &mut Vec<&'a FunctionCallNode>) {
+    let mut collect = |exprs: &mut dyn Iterator<Item = &'a ExpressionNode>| {
+        for expr in exprs {
+            collect_serial_calls(expr, calls);
+        }
+    };
+
+    match expr {
+        ExpressionNode::FunctionCall(f) => {
+            // Arguments are executed before the call itself.
+            collect(&mut f.arguments.expressions.iter());
+            if f.serial {
+                calls.push(f);
+            }
+        },
+        ExpressionNode::BinaryOperation(o) => collect(&mut [&*o.lhs, &*o.rhs].into_iter()),
+        ExpressionNode::UnaryOperation(o) => collect(&mut [&*o.operand].into_iter()),
+        ExpressionNode::Conditional(c) => {
+            collect(&mut [&*c.condition, &*c.then_branch, &*c.else_branch].into_iter())
+        },
+        ExpressionNode::ListLiteral(l) => collect(&mut l.elements.iter()),
+        ExpressionNode::TupleLiteral(t) => collect(&mut t.elements.iter()),
+        ExpressionNode::DictionaryLiteral(d) => {
+            collect(&mut d.entries.iter().flat_map(|(key, value)| [key, value]))
+        },
+        ExpressionNode::StructInit(s) => collect(&mut s.fields.iter().map(|f| &f.expression)),
+        ExpressionNode::IntLiteral(_)
+        | ExpressionNode::FloatLiteral(_)
+        | ExpressionNode::StringLiteral(_)
+        | ExpressionNode::BoolLiteral(_)
+        | ExpressionNode::Variable(_)
+        | ExpressionNode::InnerVariable(_)
+        | ExpressionNode::Lambda(_)
+        | ExpressionNode::ExternalFunction(_)
+        | ExpressionNode::Error(_) => {},
+    }
+}
+
+
+fn parse_expressions_into_nodes(
+    exprs: &[ExpressionNode], nodes: &mut Vec<IRNode>, variables: &[IRVariable],
+    params: &[ArgumentNode], function_registry: &FunctionRegistry,
+) -> Result<Vec<IRNodeInput>, CompilerError> {
+    let mut inputs = vec![];
+    for expr in exprs {
+        inputs.push(parse_expression_into_nodes(
+            expr,
+            nodes,
+            variables,
             params,
             function_registry,
-        )?;
+        )?);
     }
 
-    Ok(nodes)
+    Ok(inputs)
 }
 
 
-fn parse_expression_into_nodes(
-    expr: &ExpressionNode, nodes: &mut Vec<IRNode>, assignments: &[AssignmentNode],
-    params: &[ArgumentNode], function_registry: &FunctionRegistry,
-) -> Result<IRNodeInput, CompilerError> {
-    let node = match expr {
-        ExpressionNode::IntLiteral(v) => {
-            IRNode::new(IRFuncCall::IntConstant(v.value), vec![], IRDataType::Int)
+/// Collects the names of all variables that are read within the given
+/// expression.
+fn expression_variables<'a>(expr: &'a ExpressionNode, names: &mut Vec<&'a str>) {
+    match expr {
+        ExpressionNode::IntLiteral(_) => {},
+        ExpressionNode::FloatLiteral(_) => {},
+        ExpressionNode::StringLiteral(_) => {},
+        ExpressionNode::BoolLiteral(_) => {},
+        ExpressionNode::BinaryOperation(o) => {
+            expression_variables(&o.lhs, names);
+            expression_variables(&o.rhs, names);
         },
-        ExpressionNode::FloatLiteral(v) => {
-            IRNode::new(
-                IRFuncCall::FloatConstant(v.value),
-                vec![],
-                IRDataType::Float,
-            )
+        ExpressionNode::UnaryOperation(o) => expression_variables(&o.operand, names),
+        ExpressionNode::Conditional(c) => {
+            expression_variables(&c.condition, names);
+            expression_variables(&c.then_branch, names);
+            expression_variables(&c.else_branch, names);
         },
-        ExpressionNode::StringLiteral(v) => {
-            IRNode::new(
-                IRFuncCall::StringConstant(v.value.clone()),
-                vec![],
-                IRDataType::String,
-            )
+        ExpressionNode::ListLiteral(l) => {
+            for element in &l.elements {
+                expression_variables(element, names);
+            }
         },
-        ExpressionNode::BoolLiteral(v) => {
-            IRNode::new(IRFuncCall::BoolConstant(v.value), vec![], IRDataType::Bool)
+        ExpressionNode::TupleLiteral(t) => {
+            for element in &t.elements {
+                expression_variables(element, names);
+            }
         },
-        ExpressionNode::Variable(v) => {
-            let param_pos = params.iter().position(|a| a.name.eq(&v.name));
-            let hidden_pos = assignments
-                .iter()
-                .filter_map(|a| a.variable.as_ref())
-                .position(|a| a.name.eq(&v.name));
-
-            if let Some(p) = param_pos {
-                return Ok(IRNodeInput::FunctionParam(p as u32));
-            } else if let Some(p) = hidden_pos {
-                return Ok(IRNodeInput::HiddenNode(p as u32));
-            } else {
-                return Err(CompilerError::new(
-                    v.position.clone(),
-                    IRError::UnknownIdentifier(v.name.clone()),
-                ));
+        ExpressionNode::DictionaryLiteral(d) => {
+            for (key, value) in &d.entries {
+                expression_variables(key, names);
+                expression_variables(value, names);
             }
         },
-        ExpressionNode::InnerVariable(_) => todo!(),
+        ExpressionNode::Variable(v) => names.push(&v.name),
+        ExpressionNode::InnerVariable(v) => names.push(&v.path[0]),
         ExpressionNode::FunctionCall(f) => {
-            let mut inputs = vec![];
-            for arg_expr in &f.arguments.expressions {
-                inputs.push(parse_expression_into_nodes(
-                    arg_expr,
-                    nodes,
-                    assignments,
-                    params,
-                    function_registry,
-                )?);
+            // The called function may be a function value held by a variable.
+            if !f.external {
+                names.push(&f.function_name);
             }
 
-            if f.external {
-                if let Some(ext_func) = function_registry.get_function(&f.function_name) {
-                    IRNode::new(
-                        IRFuncCall::External(f.function_name.clone()),
-                        inputs,
-                        ext_func.get_output().clone(),
-                    )
-                } else {
-                    return Err(CompilerError::new(
-                        f.position.clone(),
-                        IRError::UnknownIdentifier(f.function_name.clone()),
-                    ));
-                }
-            } else {
-                IRNode::new(
-                    IRFuncCall::Unresolved(f.function_name.clone()),
-                    inputs,
-                    IRDataType::Unknown,
-                )
+            for argument in &f.arguments.expressions {
+                expression_variables(argument, names);
             }
         },
-    };
-
-    let len = nodes.len();
-    nodes.push(node);
-    Ok(IRNodeInput::HiddenNode(len as u32))
+        ExpressionNode::Lambda(l) => lambda_variables(l, names),
+        ExpressionNode::ExternalFunction(_) => {},
+        ExpressionNode::StructInit(s) => {
+            for field in &s.fields {
+                expression_variables(&field.expression, names);
+            }
+        },
+        ExpressionNode::Error(_) => {},
+    }
 }
 
 
-fn expression_contains_variable(expr: &ExpressionNode, var: &VariableNode) -> bool {
+/// Checks whether or not the given expression contains a syntax error,
+/// excluding those within anonymous functions, which are checked when the
+/// anonymous function itself is loaded.
+fn contains_error(expr: &ExpressionNode) -> bool {
     match expr {
-        ExpressionNode::IntLiteral(_) => false,
-        ExpressionNode::FloatLiteral(_) => false,
-        ExpressionNode::StringLiteral(_) => false,
-        ExpressionNode::BoolLiteral(_) => false,
-        ExpressionNode::Variable(v) => v.name.eq(&var.name),
-        ExpressionNode::InnerVariable(v) => v.path[0].eq(&var.name),
-        ExpressionNode::FunctionCall(f) => {
-            f.arguments.expressions.iter().any(|a| expression_contains_variable(a, var))
+        ExpressionNode::Error(_) => true,
+        ExpressionNode::BinaryOperation(o) => contains_error(&o.lhs) || contains_error(&o.rhs),
+        ExpressionNode::UnaryOperation(o) => contains_error(&o.operand),
+        ExpressionNode::Conditional(c) => {
+            contains_error(&c.condition)
+                || contains_error(&c.then_branch)
+                || contains_error(&c.else_branch)
+        },
+        ExpressionNode::ListLiteral(l) => l.elements.iter().any(contains_error),
+        ExpressionNode::TupleLiteral(t) => t.elements.iter().any(contains_error),
+        ExpressionNode::DictionaryLiteral(d) => {
+            d.entries.iter().any(|(key, value)| contains_error(key) || contains_error(value))
+        },
+        ExpressionNode::FunctionCall(f) => f.arguments.expressions.iter().any(contains_error),
+        ExpressionNode::StructInit(s) => s.fields.iter().any(|f| contains_error(&f.expression)),
+        ExpressionNode::IntLiteral(_)
+        | ExpressionNode::FloatLiteral(_)
+        | ExpressionNode::StringLiteral(_)
+        | ExpressionNode::BoolLiteral(_)
+        | ExpressionNode::Variable(_)
+        | ExpressionNode::InnerVariable(_)
+        | ExpressionNode::Lambda(_)
+        | ExpressionNode::ExternalFunction(_) => false,
+    }
+}
+
+
+/// Orders the given assignments such that every assignment comes after all of
+/// the assignments that write to the variables it reads. Assignments that do
+/// not depend on each other keep the order they were written in, and
+/// assignments containing serial calls are never reordered among themselves.
+///
+/// If two or more assignments depend on each other, then an error is recorded
+/// listing every assignment within the cycle, and those assignments are left
+/// out of the returned order. If the serial calls cannot be kept in the order
+/// they were written, then an error is recorded and the assignments are only
+/// ordered by the variables they read.
+fn sort_assignments<'a>(
+    assignments: &'a [AssignmentNode], errors: &mut Vec<CompilerError>,
+) -> Vec<&'a AssignmentNode> {
+    let mut dependencies: Vec<Vec<usize>> = assignments
+        .iter()
+        .map(|assignment| {
+            let mut names = vec![];
+            expression_variables(&assignment.expression, &mut names);
+
+            names
+                .iter()
+                .filter_map(|name| {
+                    assignments.iter().position(|a| a.variables.iter().any(|v| v.name == *name))
+                })
+                .collect()
+        })
+        .collect();
+
+    // Assignments within a cycle are removed from the dependency graph, one
+    // cycle at a time, until no cycles remain.
+    let mut removed = vec![false; assignments.len()];
+    let order = loop {
+        match topological_order(&dependencies) {
+            Ok(order) => break order,
+            Err(cycle) => {
+                let variables: Vec<(String, NodePosition)> = cycle
+                    .iter()
+                    .map(|&i| {
+                        let names: Vec<&str> =
+                            assignments[i].variables.iter().map(|v| v.name.as_str()).collect();
+                        (names.join(", "), assignments[i].position.clone())
+                    })
+                    .collect();
+
+                errors.push(CompilerError::new(
+                    variables[0].1.clone(),
+                    IRError::CircularDependency(variables),
+                ));
+
+                for index in cycle {
+                    removed[index] = true;
+                    dependencies[index].clear();
+                }
+
+                for dependency in &mut dependencies {
+                    dependency.retain(|&index| !removed[index]);
+                }
+            },
+        }
+    };
+
+    // Each assignment containing serial calls also depends on the previous
+    // assignment containing serial calls.
+    let serial_calls: Vec<Vec<&FunctionCallNode>> = assignments
+        .iter()
+        .map(|assignment| {
+            let mut calls = vec![];
+            collect_serial_calls(&assignment.expression, &mut calls);
+            calls
+        })
+        .collect();
+
+    let serial: Vec<usize> = (0..assignments.len())
+        .filter(|&i| !removed[i] && !serial_calls[i].is_empty())
+        .collect();
+    for pair in serial.windows(2) {
+        dependencies[pair[1]].push(pair[0]);
+    }
+
+    let order = match topological_order(&dependencies) {
+        Ok(order) => order,
+        Err(cycle) => {
+            // The cycle can only be caused by a serial call that reads a value
+            // written after a later serial call.
+            let index = cycle.into_iter().filter(|i| serial.contains(i)).min().unwrap();
+            let call = serial_calls[index][0];
+
+            errors.push(CompilerError::new(
+                call.position.clone(),
+                IRError::SerialOrderConflict(call.function_name.clone()),
+            ));
+
+            order
         },
+    };
+
+    order.into_iter().filter(|&index| !removed[index]).map(|index| &assignments[index]).collect()
+}
+
+
+/// Orders the nodes of the given dependency graph such that every node comes
+/// after all of it's dependencies, where each node lists the indices of the
+/// nodes it depends on.
+///
+/// If the graph contains a cycle, then the indices of the nodes within that
+/// cycle are returned as an error.
+fn topological_order(dependencies: &[Vec<usize>]) -> Result<Vec<usize>, Vec<usize>> {
+    let mut visited = vec![false; dependencies.len()];
+    let mut stack = vec![];
+    let mut order = vec![];
+
+    for index in 0..dependencies.len() {
+        visit_assignment(index, dependencies, &mut visited, &mut stack, &mut order)?;
     }
+
+    Ok(order)
 }
 
 
-fn verify_no_circular_deps(_assignments: &[AssignmentNode]) -> Result<(), CompilerError> {
-    // TODO
+/// Performs a depth-first traversal over the assignment dependency graph,
+/// starting at the given assignment, and appends each assignment to the order
+/// list after all of it's dependencies.
+///
+/// The stack contains the assignments that are currently being visited. If an
+/// assignment on the stack is reached again, then the indices of the
+/// assignments within that cycle are returned as an error.
+fn visit_assignment(
+    index: usize, dependencies: &[Vec<usize>], visited: &mut [bool], stack: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<(), Vec<usize>> {
+    if let Some(start) = stack.iter().position(|&i| i == index) {
+        return Err(stack[start..].to_vec());
+    }
+
+    if visited[index] {
+        return Ok(());
+    }
+
+    stack.push(index);
+    for &dependency in &dependencies[index] {
+        visit_assignment(dependency, dependencies, visited, stack, order)?;
+    }
+    stack.pop();
+
+    visited[index] = true;
+    order.push(index);
     Ok(())
 }
 
@@ -735,8 +2397,10 @@ fn verify_no_circular_deps(_assignments: &[AssignmentNode]) -> Result<(), Compil
 mod tests {
     use super::*;
     use crate::compiler::grammar::parse;
+    use crate::compiler::resolver::resolve_context;
+    use crate::compiler::typecheck::check_types;
     use crate::data::Data;
-    use crate::registry::FuncMeta;
+    use crate::registry::{standard_library, FuncMeta};
     use indoc::indoc;
     use pretty_assertions::assert_eq;
     use std::sync::Arc;
@@ -803,7 +2467,7 @@ mod tests {
         )
         .unwrap();
 
-        let add_func = IRFunction::new(
+        let mut add_func = IRFunction::new(
             vec![String::from("Math"), String::from("Add")],
             0,
             vec![IRDataType::Int, IRDataType::Int],
@@ -812,10 +2476,24 @@ mod tests {
                 IRFuncCall::External(String::from("Add")),
                 vec![IRNodeInput::FunctionParam(0), IRNodeInput::FunctionParam(1)],
                 IRDataType::Int,
+                NodePosition {
+                    file:     0,
+                    line:     13,
+                    col:      17,
+                    end_line: 13,
+                    end_col:  33,
+                },
             )],
+            NodePosition {
+                file:     0,
+                line:     9,
+                col:      5,
+                end_line: 14,
+                end_col:  6,
+            },
         );
 
-        let mul_func = IRFunction::new(
+        let mut mul_func = IRFunction::new(
             vec![String::from("Math"), String::from("Multiply")],
             0,
             vec![IRDataType::Int, IRDataType::Int],
@@ -824,12 +2502,61 @@ mod tests {
                 IRFuncCall::External(String::from("Mul")),
                 vec![IRNodeInput::FunctionParam(0), IRNodeInput::FunctionParam(1)],
                 IRDataType::Int,
+                NodePosition {
+                    file:     0,
+                    line:     20,
+                    col:      17,
+                    end_line: 20,
+                    end_col:  33,
+                },
             )],
+            NodePosition {
+                file:     0,
+                line:     16,
+                col:      5,
+                end_line: 21,
+                end_col:  6,
+            },
         );
 
+        add_func.add_variable(IRVariable::new(
+            String::from("value"),
+            IRNodeInput::HiddenNode(0),
+            NodePosition {
+                file:     0,
+                line:     13,
+                col:      9,
+                end_line: 13,
+                end_col:  14,
+            },
+        ));
+        add_func.add_return(String::from("value"));
+        add_func.set_result(Some(IRNodeInput::HiddenNode(0)));
+
+        mul_func.add_variable(IRVariable::new(
+            String::from("value"),
+            IRNodeInput::HiddenNode(0),
+            NodePosition {
+                file:     0,
+                line:     20,
+                col:      9,
+                end_line: 20,
+                end_col:  14,
+            },
+        ));
+        mul_func.add_return(String::from("value"));
+        mul_func.set_result(Some(IRNodeInput::HiddenNode(0)));
+
         let mut point_struct = IRStruct::new(
             vec![String::from("Math"), String::from("Vector"), String::from("Point")],
             0,
+            NodePosition {
+                file:     0,
+                line:     3,
+                col:      9,
+                end_line: 6,
+                end_col:  10,
+            },
         );
         point_struct.add_field(String::from("x"), IRDataType::Float).unwrap();
         point_struct.add_field(String::from("y"), IRDataType::Float).unwrap();
@@ -841,4 +2568,457 @@ mod tests {
 
         assert_eq!(source, target);
     }
+
+
+    #[test]
+    fn assignments_sorted_by_dependency() {
+        let context = compile_context(
+            parse(indoc! {r#"
+                Main = mod {
+                    Main = function {
+                        params = ()
+                        return = (c: Int)
+
+                        c = Inc(b)
+                        b = Inc(a)
+                        a = 1
+                    }
+                }
+            "#})
+            .unwrap(),
+            &FunctionRegistry::new(),
+        )
+        .unwrap();
+
+        let function = &context.get_functions()[0];
+        let statements = function.get_statements();
+        assert_eq!(statements[0].get_function(), &IRFuncCall::IntConstant(1));
+        assert_eq!(statements[1].get_inputs(), &vec![IRNodeInput::HiddenNode(
+            0
+        )]);
+        assert_eq!(statements[2].get_inputs(), &vec![IRNodeInput::HiddenNode(
+            1
+        )]);
+        assert_eq!(
+            function.get_variable("c").unwrap().get_value(),
+            &IRNodeInput::HiddenNode(2)
+        );
+    }
+
+
+    #[test]
+    fn circular_dependency() {
+        let errors = compile_context(
+            parse(indoc! {r#"
+                Main = mod {
+                    Main = function {
+                        params = ()
+                        return = (c: Int)
+
+                        c = Inc(a)
+                        a = Inc(b)
+                        b = Dec(a)
+                    }
+                }
+            "#})
+            .unwrap(),
+            &FunctionRegistry::new(),
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Circular dependency between variables 'a' (7:9) -> 'b' (8:9) -> 'a', at 7:9"
+        );
+    }
+
+
+    #[test]
+    fn unassigned_return() {
+        let errors = compile_context(
+            parse(indoc! {r#"
+                Main = mod {
+                    DivMod = function {
+                        params = (a: Int, b: Int)
+                        return = (quotient: Int, remainder: Int)
+
+                        quotient = a / b
+                    }
+                }
+            "#})
+            .unwrap(),
+            &FunctionRegistry::new(),
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Return value 'remainder' is never assigned, at 4:34"
+        );
+    }
+
+
+    #[test]
+    fn serial_call_order() {
+        let compile =
+            |source: &str| compile_context(parse(source).unwrap(), &FunctionRegistry::new());
+
+        let context = compile(indoc! {r#"
+            Main = mod {
+                Main = serial function {
+                    params = ()
+                    return = (value: Int)
+
+                    a = serial Read()
+                    serial Print(a)
+                    value = a + 1
+                }
+            }
+        "#})
+        .unwrap();
+
+        let function = &context.get_functions()[0];
+        let ordering: Vec<(bool, Option<u32>)> = function
+            .get_statements()
+            .iter()
+            .map(|s| (s.is_serial(), s.get_after()))
+            .collect();
+        assert_eq!(ordering, vec![
+            (true, None),
+            (true, Some(0)),
+            (false, None),
+            (false, None),
+        ]);
+        assert_eq!(function.get_after(), Some(1));
+
+        let errors = compile(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = ()
+                    return = ()
+
+                    serial Print("Hello")
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Serial call to 'Print' is only allowed within a serial function, at 6:9"
+        );
+
+        let errors = compile(indoc! {r#"
+            Main = mod {
+                Main = serial function {
+                    params = ()
+                    return = ()
+
+                    serial Print(line)
+                    line = serial Read()
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Serial call to 'Print' reads a value that is only available after a later \
+             serial call, at 6:9"
+        );
+    }
+
+
+    #[test]
+    fn display_context() {
+        let context = compile_context(
+            parse(indoc! {r#"
+                Main = mod {
+                    Point = struct {
+                        x: Float
+                        y: Float
+                    }
+
+                    Main = serial function {
+                        params = (scale: Float)
+                        return = (value: Point)
+
+                        serial Print("Scaling")
+                        value = Point { x = scale * 2.0, y = -scale }
+                        doubled = map([1, 2], function {
+                            params = (n: Int)
+                            return = (m: Int)
+
+                            m = n * 2
+                        })
+                    }
+                }
+            "#})
+            .unwrap(),
+            &FunctionRegistry::new(),
+        )
+        .unwrap();
+
+        assert_eq!(context.to_string(), indoc! {r#"
+            struct Main.Point (access 1):
+                x: Float
+                y: Float
+
+            serial function 0 Main.Main (access 1): fn(Float) -> Point
+                %0 = string "Scaling": String
+                %1 = serial unresolved Print (%0): Unknown
+                %2 = float 2.0: Float
+                %3 = operator Multiply ($0, %2): Unknown
+                %4 = operator Negate ($0): Unknown
+                %5 = unresolved struct Point {x, y} (%3, %4): Unknown
+                %6 = int 1: Int
+                %7 = int 2: Int
+                %8 = list (%6, %7): Unknown
+                %9 = closure(unresolved lambda@13:31): Unknown
+                %10 = map(invoke lambda@13:31) (%8, %9): Unknown
+                result = %5 after %1
+
+            function 1 Main.Main.lambda@13:31 (access 2): fn(Int) -> Int
+                %0 = int 2: Int
+                %1 = operator Multiply ($0, %0): Unknown
+                result = %1
+        "#});
+
+        let function = &context.get_functions()[1];
+        assert!(function.to_string().starts_with("function Main.Main.lambda@13:31 (access 2)"));
+        assert_eq!(function.get_statements()[1].to_string(), "operator Multiply ($0, %0): Unknown");
+    }
+
+
+    #[test]
+    fn display_resolved_context() {
+        let registry = standard_library();
+        let mut context = compile_context(
+            parse(indoc! {r#"
+                Main = mod {
+                    Point = struct {
+                        x: Int
+                        y: Int
+                    }
+
+                    Main = serial function {
+                        params = (offset: Int)
+                        return = (sum: Int)
+
+                        serial extern Println("Start")
+                        point = Point { x = offset, y = 2 }
+                        shifted = map([point.x, point.y], function {
+                            params = (n: Int)
+                            return = (m: Int)
+
+                            m = n + offset
+                        })
+                        serial extern Println("Done")
+                        sum = fold(shifted, 0, function {
+                            params = (a: Int, b: Int)
+                            return = (c: Int)
+
+                            c = a + b
+                        })
+                    }
+                }
+            "#})
+            .unwrap(),
+            &registry,
+        )
+        .unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+
+        // The first lambda captures `offset`, which is passed to the closure
+        // and read as the first parameter of the lifted function.
+        assert_eq!(context.to_string(), indoc! {r#"
+            struct Main.Point (access 1):
+                x: Int
+                y: Int
+
+            serial function 0 Main.Main (access 1): fn(Int) -> Int
+                %0 = string "Start": String
+                %1 = serial external Println (%0): Null
+                %2 = int 2: Int
+                %3 = struct Main.Point ($0, %2): Main.Point
+                %4 = field 0 (%3): Int
+                %5 = field 1 (%3): Int
+                %6 = list (%4, %5): Int[]
+                %7 = closure(internal 1) ($0): fn(Int) -> Int
+                %8 = map(invoke lambda@13:43) (%6, %7): Int[]
+                %9 = string "Done": String
+                %10 = serial external Println (%9) after %1: Null
+                %11 = int 0: Int
+                %12 = closure(internal 2): fn(Int, Int) -> Int
+                %13 = fold(invoke lambda@20:32) (%8, %11, %12): Int
+                result = %13 after %10
+
+            function 1 Main.Main.lambda@13:43 (access 2): fn(Int, Int) -> Int
+                %0 = operator Add ($1, $0): Int
+                result = %0
+
+            function 2 Main.Main.lambda@20:32 (access 2): fn(Int, Int) -> Int
+                %0 = operator Add ($0, $1): Int
+                result = %0
+        "#});
+    }
+
+
+    #[test]
+    fn report_multiple_errors() {
+        let (context, errors) = compile_context_partial(
+            parse(indoc! {r#"
+                Main = mod {
+                    Point = struct {
+                        x: Float
+                        x: Float
+                    }
+
+                    Broken = function {
+                        params = ()
+                        return = (value: Int)
+
+                        a = extern Missing()
+                        b = a + 1
+                        value = b * 2
+                        c = 3
+                        c = 4
+                    }
+
+                    Valid = function {
+                        params = ()
+                        return = (value: Int)
+
+                        value = 1
+                    }
+                }
+            "#})
+            .unwrap(),
+            &FunctionRegistry::new(),
+        );
+
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages, vec![
+            "Error: Cannot find element 'Missing' within the current scope, at 11:13",
+            "Error: An element with the name 'c' already exists, at 15:9",
+            "Error: An element with the name 'x' already exists, at 4:9",
+        ]);
+
+        let broken = &context.get_functions()[0];
+        assert_eq!(broken.get_statements().len(), 2);
+        assert_eq!(broken.get_result(), None);
+        assert!(broken.get_variable("a").is_none());
+        assert!(broken.get_variable("c").is_some());
+
+        let valid = &context.get_functions()[1];
+        assert_eq!(valid.get_result(), Some(&IRNodeInput::HiddenNode(0)));
+        assert_eq!(context.get_structs()[0].get_fields().len(), 1);
+    }
+
+
+    #[test]
+    fn nested_function_captures() {
+        let (mut context, errors) = compile_context_partial(
+            parse(indoc! {r#"
+                Main = mod {
+                    Main = function {
+                        params = (k: Int)
+                        return = (value: Int)
+
+                        Offset = function {
+                            params = (x: Int)
+                            return = (y: Int)
+
+                            y = x + k
+                        }
+
+                        Shadow = function {
+                            params = (k: Int)
+                            return = (y: Int)
+
+                            y = k * 2
+                        }
+
+                        f = (function {
+                            params = (x: Int)
+                            return = (y: Int)
+
+                            y = x + k
+                        })
+                        value = f(Offset(Shadow(1)))
+                    }
+                }
+            "#})
+            .unwrap(),
+            &FunctionRegistry::new(),
+        );
+
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages, vec![
+            "Error: Nested function 'Offset' cannot capture 'k' from an enclosing function, at \
+             10:13",
+        ]);
+
+        // The assignment that captures is skipped, so it is not reported again
+        // once function calls are resolved.
+        assert!(crate::compiler::resolver::resolve_context(&mut context).is_ok());
+        let offset = context.get_function(&vec![
+            String::from("Main"),
+            String::from("Main"),
+            String::from("Offset"),
+        ]);
+        assert_eq!(offset.unwrap().get_result(), None);
+    }
+
+
+    #[test]
+    fn parse_data_type_names() {
+        assert_eq!(
+            IRDataType::from("Int[]"),
+            IRDataType::List(Box::new(IRDataType::Int))
+        );
+        assert_eq!(
+            IRDataType::from("Float[3]"),
+            IRDataType::Array(Box::new(IRDataType::Float), 3)
+        );
+        assert_eq!(
+            IRDataType::from("Geometry.Point?"),
+            IRDataType::Option(Box::new(IRDataType::Unresolved(String::from(
+                "Geometry.Point"
+            ))))
+        );
+        assert_eq!(
+            IRDataType::from("(Int, (Bool, Char)[])"),
+            IRDataType::Tuple(vec![
+                IRDataType::Int,
+                IRDataType::List(Box::new(IRDataType::Tuple(vec![
+                    IRDataType::Bool,
+                    IRDataType::Char,
+                ]))),
+            ])
+        );
+        assert_eq!(
+            IRDataType::from("{String: Int[]}!"),
+            IRDataType::Result(Box::new(IRDataType::Dictionary(
+                Box::new(IRDataType::String),
+                Box::new(IRDataType::List(Box::new(IRDataType::Int)))
+            )))
+        );
+        assert_eq!(
+            IRDataType::from("fn(Int, fn(Int) -> Bool) -> Int[]"),
+            IRDataType::Function(
+                vec![
+                    IRDataType::Int,
+                    IRDataType::Function(vec![IRDataType::Int], Box::new(IRDataType::Bool)),
+                ],
+                Box::new(IRDataType::List(Box::new(IRDataType::Int)))
+            )
+        );
+        assert_eq!(IRDataType::from("fn() -> Int").to_string(), "fn() -> Int");
+    }
 }
@@ -1,8 +1,16 @@
 //! Handles converting Vertex source code into an executable form.
 
 
+pub mod assembly;
 pub mod ast;
 pub mod bytecode;
+pub mod diagnostics;
+pub mod dot;
 mod errors;
 pub mod grammar;
 pub mod ir;
+pub mod optimizer;
+pub mod resolver;
+pub mod sources;
+pub mod typecheck;
+pub mod vxb;
@@ -0,0 +1,410 @@
+//! Optimization passes that simplify the functions within an intermediate
+//! representation context, so that fewer nodes are scheduled as jobs when the
+//! program is executed.
+//!
+//! Each pass is independent and may be enabled or disabled individually. All
+//! passes expect the context to have already been resolved and type checked.
+
+
+use super::ir::{IRContext, IRDataType, IRFuncCall, IRFunction, IRNodeInput, IRVariable};
+use crate::data::{Data, VertexFunction};
+use crate::registry::FunctionRegistry;
+use crate::vm::operator_function;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+
+/// An optimization pass that can be run over the functions of a context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    /// Evaluates each built-in operator and pure external function whose inputs
+    /// are all constants, replacing the call with a constant of it's result.
+    ///
+    /// Calls that produce an error value, or a value that cannot be written as
+    /// a constant, are left to be evaluated when the program is executed.
+    /// Serial calls are never folded.
+    ConstantFolding,
+
+    /// Removes every node whose value never reaches the output of it's
+    /// function. Serial calls are always kept, as the output of a function
+    /// waits for all of them to finish.
+    DeadNodeElimination,
+}
+
+impl Pass {
+    /// Every optimization pass, in the order they are run by default.
+    pub const ALL: [Pass; 2] = [Pass::ConstantFolding, Pass::DeadNodeElimination];
+
+
+    /// Gets the name of this pass, as used on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::ConstantFolding => "constant-folding",
+            Pass::DeadNodeElimination => "dead-node-elimination",
+        }
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Pass {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match Pass::ALL.into_iter().find(|pass| pass.name() == name) {
+            Some(pass) => Ok(pass),
+            None => {
+                let names: Vec<&str> = Pass::ALL.iter().map(Pass::name).collect();
+                Err(format!("unknown pass '{}', expected one of: {}", name, names.join(", ")))
+            },
+        }
+    }
+}
+
+
+/// Runs the given optimization passes, in order, over every function within
+/// the given context.
+///
+/// External functions are only evaluated if they are marked as pure within the
+/// given function registry.
+pub fn optimize_context(context: &mut IRContext, registry: &FunctionRegistry, passes: &[Pass]) {
+    for function in context.get_functions_mut() {
+        for pass in passes {
+            match pass {
+                Pass::ConstantFolding => fold_constants(function, registry),
+                Pass::DeadNodeElimination => eliminate_dead_nodes(function),
+            }
+        }
+    }
+}
+
+
+fn fold_constants(function: &mut IRFunction, registry: &FunctionRegistry) {
+    let statements = function.get_statements_mut();
+
+    // Statements are ordered by their dependencies, so the inputs of each
+    // statement have already been folded by the time it is reached.
+    for index in 0..statements.len() {
+        let statement = &statements[index];
+        if statement.is_serial() || statement.get_after().is_some() {
+            continue;
+        }
+
+        let evaluate: VertexFunction = match statement.get_function() {
+            IRFuncCall::Operator(operator) => operator_function(*operator),
+            IRFuncCall::External(name) => {
+                match registry.get_function(name) {
+                    Some(meta) if meta.is_pure() => meta.get_func(),
+                    _ => continue,
+                }
+            },
+            _ => continue,
+        };
+
+        let inputs = statement
+            .get_inputs()
+            .iter()
+            .map(|input| {
+                match *input {
+                    IRNodeInput::HiddenNode(node) => {
+                        constant_value(statements[node as usize].get_function()).map(Arc::new)
+                    },
+                    IRNodeInput::FunctionParam(_) => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>();
+
+        let inputs = match inputs {
+            Some(inputs) => inputs,
+            None => continue,
+        };
+
+        if let Some((constant, dtype)) = constant_call(evaluate(inputs)) {
+            if dtype == *statement.get_output() {
+                statements[index].set_function(constant, dtype);
+                statements[index].set_inputs(vec![]);
+            }
+        }
+    }
+}
+
+
+/// Gets the value of the given function call, if it is a constant.
+fn constant_value(call: &IRFuncCall) -> Option<Data> {
+    match call {
+        IRFuncCall::IntConstant(value) => Some(Data::Int(*value)),
+        IRFuncCall::FloatConstant(value) => Some(Data::Float(*value)),
+        IRFuncCall::StringConstant(value) => Some(Data::String(value.clone())),
+        IRFuncCall::CharConstant(value) => Some(Data::Char(*value)),
+        IRFuncCall::BoolConstant(value) => Some(Data::Bool(*value)),
+        _ => None,
+    }
+}
+
+
+/// Gets the constant function call that outputs the given value, along with
+/// it's data type. If the value cannot be written as a constant, then None is
+/// returned.
+fn constant_call(value: Data) -> Option<(IRFuncCall, IRDataType)> {
+    match value {
+        Data::Int(value) => Some((IRFuncCall::IntConstant(value), IRDataType::Int)),
+        Data::Float(value) => Some((IRFuncCall::FloatConstant(value), IRDataType::Float)),
+        Data::String(value) => Some((IRFuncCall::StringConstant(value), IRDataType::String)),
+        Data::Char(value) => Some((IRFuncCall::CharConstant(value), IRDataType::Char)),
+        Data::Bool(value) => Some((IRFuncCall::BoolConstant(value), IRDataType::Bool)),
+        _ => None,
+    }
+}
+
+
+fn hidden_node(input: &IRNodeInput) -> Option<u32> {
+    match *input {
+        IRNodeInput::HiddenNode(index) => Some(index),
+        IRNodeInput::FunctionParam(_) => None,
+    }
+}
+
+
+fn eliminate_dead_nodes(function: &mut IRFunction) {
+    let statements = function.get_statements();
+    let mut live = vec![false; statements.len()];
+
+    let mut pending: Vec<u32> = function.get_result().and_then(hidden_node).into_iter().collect();
+    pending.extend(function.get_after());
+
+    while let Some(index) = pending.pop() {
+        let index = index as usize;
+        if live[index] {
+            continue;
+        }
+
+        live[index] = true;
+        pending.extend(statements[index].get_inputs().iter().filter_map(hidden_node));
+        pending.extend(statements[index].get_after());
+    }
+
+    if live.iter().all(|live| *live) {
+        return;
+    }
+
+    // The new index of each live node, once all dead nodes are removed.
+    let mut indexes = vec![None; live.len()];
+    let kept = live.iter().enumerate().filter(|(_, live)| **live);
+    for (next, (index, _)) in kept.enumerate() {
+        indexes[index] = Some(next as u32);
+    }
+
+    let renumber = |input: &IRNodeInput| {
+        match input {
+            IRNodeInput::HiddenNode(index) => indexes[*index as usize].map(IRNodeInput::HiddenNode),
+            param => Some(param.clone()),
+        }
+    };
+
+    let statements = std::mem::take(function.get_statements_mut());
+    *function.get_statements_mut() = statements
+        .into_iter()
+        .zip(live)
+        .filter(|(_, live)| *live)
+        .map(|(mut statement, _)| {
+            let inputs = statement.get_inputs().iter().filter_map(renumber).collect();
+            statement.set_inputs(inputs);
+            statement.set_after(statement.get_after().and_then(|after| indexes[after as usize]));
+            statement
+        })
+        .collect();
+
+    let result = function.get_result().and_then(renumber);
+    function.set_result(result);
+
+    let after = function.get_after().and_then(|after| indexes[after as usize]);
+    function.set_after(after);
+
+    // Variables that were assigned to a removed node no longer have a value.
+    let variables = std::mem::take(function.get_variables_mut());
+    *function.get_variables_mut() = variables
+        .into_iter()
+        .filter_map(|variable| {
+            let value = renumber(variable.get_value())?;
+            let name = variable.get_name().to_owned();
+            Some(IRVariable::new(name, value, variable.get_position().clone()))
+        })
+        .collect();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::bytecode::bytecode_from_ir;
+    use crate::compiler::grammar::parse;
+    use crate::compiler::ir::compile_context;
+    use crate::compiler::resolver::resolve_context;
+    use crate::compiler::typecheck::check_types;
+    use crate::multithreading::jobs::{JobScheduler, Scheduler};
+    use crate::registry::FuncMeta;
+    use crate::{multithreading, unwrap_data, vm};
+    use indoc::indoc;
+    use ntest::timeout;
+    use pretty_assertions::assert_eq;
+
+
+    fn double(inputs: Vec<Arc<Data>>) -> Data {
+        Data::Int(unwrap_data!(inputs[0], Int) * 2)
+    }
+
+
+    fn registry() -> FunctionRegistry {
+        let mut registry = FunctionRegistry::new();
+        for (name, pure) in [("Double", true), ("Twice", false)] {
+            let inputs = vec![IRDataType::Int];
+            let mut meta =
+                FuncMeta::new(String::from(name), double, inputs, IRDataType::Int).unwrap();
+            meta.set_pure(pure);
+            registry.register(meta).unwrap();
+        }
+        registry
+    }
+
+
+    fn compile(source: &str, registry: &FunctionRegistry, passes: &[Pass]) -> IRContext {
+        let mut context = compile_context(parse(source).unwrap(), registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, registry).unwrap();
+        optimize_context(&mut context, registry, passes);
+        context
+    }
+
+
+    const PROGRAM: &str = indoc! {r#"
+        Main = mod {
+            Main = function {
+                params = (x: Int)
+                return = (value: Int, error: Int)
+
+                scale = 2 * 3 + 1
+                doubled = extern Double(scale)
+                twice = extern Twice(scale)
+                value = doubled + x
+                error = 1 / 0
+            }
+        }
+    "#};
+
+
+    #[test]
+    fn fold_constant_inputs() {
+        let context = compile(PROGRAM, &registry(), &[Pass::ConstantFolding]);
+        assert_eq!(context.to_string(), indoc! {r#"
+            function 0 Main.Main (access 1): fn(Int) -> (Int, Int)
+                %0 = int 2: Int
+                %1 = int 3: Int
+                %2 = int 6: Int
+                %3 = int 1: Int
+                %4 = int 7: Int
+                %5 = int 14: Int
+                %6 = external Twice (%4): Int
+                %7 = operator Add (%5, $0): Int
+                %8 = int 1: Int
+                %9 = int 0: Int
+                %10 = operator Divide (%8, %9): Int
+                %11 = tuple (%7, %10): (Int, Int)
+                result = %11
+        "#});
+    }
+
+
+    #[test]
+    fn remove_dead_nodes() {
+        let registry = registry();
+        let context = compile(PROGRAM, &registry, &[Pass::DeadNodeElimination]);
+        assert_eq!(context.to_string(), indoc! {r#"
+            function 0 Main.Main (access 1): fn(Int) -> (Int, Int)
+                %0 = int 2: Int
+                %1 = int 3: Int
+                %2 = operator Multiply (%0, %1): Int
+                %3 = int 1: Int
+                %4 = operator Add (%2, %3): Int
+                %5 = external Double (%4): Int
+                %6 = operator Add (%5, $0): Int
+                %7 = int 1: Int
+                %8 = int 0: Int
+                %9 = operator Divide (%7, %8): Int
+                %10 = tuple (%6, %9): (Int, Int)
+                result = %10
+        "#});
+
+        let function = &context.get_functions()[0];
+        assert_eq!(function.get_variable("twice"), None);
+        let value = function.get_variable("value").unwrap();
+        assert_eq!(value.get_value(), &IRNodeInput::HiddenNode(6));
+
+        let context = compile(PROGRAM, &registry, &Pass::ALL);
+        assert_eq!(context.to_string(), indoc! {r#"
+            function 0 Main.Main (access 1): fn(Int) -> (Int, Int)
+                %0 = int 14: Int
+                %1 = operator Add (%0, $0): Int
+                %2 = int 1: Int
+                %3 = int 0: Int
+                %4 = operator Divide (%2, %3): Int
+                %5 = tuple (%1, %4): (Int, Int)
+                result = %5
+        "#});
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn keep_serial_calls() {
+        let registry = registry();
+        let context = compile(
+            indoc! {r#"
+                Main = mod {
+                    Main = serial function {
+                        params = ()
+                        return = (value: Int)
+
+                        unused = extern Twice(4)
+                        serial extern Twice(1)
+                        value = 2 + 3
+                    }
+                }
+            "#},
+            &registry,
+            &Pass::ALL,
+        );
+
+        assert_eq!(context.to_string(), indoc! {r#"
+            serial function 0 Main.Main (access 1): fn() -> Int
+                %0 = int 1: Int
+                %1 = serial external Twice (%0): Int
+                %2 = int 5: Int
+                result = %2 after %1
+        "#});
+
+        let bytecode = bytecode_from_ir(context, &registry);
+        let scheduler = JobScheduler::new().into_async();
+        multithreading::build_workers(&scheduler, 2);
+        let output = vm::evaluate(&scheduler, vm::link(&bytecode)[0].clone()).complete();
+        scheduler.terminate_workers();
+        assert_eq!(*output, Data::Int(5));
+    }
+
+
+    #[test]
+    fn parse_pass_names() {
+        assert_eq!("constant-folding".parse(), Ok(Pass::ConstantFolding));
+        assert_eq!(Pass::DeadNodeElimination.to_string().parse(), Ok(Pass::DeadNodeElimination));
+        assert_eq!(
+            "inline".parse::<Pass>(),
+            Err(String::from(
+                "unknown pass 'inline', expected one of: constant-folding, dead-node-elimination"
+            ))
+        );
+    }
+}
@@ -0,0 +1,700 @@
+//! A compiler pass that resolves named references between elements within an
+//! intermediate representation context.
+
+
+use super::errors::{CompilerError, IRError};
+use super::ir::{IRContext, IRDataType, IRFuncCall, IRFunction, IRNode, IRStruct};
+use crate::compiler::ast::NodePosition;
+
+
+/// Resolves all named references to structs and internal functions within the
+/// given context.
+///
+/// Names are resolved using lexical scoping, relative to the element the
+/// reference is made from. Elements nested within the referencing element are
+/// searched first, followed by the elements nested within each enclosing
+/// function and module, moving outwards until the root of the context is
+/// reached. Names may be qualified with the names of the modules or functions
+/// they are nested within, such as `Vector.Length`.
+///
+/// Unresolved data types are replaced with the struct they refer to, and each
+/// unresolved function call is replaced with a pointer to the internal function
+/// it refers to. Struct constructions are replaced with the struct they build,
+/// with their inputs reordered to match the declaration order of the struct
+/// fields.
+///
+/// If an element cannot be found, or the element is not accessible from the
+/// referencing element, an error is recorded and resolution continues with the
+/// remaining references. Data types that cannot be resolved are replaced with
+/// an unknown data type, and function calls that cannot be resolved are left
+/// unresolved. If any errors are found, then all of them are returned, in the
+/// order they were found.
+pub fn resolve_context(context: &mut IRContext) -> Result<(), Vec<CompilerError>> {
+    let mut errors = vec![];
+    resolve_struct_types(context, &mut errors);
+    resolve_function_types(context, &mut errors);
+    resolve_function_calls(context, &mut errors);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+
+/// Records the given error, unless an identical error has already been
+/// recorded. The same unresolvable struct field is reported once for every
+/// data type that contains it, so duplicates are expected.
+fn report(errors: &mut Vec<CompilerError>, error: CompilerError) {
+    let exists = errors.iter().any(|e| {
+        e.get_position() == error.get_position() && e.to_string() == error.to_string()
+    });
+
+    if !exists {
+        errors.push(error);
+    }
+}
+
+
+fn resolve_struct_types(context: &mut IRContext, errors: &mut Vec<CompilerError>) {
+    let structs = context.get_structs().clone();
+
+    for structure in context.get_structs_mut() {
+        let scope = structure.path().clone();
+        let position = structure.get_position().clone();
+
+        for (_, dtype) in structure.get_fields_mut() {
+            let mut visiting = vec![scope.clone()];
+            *dtype = match resolve_data_type(&structs, dtype, &scope, &position, &mut visiting) {
+                Ok(dtype) => dtype,
+                Err(e) => {
+                    report(errors, e);
+                    IRDataType::Unknown
+                },
+            };
+        }
+    }
+}
+
+
+fn resolve_function_types(context: &mut IRContext, errors: &mut Vec<CompilerError>) {
+    let structs = context.get_structs().clone();
+
+    for function in context.get_functions_mut() {
+        let scope = function.path().clone();
+        let position = function.get_position().clone();
+
+        let mut resolve = |dtype: &IRDataType| {
+            match resolve_data_type(&structs, dtype, &scope, &position, &mut vec![]) {
+                Ok(dtype) => dtype,
+                Err(e) => {
+                    report(errors, e);
+                    IRDataType::Unknown
+                },
+            }
+        };
+
+        let inputs = function.get_inputs().iter().map(&mut resolve).collect();
+        let output = resolve(function.get_output());
+
+        function.set_signature(inputs, output);
+    }
+}
+
+
+fn resolve_function_calls(context: &mut IRContext, errors: &mut Vec<CompilerError>) {
+    let structs = context.get_structs().clone();
+    let functions = context.get_functions().clone();
+    let function_paths: Vec<Vec<String>> = functions.iter().map(|f| f.path().clone()).collect();
+
+    for function in context.get_functions_mut() {
+        let scope = function.path().clone();
+
+        for statement in function.get_statements_mut() {
+            let position = statement.get_position().clone();
+
+            let result = match statement.get_function().clone() {
+                IRFuncCall::Unresolved(name) => {
+                    resolve_function(&functions, &function_paths, &scope, &name, &position).map(
+                        |index| {
+                            let output = functions[index].get_output().clone();
+                            statement.set_function(IRFuncCall::Internal(index), output);
+                        },
+                    )
+                },
+                IRFuncCall::UnresolvedStruct(..) => resolve_struct_init(&structs, statement, &scope),
+                function => {
+                    match function.get_target() {
+                        Some(IRFuncCall::Unresolved(name)) => {
+                            resolve_function(&functions, &function_paths, &scope, name, &position)
+                                .map(|index| {
+                                    let target =
+                                        statement.get_function_mut().get_target_mut().unwrap();
+                                    *target = IRFuncCall::Internal(index);
+                                })
+                        },
+                        _ => Ok(()),
+                    }
+                },
+            };
+
+            if let Err(e) = result {
+                report(errors, e);
+            }
+        }
+    }
+}
+
+
+/// Finds the index of the internal function with the given name, as seen from
+/// the given scope.
+///
+/// If the function cannot be found, or is not accessible from the given scope,
+/// then an error is returned.
+fn resolve_function(
+    functions: &[IRFunction], function_paths: &[Vec<String>], scope: &[String], name: &str,
+    position: &NodePosition,
+) -> Result<usize, CompilerError> {
+    let index = match find_in_scope(function_paths, scope, name) {
+        Some(index) => index,
+        None => {
+            return Err(CompilerError::new(
+                position.clone(),
+                IRError::UnknownIdentifier(name.to_owned()),
+            ))
+        },
+    };
+
+    let callee = &functions[index];
+    if !callee.is_accessible_from(scope) {
+        return Err(CompilerError::new(
+            position.clone(),
+            IRError::InaccessibleIdentifier(scope.join("."), callee.path().join(".")),
+        ));
+    }
+
+    Ok(index)
+}
+
+
+/// Resolves the struct that is constructed by the given node, as seen from the
+/// given scope, and reorders the node inputs to match the order the fields are
+/// declared within the struct.
+///
+/// Every field within the struct must be assigned exactly once.
+fn resolve_struct_init(
+    structs: &[IRStruct], statement: &mut IRNode, scope: &[String],
+) -> Result<(), CompilerError> {
+    let (name, field_names) = match statement.get_function() {
+        IRFuncCall::UnresolvedStruct(name, field_names) => (name.clone(), field_names.clone()),
+        _ => return Ok(()),
+    };
+
+    let position = statement.get_position().clone();
+    let unresolved = IRDataType::Unresolved(name);
+    let dtype = resolve_data_type(structs, &unresolved, scope, &position, &mut vec![])?;
+
+    let (struct_name, fields) = match &dtype {
+        IRDataType::Struct(struct_name, fields) => (struct_name.clone(), fields),
+        _ => unreachable!("Unresolved data types always resolve to structs"),
+    };
+
+    for (index, field_name) in field_names.iter().enumerate() {
+        if field_names[..index].contains(field_name) {
+            return Err(CompilerError::new(
+                position,
+                IRError::IdentifierAlreadyExists(field_name.clone()),
+            ));
+        }
+
+        if !fields.iter().any(|(name, _)| name == field_name) {
+            return Err(CompilerError::new(
+                position,
+                IRError::UnknownField(struct_name, field_name.clone()),
+            ));
+        }
+    }
+
+    let mut inputs = vec![];
+    for (field_name, _) in fields {
+        match field_names.iter().position(|name| name == field_name) {
+            Some(index) => inputs.push(statement.get_inputs()[index].clone()),
+            None => {
+                return Err(CompilerError::new(
+                    position,
+                    IRError::MissingField(struct_name, field_name.clone()),
+                ))
+            },
+        }
+    }
+
+    statement.set_inputs(inputs);
+    statement.set_function(IRFuncCall::Struct(struct_name), dtype.clone());
+    Ok(())
+}
+
+
+/// Resolves all unresolved struct names within the given data type, as seen
+/// from the given scope.
+///
+/// The visiting list contains the paths of all structs that are currently
+/// being resolved, and is used to detect structs that contain themselves.
+fn resolve_data_type(
+    structs: &[IRStruct], dtype: &IRDataType, scope: &[String], position: &NodePosition,
+    visiting: &mut Vec<Vec<String>>,
+) -> Result<IRDataType, CompilerError> {
+    let mut resolve = |dtype: &IRDataType| -> Result<Box<IRDataType>, CompilerError> {
+        Ok(Box::new(resolve_data_type(
+            structs, dtype, scope, position, visiting,
+        )?))
+    };
+
+    Ok(match dtype {
+        IRDataType::Unresolved(name) => {
+            let struct_paths: Vec<Vec<String>> = structs.iter().map(|s| s.path().clone()).collect();
+
+            let structure = match find_in_scope(&struct_paths, scope, name) {
+                Some(index) => &structs[index],
+                None => {
+                    return Err(CompilerError::new(
+                        position.clone(),
+                        IRError::UnknownIdentifier(name.clone()),
+                    ))
+                },
+            };
+
+            let struct_name = structure.path().join(".");
+            if !structure.is_accessible_from(scope) {
+                return Err(CompilerError::new(
+                    position.clone(),
+                    IRError::InaccessibleIdentifier(scope.join("."), struct_name),
+                ));
+            }
+
+            if visiting.contains(structure.path()) {
+                return Err(CompilerError::new(
+                    position.clone(),
+                    IRError::RecursiveStruct(struct_name),
+                ));
+            }
+
+            visiting.push(structure.path().clone());
+            let struct_scope = structure.path();
+            let struct_position = structure.get_position();
+
+            let mut fields = vec![];
+            for (field_name, field_type) in structure.get_fields() {
+                let field_type = resolve_data_type(
+                    structs,
+                    field_type,
+                    struct_scope,
+                    struct_position,
+                    visiting,
+                )?;
+                fields.push((field_name.clone(), field_type));
+            }
+
+            visiting.pop();
+            IRDataType::Struct(struct_name, fields)
+        },
+        IRDataType::List(e) => IRDataType::List(resolve(e)?),
+        IRDataType::Array(e, len) => IRDataType::Array(resolve(e)?, *len),
+        IRDataType::Option(e) => IRDataType::Option(resolve(e)?),
+        IRDataType::Result(e) => IRDataType::Result(resolve(e)?),
+        IRDataType::Dictionary(k, v) => IRDataType::Dictionary(resolve(k)?, resolve(v)?),
+        IRDataType::Tuple(elements) => {
+            let mut resolved = vec![];
+            for element in elements {
+                resolved.push(*resolve(element)?);
+            }
+            IRDataType::Tuple(resolved)
+        },
+        IRDataType::Function(params, output) => {
+            let mut resolved = vec![];
+            for param in params {
+                resolved.push(*resolve(param)?);
+            }
+            IRDataType::Function(resolved, resolve(output)?)
+        },
+        other => other.clone(),
+    })
+}
+
+
+/// Searches for the element with the given, possibly qualified, name that is
+/// visible from within the given scope. The index of the closest matching
+/// element path is returned.
+///
+/// If there is no element visible from the given scope with the given name,
+/// then None is returned.
+fn find_in_scope(paths: &[Vec<String>], scope: &[String], name: &str) -> Option<usize> {
+    let name: Vec<&str> = name.split('.').collect();
+
+    for depth in (0..=scope.len()).rev() {
+        let index = paths.iter().position(|path| {
+            path.len() == depth + name.len()
+                && path[..depth] == scope[..depth]
+                && path[depth..].iter().zip(&name).all(|(a, b)| a == b)
+        });
+
+        if index.is_some() {
+            return index;
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::grammar::parse;
+    use crate::compiler::ir::{compile_context, IRDataType, IRNodeInput};
+    use crate::registry::FunctionRegistry;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+
+    fn compile(source: &str) -> Result<IRContext, Vec<CompilerError>> {
+        let registry = FunctionRegistry::new();
+        let mut context = compile_context(parse(source).unwrap(), &registry)?;
+        resolve_context(&mut context)?;
+        Ok(context)
+    }
+
+
+    fn function_index(context: &IRContext, path: &[&str]) -> usize {
+        context.get_functions().iter().position(|f| f.path().eq(path)).unwrap()
+    }
+
+
+    fn called_function<'a>(context: &'a IRContext, path: &[&str]) -> &'a IRFuncCall {
+        let index = function_index(context, path);
+        let statements = context.get_functions()[index].get_statements();
+        statements.last().unwrap().get_function()
+    }
+
+
+    #[test]
+    fn resolve_lexical_scope() {
+        let context = compile(indoc! {r#"
+            Math = mod {
+                Vector = export mod {
+                    Length = export function {
+                        params = ()
+                        return = (value: Float)
+
+                        value = 1.0
+                    }
+                }
+
+                Helper = function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = 1
+                }
+
+                Nested = function {
+                    params = ()
+                    return = (value: Int)
+
+                    Helper = function {
+                        params = ()
+                        return = (value: Bool)
+
+                        value = true
+                    }
+
+                    value = Helper()
+                }
+
+                Sibling = function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = Helper()
+                }
+
+                Qualified = function {
+                    params = ()
+                    return = (value: Float)
+
+                    value = Vector.Length()
+                }
+            }
+
+            Other = mod {
+                Main = function {
+                    params = ()
+                    return = (value: Float)
+
+                    value = Math.Vector.Length()
+                }
+            }
+        "#})
+        .unwrap();
+
+        let length = function_index(&context, &["Math", "Vector", "Length"]);
+        let helper = function_index(&context, &["Math", "Helper"]);
+        let nested_helper = function_index(&context, &["Math", "Nested", "Helper"]);
+
+        assert_eq!(
+            called_function(&context, &["Math", "Nested"]),
+            &IRFuncCall::Internal(nested_helper)
+        );
+        assert_eq!(
+            called_function(&context, &["Math", "Sibling"]),
+            &IRFuncCall::Internal(helper)
+        );
+        assert_eq!(
+            called_function(&context, &["Math", "Qualified"]),
+            &IRFuncCall::Internal(length)
+        );
+        assert_eq!(
+            called_function(&context, &["Other", "Main"]),
+            &IRFuncCall::Internal(length)
+        );
+
+        let index = function_index(&context, &["Math", "Nested"]);
+        let statements = context.get_functions()[index].get_statements();
+        assert_eq!(statements[0].get_output(), &IRDataType::Bool);
+    }
+
+
+    #[test]
+    fn unknown_function() {
+        let result = compile(indoc! {r#"
+            Math = mod {
+                Main = function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = Missing(1)
+                }
+            }
+        "#});
+
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Cannot find element 'Missing' within the current scope, at 6:17"
+        );
+    }
+
+
+    #[test]
+    fn private_function_is_inaccessible() {
+        let source = indoc! {r#"
+            Math = mod {
+                Secret = function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = 1
+                }
+
+                Public = export function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = Secret()
+                }
+            }
+
+            Other = mod {
+                Main = function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = Math.CALLEE()
+                }
+            }
+        "#};
+
+        assert!(compile(&source.replace("CALLEE", "Public")).is_ok());
+
+        let errors = compile(&source.replace("CALLEE", "Secret")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Element 'Math.Secret' is not accessible from 'Other.Main', at 22:17"
+        );
+    }
+
+
+    #[test]
+    fn resolve_struct_types() {
+        let context = compile(indoc! {r#"
+            Geometry = mod {
+                Point = export struct {
+                    x: Float
+                    y: Float
+                }
+
+                Line = export struct {
+                    a: Point
+                    b: Point
+                }
+            }
+
+            Main = mod {
+                Start = function {
+                    params = (line: Geometry.Line)
+                    return = (point: Geometry.Point)
+
+                    point = Start(line)
+                }
+            }
+        "#})
+        .unwrap();
+
+        let point = IRDataType::Struct(String::from("Geometry.Point"), vec![
+            (String::from("x"), IRDataType::Float),
+            (String::from("y"), IRDataType::Float),
+        ]);
+        let line = IRDataType::Struct(String::from("Geometry.Line"), vec![
+            (String::from("a"), point.clone()),
+            (String::from("b"), point.clone()),
+        ]);
+
+        let index = function_index(&context, &["Main", "Start"]);
+        let function = &context.get_functions()[index];
+        assert_eq!(function.get_inputs(), &vec![line]);
+        assert_eq!(function.get_output(), &point);
+        assert_eq!(function.get_statements()[0].get_output(), &point);
+    }
+
+
+    #[test]
+    fn private_struct_is_inaccessible() {
+        let errors = compile(indoc! {r#"
+            Geometry = mod {
+                Point = struct {
+                    x: Float
+                    y: Float
+                }
+            }
+
+            Main = mod {
+                Origin = function {
+                    params = ()
+                    return = (point: Geometry.Point)
+
+                    point = Origin()
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Element 'Geometry.Point' is not accessible from 'Main.Origin', at 9:5"
+        );
+    }
+
+
+    #[test]
+    fn recursive_struct() {
+        let errors = compile(indoc! {r#"
+            Lists = mod {
+                Node = struct {
+                    value: Int
+                    next: Node
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Struct 'Lists.Node' cannot contain itself, at 2:5"
+        );
+    }
+
+
+    #[test]
+    fn struct_construction_in_declaration_order() {
+        let context = compile(indoc! {r#"
+            Geometry = mod {
+                Point = struct {
+                    x: Float
+                    y: Float
+                }
+
+                Origin = function {
+                    params = ()
+                    return = (point: Point)
+
+                    point = Point { y = 2.0, x = 1.0 }
+                }
+            }
+        "#})
+        .unwrap();
+
+        let index = function_index(&context, &["Geometry", "Origin"]);
+        let statements = context.get_functions()[index].get_statements();
+        assert_eq!(
+            statements[0].get_function(),
+            &IRFuncCall::FloatConstant(2.0)
+        );
+        assert_eq!(
+            statements[1].get_function(),
+            &IRFuncCall::FloatConstant(1.0)
+        );
+        assert_eq!(
+            statements[2].get_function(),
+            &IRFuncCall::Struct(String::from("Geometry.Point"))
+        );
+        assert_eq!(statements[2].get_inputs(), &vec![
+            IRNodeInput::HiddenNode(1),
+            IRNodeInput::HiddenNode(0),
+        ]);
+    }
+
+
+    #[test]
+    fn struct_construction_fields_must_match() {
+        let source = indoc! {r#"
+            Geometry = mod {
+                Point = struct {
+                    x: Float
+                    y: Float
+                }
+
+                Origin = function {
+                    params = ()
+                    return = (point: Point)
+
+                    point = Point { FIELDS }
+                }
+            }
+        "#};
+
+        let errors = compile(&source.replace("FIELDS", "x = 1.0")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Struct 'Geometry.Point' is missing a value for field 'y', at 11:17"
+        );
+
+        let errors = compile(&source.replace("FIELDS", "x = 1.0, y = 2.0, z = 3.0")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Type 'Geometry.Point' does not have a field named 'z', at 11:17"
+        );
+
+        let errors = compile(&source.replace("FIELDS", "x = 1.0, x = 2.0")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: An element with the name 'x' already exists, at 11:17"
+        );
+    }
+}
@@ -0,0 +1,391 @@
+//! A driver for loading Vertex programs that are split across multiple source
+//! files.
+//!
+//! Each loaded source file is assigned a file id, which is stored within the
+//! position of every node parsed from that file. Once all files are loaded,
+//! their modules are merged into a single context node that can be passed to
+//! the compiler.
+
+
+use super::ast::ContextNode;
+use super::diagnostics::{Diagnostic, Severity};
+use super::grammar::parse_file;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+/// The file extension of Vertex source files.
+pub const SOURCE_EXTENSION: &str = "vx";
+
+
+/// A single source file that has been loaded into a source set.
+#[derive(Debug)]
+pub struct SourceFile {
+    path:      PathBuf,
+    canonical: PathBuf,
+    source:    String,
+    context:   ContextNode,
+}
+
+impl SourceFile {
+    /// Gets the path of this source file, as it was given when loaded or
+    /// imported.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+
+    /// Gets the source code of this file.
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+
+    /// Gets the abstract syntax tree that was parsed from this file.
+    pub fn get_context(&self) -> &ContextNode {
+        &self.context
+    }
+}
+
+
+/// A collection of loaded source files, where the id of each file is it's
+/// index within the collection.
+#[derive(Debug, Default)]
+pub struct SourceSet {
+    files: Vec<SourceFile>,
+}
+
+impl SourceSet {
+    /// Creates a new, empty source set.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+
+    /// Gets the source file with the given file id, if it exists.
+    pub fn get_file(&self, file: usize) -> Option<&SourceFile> {
+        self.files.get(file)
+    }
+
+
+    /// Gets a list of all source files within this source set, ordered by
+    /// their file ids.
+    pub fn get_files(&self) -> &Vec<SourceFile> {
+        &self.files
+    }
+
+
+    /// Loads the source file at the given path, along with all of the files
+    /// that it imports, and returns the file id of the given file.
+    ///
+    /// Files that have already been loaded are not loaded again.
+    pub fn load_file(&mut self, path: &Path) -> Result<usize, Diagnostic> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => return Err(read_error(path, e)),
+        };
+
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(file) = self.find_file(&canonical) {
+            return Ok(file);
+        }
+
+        self.add_file(path.to_path_buf(), canonical, source)
+    }
+
+
+    /// Loads the given source code as if it were read from the given path,
+    /// along with all of the files that it imports, and returns the file id
+    /// of the new file.
+    ///
+    /// Imports are resolved relative to the directory of the given path.
+    pub fn add_source(&mut self, path: &Path, source: String) -> Result<usize, Diagnostic> {
+        self.add_file(path.to_path_buf(), path.to_path_buf(), source)
+    }
+
+
+    /// Loads all source files within the given directory and all of it's
+    /// subdirectories, along with all of the files that they import.
+    ///
+    /// Files are loaded in order of their paths, so that file ids are stable
+    /// between runs.
+    pub fn load_directory(&mut self, dir: &Path) -> Result<(), Diagnostic> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => return Err(read_error(dir, e)),
+        };
+
+        let mut paths = vec![];
+        for entry in entries {
+            match entry {
+                Ok(entry) => paths.push(entry.path()),
+                Err(e) => return Err(read_error(dir, e)),
+            }
+        }
+        paths.sort();
+
+        for path in paths {
+            if path.is_dir() {
+                self.load_directory(&path)?;
+            } else if path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION) {
+                self.load_file(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Merges the modules of all loaded source files into a single context
+    /// node, ordered by file id.
+    ///
+    /// If a root-level module with the same name is defined more than once,
+    /// then an error is returned.
+    pub fn merge(&self) -> Result<ContextNode, Diagnostic> {
+        let mut context = ContextNode {
+            imports: vec![],
+            modules: vec![],
+        };
+
+        for file in &self.files {
+            for module in &file.context.modules {
+                if let Some(other) = context.modules.iter().find(|m| m.name == module.name) {
+                    let mut diagnostic = Diagnostic::new(
+                        Severity::Error,
+                        Some("E0004"),
+                        format!("Module '{}' is defined in more than one place", module.name),
+                        Some(module.position.clone()),
+                    );
+
+                    diagnostic.add_note(format!(
+                        "'{}' is first defined in {} at {}:{}",
+                        other.name,
+                        self.files[other.position.file].path.display(),
+                        other.position.line,
+                        other.position.col
+                    ));
+
+                    return Err(diagnostic);
+                }
+
+                context.modules.push(module.clone());
+            }
+
+            context.imports.extend(file.context.imports.iter().cloned());
+        }
+
+        Ok(context)
+    }
+
+
+    /// Finds the id of the loaded file with the given canonical path.
+    fn find_file(&self, canonical: &Path) -> Option<usize> {
+        self.files.iter().position(|file| file.canonical == canonical)
+    }
+
+
+    /// Parses the given source code as a new file, and then loads all of the
+    /// files imported by it.
+    ///
+    /// The file is kept within this source set even if it cannot be parsed, so
+    /// that the returned diagnostic can be rendered with the source code it
+    /// points at.
+    fn add_file(
+        &mut self, path: PathBuf, canonical: PathBuf, source: String,
+    ) -> Result<usize, Diagnostic> {
+        let file = self.files.len();
+        self.files.push(SourceFile {
+            path: path.clone(),
+            canonical,
+            source,
+            context: ContextNode {
+                imports: vec![],
+                modules: vec![],
+            },
+        });
+
+        let context = parse_file(&self.files[file].source, file)?;
+        let imports = context.imports.clone();
+        self.files[file].context = context;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for import in imports {
+            let import_path = dir.join(&import.path);
+            if let Err(e) = fs::metadata(&import_path) {
+                return Err(Diagnostic::new(
+                    Severity::Error,
+                    Some("E0003"),
+                    format!("Cannot import {}: {}", import_path.display(), e),
+                    Some(import.position),
+                ));
+            }
+
+            self.load_file(&import_path)?;
+        }
+
+        Ok(file)
+    }
+}
+
+
+/// Creates a diagnostic for a file or directory that could not be read.
+fn read_error(path: &Path, error: std::io::Error) -> Diagnostic {
+    let message = format!("Cannot read {}: {}", path.display(), error);
+    Diagnostic::new(Severity::Error, Some("E0002"), message, None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::ast::NodePosition;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+
+    /// Writes the given files into a new, empty temporary directory, and
+    /// returns the path of that directory.
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vertex-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+
+        for (path, source) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+
+        dir
+    }
+
+
+    #[test]
+    fn load_directory() {
+        let dir = write_files("load_directory", &[
+            ("main.vx", indoc! {r#"
+                Main = mod {
+                    Main = function {
+                        params = ()
+                        return = ()
+                    }
+                }
+            "#}),
+            ("math/vector.vx", indoc! {r#"
+                Vector = mod {
+                }
+            "#}),
+            ("notes.txt", "Not a source file."),
+        ]);
+
+        let mut sources = SourceSet::new();
+        sources.load_directory(&dir).unwrap();
+        let context = sources.merge().unwrap();
+
+        assert_eq!(sources.get_files().len(), 2);
+        assert_eq!(sources.get_file(0).unwrap().get_path(), dir.join("main.vx"));
+        assert_eq!(
+            sources.get_file(1).unwrap().get_path(),
+            dir.join("math/vector.vx")
+        );
+
+        let names: Vec<&str> = context.modules.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["Main", "Vector"]);
+        assert_eq!(context.modules[1].position, NodePosition {
+            file:     1,
+            line:     1,
+            col:      1,
+            end_line: 2,
+            end_col:  2,
+        });
+        assert_eq!(context.modules[0].functions[0].position.file, 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+
+    #[test]
+    fn follow_imports() {
+        let dir = write_files("follow_imports", &[
+            ("app/main.vx", indoc! {r#"
+                import "../lib/math.vx"
+                import "../lib/strings.vx"
+
+                Main = mod {
+                }
+            "#}),
+            ("lib/math.vx", indoc! {r#"
+                import "strings.vx"
+
+                Math = mod {
+                }
+            "#}),
+            ("lib/strings.vx", indoc! {r#"
+                Strings = mod {
+                }
+            "#}),
+        ]);
+
+        let mut sources = SourceSet::new();
+        sources.load_file(&dir.join("app/main.vx")).unwrap();
+        let context = sources.merge().unwrap();
+
+        let names: Vec<&str> = context.modules.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["Main", "Math", "Strings"]);
+        assert_eq!(context.imports.len(), 3);
+
+        let error = sources.load_file(&dir.join("lib/missing.vx")).unwrap_err();
+        assert_eq!(error.get_code(), Some("E0002"));
+        assert_eq!(error.get_position(), None);
+
+        let source = String::from("import \"missing.vx\"\n");
+        let error = sources.add_source(&dir.join("app/other.vx"), source).unwrap_err();
+        assert_eq!(error.get_code(), Some("E0003"));
+        assert_eq!(error.get_position().unwrap().file, 3);
+        assert!(error.get_message().starts_with(&format!(
+            "Cannot import {}: ",
+            dir.join("app/missing.vx").display()
+        )));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+
+    #[test]
+    fn duplicate_modules() {
+        let dir = write_files("duplicate_modules", &[
+            ("a.vx", indoc! {r#"
+                Math = mod {
+                }
+            "#}),
+            ("b.vx", indoc! {r#"
+                Strings = mod {
+                }
+
+                Math = mod {
+                }
+            "#}),
+        ]);
+
+        let mut sources = SourceSet::new();
+        sources.load_directory(&dir).unwrap();
+
+        let error = sources.merge().unwrap_err();
+        assert_eq!(
+            error.render(&sources),
+            format!(
+                indoc! {"
+                error[E0004]: Module 'Math' is defined in more than one place
+                 --> {}:4:1
+                  |
+                4 | Math = mod {{
+                  | ^^^^^^^^^^^^
+                  = note: 'Math' is first defined in {} at 1:1
+            "},
+                dir.join("b.vx").display(),
+                dir.join("a.vx").display()
+            )
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
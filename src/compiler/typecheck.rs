@@ -0,0 +1,1257 @@
+//! A compiler pass that infers the data type of every node within an
+//! intermediate representation context, and verifies that all values are used
+//! with the data types they are expected to have.
+
+
+use super::ast::Operator;
+use super::errors::{CompilerError, IRError};
+use super::ir::{IRContext, IRDataType, IRFuncCall, IRFunction, IRNode, IRNodeInput};
+use crate::registry::FunctionRegistry;
+
+
+/// The input and output data types of an internal function, along with it's
+/// name.
+struct Signature {
+    name:   String,
+    inputs: Vec<IRDataType>,
+    output: IRDataType,
+}
+
+
+/// Infers the output data type of every node within the given context and
+/// checks that all function calls and returned values are correctly typed.
+///
+/// Each function call must be given the number of arguments that function
+/// expects, where each argument matches the data type of the respective
+/// function parameter. Each returned variable must match the data type that
+/// was declared for it.
+///
+/// If a mismatch is found, an error is recorded and the node is given an
+/// unknown data type. Nodes that read from such a node, or that could not be
+/// resolved by an earlier pass, are skipped without reporting further errors.
+/// If any errors are found, then all of them are returned, in the order they
+/// were found.
+///
+/// Field reads are resolved into the index of the field within the struct that
+/// is being read from.
+///
+/// The data types of the variables captured by each anonymous function are
+/// inferred from the closure that captures them.
+///
+/// This pass expects all references within the context to have already been
+/// resolved.
+pub fn check_types(
+    context: &mut IRContext, function_registry: &FunctionRegistry,
+) -> Result<(), Vec<CompilerError>> {
+    let mut errors = vec![];
+    let signatures: Vec<Signature> = context
+        .get_functions()
+        .iter()
+        .map(|function| {
+            Signature {
+                name:   function.path().join("."),
+                inputs: function.get_inputs().clone(),
+                output: function.get_output().clone(),
+            }
+        })
+        .collect();
+
+    // Anonymous functions are always loaded after the function that defines
+    // them, so the data types of their captured variables are known before
+    // they are checked.
+    let mut captures: Vec<Option<Vec<IRDataType>>> = vec![None; signatures.len()];
+
+    for (function_index, function) in context.get_functions_mut().iter_mut().enumerate() {
+        if let Some(captured) = captures[function_index].take() {
+            let mut inputs = function.get_inputs().clone();
+            inputs[..captured.len()].clone_from_slice(&captured);
+            let output = function.get_output().clone();
+            function.set_signature(inputs, output);
+        }
+
+        let params = function.get_inputs().clone();
+        let statements = function.get_statements_mut();
+        let mut failed = vec![false; statements.len()];
+
+        for index in 0..statements.len() {
+            // Each variable of a destructuring assignment reads from the same
+            // tuple, so a mismatch is only reported for the first variable.
+            let node = &statements[index];
+            let skip = is_unresolved(node.get_function())
+                || node.get_inputs().iter().any(|input| {
+                    matches!(input, IRNodeInput::HiddenNode(node) if failed[*node as usize])
+                })
+                || matches!(node.get_function(), IRFuncCall::Element(..))
+                    && statements[..index].iter().zip(&failed).any(|(other, &failed)| {
+                        failed
+                            && matches!(other.get_function(), IRFuncCall::Element(..))
+                            && other.get_inputs() == node.get_inputs()
+                    });
+
+            if skip {
+                failed[index] = true;
+                statements[index].set_output(IRDataType::Unknown);
+                continue;
+            }
+
+            let arguments: Vec<IRDataType> = statements[index]
+                .get_inputs()
+                .iter()
+                .map(|input| input_type(input, &params, statements))
+                .collect();
+
+            // Operators and field reads cannot be checked against a value of
+            // unknown data type, which is only left behind by an earlier error.
+            let unknown = arguments.contains(&IRDataType::Unknown)
+                && matches!(
+                    statements[index].get_function(),
+                    IRFuncCall::Operator(_) | IRFuncCall::UnresolvedField(_)
+                );
+
+            if unknown {
+                failed[index] = true;
+                statements[index].set_output(IRDataType::Unknown);
+                continue;
+            }
+
+            if let IRFuncCall::UnresolvedField(name) = statements[index].get_function() {
+                match resolve_field(&statements[index], name, &arguments) {
+                    Ok((field, output)) => {
+                        statements[index].set_function(IRFuncCall::Field(field), output);
+                    },
+                    Err(e) => {
+                        errors.push(e);
+                        failed[index] = true;
+                        statements[index].set_output(IRDataType::Unknown);
+                    },
+                }
+                continue;
+            }
+
+            let output = match infer_node_type(
+                &statements[index],
+                &arguments,
+                &signatures,
+                function_registry,
+            ) {
+                Ok(output) => output,
+                Err(e) => {
+                    errors.push(e);
+                    failed[index] = true;
+                    statements[index].set_output(IRDataType::Unknown);
+                    continue;
+                },
+            };
+            statements[index].set_output(output);
+
+            if let IRFuncCall::Closure(target) = statements[index].get_function() {
+                if let (IRFuncCall::Internal(target), false) = (&**target, arguments.is_empty()) {
+                    captures[*target] = Some(arguments);
+                }
+            }
+        }
+
+        check_returns(function, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+
+/// Checks whether or not the given function call, or the function it applies,
+/// still refers to an internal function or struct by name. These references
+/// are reported by the resolver when they cannot be resolved.
+fn is_unresolved(function: &IRFuncCall) -> bool {
+    matches!(
+        function,
+        IRFuncCall::Unresolved(_) | IRFuncCall::UnresolvedStruct(..)
+    ) || matches!(function.get_target(), Some(IRFuncCall::Unresolved(_)))
+}
+
+
+/// Gets the data type of the value provided by the given node input.
+fn input_type(input: &IRNodeInput, params: &[IRDataType], statements: &[IRNode]) -> IRDataType {
+    match input {
+        IRNodeInput::FunctionParam(index) => params[*index as usize].clone(),
+        IRNodeInput::HiddenNode(index) => statements[*index as usize].get_output().clone(),
+    }
+}
+
+
+/// Determines the output data type of the given node, based off the data types
+/// of it's arguments.
+fn infer_node_type(
+    node: &IRNode, arguments: &[IRDataType], signatures: &[Signature],
+    function_registry: &FunctionRegistry,
+) -> Result<IRDataType, CompilerError> {
+    match node.get_function() {
+        IRFuncCall::IntConstant(_) => Ok(IRDataType::Int),
+        IRFuncCall::FloatConstant(_) => Ok(IRDataType::Float),
+        IRFuncCall::StringConstant(_) => Ok(IRDataType::String),
+        IRFuncCall::CharConstant(_) => Ok(IRDataType::Char),
+        IRFuncCall::BoolConstant(_) => Ok(IRDataType::Bool),
+        IRFuncCall::Internal(index) => {
+            let signature = &signatures[*index];
+            check_arguments(node, &signature.name, &signature.inputs, arguments)?;
+            Ok(signature.output.clone())
+        },
+        IRFuncCall::External(name) => {
+            match function_registry.get_function(name) {
+                Some(func_meta) => {
+                    check_arguments(node, name, func_meta.get_inputs(), arguments)?;
+                    Ok(func_meta.get_output().clone())
+                },
+                None => {
+                    Err(CompilerError::new(
+                        node.get_position().clone(),
+                        IRError::UnknownIdentifier(name.clone()),
+                    ))
+                },
+            }
+        },
+        IRFuncCall::Struct(name) => {
+            let fields = match node.get_output() {
+                IRDataType::Struct(_, fields) => fields,
+                dtype => unreachable!("Struct constructed as non-struct type '{}'", dtype),
+            };
+
+            for ((field_name, expected), found) in fields.iter().zip(arguments) {
+                if !types_match(expected, found) {
+                    return Err(CompilerError::new(
+                        node.get_position().clone(),
+                        IRError::FieldTypeMismatch(
+                            name.clone(),
+                            field_name.clone(),
+                            expected.to_string(),
+                            found.to_string(),
+                        ),
+                    ));
+                }
+            }
+
+            Ok(node.get_output().clone())
+        },
+        IRFuncCall::Operator(operator) => {
+            match operator_type(*operator, arguments) {
+                Some(output) => Ok(output),
+                None if arguments.len() == 1 => {
+                    Err(CompilerError::new(
+                        node.get_position().clone(),
+                        IRError::InvalidOperand(operator.to_string(), arguments[0].to_string()),
+                    ))
+                },
+                None => {
+                    Err(CompilerError::new(
+                        node.get_position().clone(),
+                        IRError::InvalidOperands(
+                            operator.to_string(),
+                            arguments[0].to_string(),
+                            arguments[1].to_string(),
+                        ),
+                    ))
+                },
+            }
+        },
+        IRFuncCall::Select => {
+            if !types_match(&arguments[0], &IRDataType::Bool) {
+                return Err(CompilerError::new(
+                    node.get_position().clone(),
+                    IRError::ConditionTypeMismatch(arguments[0].to_string()),
+                ));
+            }
+
+            if !types_match(&arguments[1], &arguments[2]) {
+                return Err(CompilerError::new(
+                    node.get_position().clone(),
+                    IRError::BranchTypeMismatch(arguments[1].to_string(), arguments[2].to_string()),
+                ));
+            }
+
+            match &arguments[1] {
+                IRDataType::Unknown => Ok(arguments[2].clone()),
+                dtype => Ok(dtype.clone()),
+            }
+        },
+        IRFuncCall::Closure(target) => {
+            match &**target {
+                IRFuncCall::Internal(index) => {
+                    let signature = &signatures[*index];
+                    let params = signature.inputs[arguments.len()..].to_vec();
+                    Ok(IRDataType::Function(
+                        params,
+                        Box::new(signature.output.clone()),
+                    ))
+                },
+                IRFuncCall::Unresolved(name) => {
+                    Err(CompilerError::new(
+                        node.get_position().clone(),
+                        IRError::UnknownIdentifier(name.clone()),
+                    ))
+                },
+                _ => Ok(node.get_output().clone()),
+            }
+        },
+        IRFuncCall::Invoke(name) => {
+            let signature = function_signature(node, name, &arguments[0], arguments.len() - 1)?;
+            check_arguments(node, name, &signature.inputs, &arguments[1..])?;
+            Ok(signature.output)
+        },
+        IRFuncCall::Map(_)
+        | IRFuncCall::Filter(_)
+        | IRFuncCall::Reduce(_)
+        | IRFuncCall::Fold(_) => check_higher_order(node, arguments, signatures),
+        IRFuncCall::BuildList => {
+            let element = check_elements(node, arguments.iter())?;
+            Ok(IRDataType::List(Box::new(element)))
+        },
+        IRFuncCall::BuildTuple => Ok(IRDataType::Tuple(arguments.to_vec())),
+        IRFuncCall::BuildDictionary => {
+            let key = check_elements(node, arguments.iter().step_by(2))?;
+            let value = check_elements(node, arguments.iter().skip(1).step_by(2))?;
+            Ok(IRDataType::Dictionary(Box::new(key), Box::new(value)))
+        },
+        IRFuncCall::Field(index) => {
+            match &arguments[0] {
+                IRDataType::Struct(_, fields) => Ok(fields[*index].1.clone()),
+                dtype => unreachable!("Field read from non-struct type '{}'", dtype),
+            }
+        },
+        IRFuncCall::Element(index, count) => {
+            match &arguments[0] {
+                IRDataType::Tuple(elements) if elements.len() == *count => {
+                    Ok(elements[*index].clone())
+                },
+                IRDataType::Unknown => Ok(IRDataType::Unknown),
+                dtype => {
+                    Err(CompilerError::new(
+                        node.get_position().clone(),
+                        IRError::DestructureMismatch(dtype.to_string(), *count),
+                    ))
+                },
+            }
+        },
+        IRFuncCall::Unresolved(name)
+        | IRFuncCall::UnresolvedStruct(name, _)
+        | IRFuncCall::UnresolvedField(name) => {
+            Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::UnknownIdentifier(name.clone()),
+            ))
+        },
+    }
+}
+
+
+/// Finds the index and data type of the field with the given name, within the
+/// struct that is provided as the only argument to the given node.
+///
+/// If the argument is not a struct, or the struct does not have a field with
+/// the given name, then an error is returned.
+fn resolve_field(
+    node: &IRNode, name: &str, arguments: &[IRDataType],
+) -> Result<(usize, IRDataType), CompilerError> {
+    let fields = match &arguments[0] {
+        IRDataType::Struct(_, fields) => fields,
+        dtype => {
+            return Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::UnknownField(dtype.to_string(), name.to_owned()),
+            ))
+        },
+    };
+
+    match fields.iter().position(|(field_name, _)| field_name == name) {
+        Some(index) => Ok((index, fields[index].1.clone())),
+        None => {
+            Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::UnknownField(arguments[0].to_string(), name.to_owned()),
+            ))
+        },
+    }
+}
+
+
+/// Checks that the function applied by the given higher-order function call
+/// accepts the elements of the list input, and returns the output data type of
+/// the call.
+fn check_higher_order(
+    node: &IRNode, arguments: &[IRDataType], signatures: &[Signature],
+) -> Result<IRDataType, CompilerError> {
+    let function = node.get_function();
+    let name = match function {
+        IRFuncCall::Map(_) => "map",
+        IRFuncCall::Filter(_) => "filter",
+        IRFuncCall::Reduce(_) => "reduce",
+        _ => "fold",
+    };
+
+    let dynamic;
+    let signature = match function.get_target() {
+        Some(IRFuncCall::Internal(index)) => &signatures[*index],
+        Some(IRFuncCall::Invoke(target)) => {
+            let count = if matches!(function, IRFuncCall::Map(_) | IRFuncCall::Filter(_)) {
+                1
+            } else {
+                2
+            };
+
+            dynamic = function_signature(node, target, arguments.last().unwrap(), count)?;
+            &dynamic
+        },
+        Some(IRFuncCall::Unresolved(target)) => {
+            return Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::UnknownIdentifier(target.clone()),
+            ))
+        },
+        target => unreachable!("Unexpected higher-order target {:?}", target),
+    };
+
+    let element = match &arguments[0] {
+        IRDataType::List(element) => (**element).clone(),
+        dtype => {
+            return Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::ExpectedList(name.to_owned(), dtype.to_string()),
+            ))
+        },
+    };
+
+    let check_output = |expected: &IRDataType| {
+        if types_match(expected, &signature.output) {
+            Ok(())
+        } else {
+            Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::FunctionOutputMismatch(
+                    signature.name.clone(),
+                    expected.to_string(),
+                    signature.output.to_string(),
+                ),
+            ))
+        }
+    };
+
+    match function {
+        IRFuncCall::Map(_) => {
+            check_arguments(node, &signature.name, &signature.inputs, &[element])?;
+            Ok(IRDataType::List(Box::new(signature.output.clone())))
+        },
+        IRFuncCall::Filter(_) => {
+            check_arguments(
+                node,
+                &signature.name,
+                &signature.inputs,
+                std::slice::from_ref(&element),
+            )?;
+            check_output(&IRDataType::Bool)?;
+            Ok(IRDataType::List(Box::new(element)))
+        },
+        IRFuncCall::Reduce(_) => {
+            let inputs = [element.clone(), element];
+            check_arguments(node, &signature.name, &signature.inputs, &inputs)?;
+            check_output(&inputs[0])?;
+            Ok(signature.output.clone())
+        },
+        _ => {
+            let inputs = [arguments[1].clone(), element];
+            check_arguments(node, &signature.name, &signature.inputs, &inputs)?;
+            check_output(&inputs[0])?;
+            Ok(signature.output.clone())
+        },
+    }
+}
+
+
+/// Gets the signature of the function value with the given name and data type.
+///
+/// If the data type of the function value is unknown, then the signature is
+/// assumed to accept the given number of arguments of any data type. If the
+/// value is not a function, then an error is returned.
+fn function_signature(
+    node: &IRNode, name: &str, dtype: &IRDataType, arguments: usize,
+) -> Result<Signature, CompilerError> {
+    match dtype {
+        IRDataType::Function(inputs, output) => {
+            Ok(Signature {
+                name:   name.to_owned(),
+                inputs: inputs.clone(),
+                output: (**output).clone(),
+            })
+        },
+        IRDataType::Unknown => {
+            Ok(Signature {
+                name:   name.to_owned(),
+                inputs: vec![IRDataType::Unknown; arguments],
+                output: IRDataType::Unknown,
+            })
+        },
+        dtype => {
+            Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::NotAFunction(name.to_owned(), dtype.to_string()),
+            ))
+        },
+    }
+}
+
+
+/// Gets the output data type of applying the given operator to operands of the
+/// given data types.
+///
+/// Arithmetic operators accept any combination of Int and Float operands. If
+/// both operands are Ints, the output is an Int, otherwise the Int operand is
+/// promoted and the output is a Float. Strings may be concatenated with `+`.
+/// Ints, Floats, Strings and Chars may be ordered, and any two values of the
+/// same data type may be checked for equality.
+///
+/// If the operator cannot be applied to the given data types, then None is
+/// returned.
+fn operator_type(operator: Operator, operands: &[IRDataType]) -> Option<IRDataType> {
+    use IRDataType::{Bool, Char, Float, Int, String};
+
+    match (operator, operands) {
+        (Operator::Negate, [Int]) => Some(Int),
+        (Operator::Negate, [Float]) => Some(Float),
+        (Operator::Not, [Bool]) => Some(Bool),
+        (Operator::And | Operator::Or, [Bool, Bool]) => Some(Bool),
+
+        (Operator::Add, [String, String]) => Some(String),
+        (
+            Operator::Add
+            | Operator::Subtract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::Remainder,
+            [a, b],
+        ) => {
+            match (a, b) {
+                (Int, Int) => Some(Int),
+                (Int | Float, Int | Float) => Some(Float),
+                _ => None,
+            }
+        },
+
+        (Operator::Equal | Operator::NotEqual, [a, b]) => {
+            match (a, b) {
+                (Int | Float, Int | Float) => Some(Bool),
+                (a, b) if types_match(a, b) => Some(Bool),
+                _ => None,
+            }
+        },
+
+        (
+            Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual,
+            [a, b],
+        ) => {
+            match (a, b) {
+                (Int | Float, Int | Float) | (String, String) | (Char, Char) => Some(Bool),
+                _ => None,
+            }
+        },
+
+        _ => None,
+    }
+}
+
+
+/// Checks that all of the given collection elements are of the same data type,
+/// and returns that data type. If there are no elements, then the element type
+/// is unknown.
+fn check_elements<'a>(
+    node: &IRNode, mut elements: impl Iterator<Item = &'a IRDataType>,
+) -> Result<IRDataType, CompilerError> {
+    let first = match elements.next() {
+        Some(first) => first,
+        None => return Ok(IRDataType::Unknown),
+    };
+
+    for element in elements {
+        if !types_match(first, element) {
+            return Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::ElementTypeMismatch(first.to_string(), element.to_string()),
+            ));
+        }
+    }
+
+    Ok(first.clone())
+}
+
+
+/// Checks whether or not the two data types are the same. An unknown data
+/// type, such as the element type of an empty list, matches any data type.
+fn types_match(a: &IRDataType, b: &IRDataType) -> bool {
+    match (a, b) {
+        (IRDataType::Unknown, _) | (_, IRDataType::Unknown) => true,
+        (IRDataType::List(a), IRDataType::List(b)) => types_match(a, b),
+        (IRDataType::Option(a), IRDataType::Option(b)) => types_match(a, b),
+        (IRDataType::Result(a), IRDataType::Result(b)) => types_match(a, b),
+        (IRDataType::Array(a, len_a), IRDataType::Array(b, len_b)) => {
+            len_a == len_b && types_match(a, b)
+        },
+        (IRDataType::Dictionary(key_a, value_a), IRDataType::Dictionary(key_b, value_b)) => {
+            types_match(key_a, key_b) && types_match(value_a, value_b)
+        },
+        (IRDataType::Tuple(a), IRDataType::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| types_match(a, b))
+        },
+        (IRDataType::Function(params_a, a), IRDataType::Function(params_b, b)) => {
+            params_a.len() == params_b.len()
+                && params_a.iter().zip(params_b).all(|(a, b)| types_match(a, b))
+                && types_match(a, b)
+        },
+        (a, b) => a == b,
+    }
+}
+
+
+/// Checks that the given arguments match the number and data types of the
+/// expected function parameters.
+fn check_arguments(
+    node: &IRNode, name: &str, params: &[IRDataType], arguments: &[IRDataType],
+) -> Result<(), CompilerError> {
+    if params.len() != arguments.len() {
+        return Err(CompilerError::new(
+            node.get_position().clone(),
+            IRError::IncorrectArgumentCount(name.to_owned(), params.len(), arguments.len()),
+        ));
+    }
+
+    for (index, (param, argument)) in params.iter().zip(arguments).enumerate() {
+        if !types_match(param, argument) {
+            return Err(CompilerError::new(
+                node.get_position().clone(),
+                IRError::ArgumentTypeMismatch(
+                    name.to_owned(),
+                    index + 1,
+                    param.to_string(),
+                    argument.to_string(),
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Checks that each returned variable within the function matches the data type
+/// that was declared for it, recording an error for each variable that does
+/// not.
+fn check_returns(function: &IRFunction, errors: &mut Vec<CompilerError>) {
+    let declared = match function.get_output() {
+        IRDataType::Tuple(elements) if function.get_returns().len() > 1 => elements.clone(),
+        output => vec![output.clone()],
+    };
+
+    for (name, expected) in function.get_returns().iter().zip(declared) {
+        let variable = match function.get_variable(name) {
+            Some(variable) => variable,
+            None => continue,
+        };
+
+        let found = input_type(
+            variable.get_value(),
+            function.get_inputs(),
+            function.get_statements(),
+        );
+
+        if !types_match(&expected, &found) {
+            errors.push(CompilerError::new(
+                variable.get_position().clone(),
+                IRError::ReturnTypeMismatch(name.clone(), expected.to_string(), found.to_string()),
+            ));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::grammar::parse;
+    use crate::compiler::ir::compile_context_partial;
+    use crate::compiler::resolver::resolve_context;
+    use crate::data::Data;
+    use crate::registry::FuncMeta;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+
+    fn compile(source: &str) -> Result<IRContext, Vec<CompilerError>> {
+        fn external_function(_: Vec<Arc<Data>>) -> Data {
+            // Implementation is not important.
+            unimplemented!();
+        }
+
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register(
+                FuncMeta::new(
+                    String::from("Length"),
+                    external_function,
+                    vec![IRDataType::String],
+                    IRDataType::Int,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let (mut context, mut errors) = compile_context_partial(parse(source).unwrap(), &registry);
+        if let Err(e) = resolve_context(&mut context) {
+            errors.extend(e);
+        }
+        if let Err(e) = check_types(&mut context, &registry) {
+            errors.extend(e);
+        }
+
+        if errors.is_empty() {
+            Ok(context)
+        } else {
+            Err(errors)
+        }
+    }
+
+
+    #[test]
+    fn well_typed_program() {
+        let context = compile(indoc! {r#"
+            Text = mod {
+                Size = function {
+                    params = (text: String)
+                    return = (size: Int)
+
+                    size = extern Length(text)
+                }
+
+                Main = function {
+                    params = ()
+                    return = (size: Int)
+
+                    text = "Hello"
+                    size = Size(text)
+                }
+            }
+        "#})
+        .unwrap();
+
+        let main = context.get_function(&vec![String::from("Text"), String::from("Main")]);
+        let statements = main.unwrap().get_statements();
+        assert_eq!(statements[0].get_output(), &IRDataType::String);
+        assert_eq!(statements[1].get_output(), &IRDataType::Int);
+    }
+
+
+    #[test]
+    fn argument_type_mismatch() {
+        let errors = compile(indoc! {r#"
+            Text = mod {
+                Main = function {
+                    params = ()
+                    return = (size: Int)
+
+                    size = extern Length(12)
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Argument 1 of function 'Length' expects type 'String', but found 'Int', at \
+             6:16"
+        );
+    }
+
+
+    #[test]
+    fn argument_count_mismatch() {
+        let errors = compile(indoc! {r#"
+            Text = mod {
+                Size = function {
+                    params = (text: String)
+                    return = (size: Int)
+
+                    size = extern Length(text)
+                }
+
+                Main = function {
+                    params = ()
+                    return = (size: Int)
+
+                    size = Size("a", "b")
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Function 'Text.Size' expects 1 argument(s), but 2 were given, at 13:16"
+        );
+    }
+
+
+    #[test]
+    fn report_multiple_errors() {
+        let errors = compile(indoc! {r#"
+            Text = mod {
+                Main = function {
+                    params = (point: Missing)
+                    return = (size: Int, text: String)
+
+                    a = extern Length(1)
+                    b = a + 1
+                    c = point.x + 1
+                    d = Unknown(b)
+                    size = "Hello" * 2
+                    text = 5
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages, vec![
+            "Error: Cannot find element 'Missing' within the current scope, at 2:5",
+            "Error: Cannot find element 'Unknown' within the current scope, at 9:13",
+            "Error: Argument 1 of function 'Length' expects type 'String', but found 'Int', at \
+             6:13",
+            "Error: Operator '*' cannot be applied to types 'String' and 'Int', at 10:24",
+            "Error: Return value 'text' expects type 'String', but found 'Int', at 11:9",
+        ]);
+    }
+
+
+    #[test]
+    fn return_type_mismatch() {
+        let errors = compile(indoc! {r#"
+            Text = mod {
+                Main = function {
+                    params = ()
+                    return = (size: Int)
+
+                    size = "Hello"
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Return value 'size' expects type 'Int', but found 'String', at 6:9"
+        );
+    }
+
+
+    #[test]
+    fn nested_field_access() {
+        let context = compile(indoc! {r#"
+            Geometry = mod {
+                Point = struct {
+                    x: Float
+                    y: Float
+                }
+
+                Line = struct {
+                    a: Point
+                    b: Point
+                }
+
+                EndY = function {
+                    params = (line: Line)
+                    return = (y: Float)
+
+                    y = line.b.y
+                }
+            }
+        "#})
+        .unwrap();
+
+        let function = context.get_function(&vec![String::from("Geometry"), String::from("EndY")]);
+        let statements = function.unwrap().get_statements();
+        assert_eq!(statements[0].get_function(), &IRFuncCall::Field(1));
+        assert_eq!(statements[0].get_inputs(), &vec![
+            IRNodeInput::FunctionParam(0)
+        ]);
+        assert_eq!(statements[1].get_function(), &IRFuncCall::Field(1));
+        assert_eq!(statements[1].get_inputs(), &vec![IRNodeInput::HiddenNode(
+            0
+        )]);
+        assert_eq!(statements[1].get_output(), &IRDataType::Float);
+    }
+
+
+    #[test]
+    fn unknown_field() {
+        let errors = compile(indoc! {r#"
+            Geometry = mod {
+                Point = struct {
+                    x: Float
+                    y: Float
+                }
+
+                Depth = function {
+                    params = (point: Point)
+                    return = (z: Float)
+
+                    z = point.z
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Type 'Geometry.Point' does not have a field named 'z', at 11:13"
+        );
+    }
+
+
+    #[test]
+    fn struct_field_type_mismatch() {
+        let errors = compile(indoc! {r#"
+            Geometry = mod {
+                Point = struct {
+                    x: Float
+                    y: Float
+                }
+
+                Origin = function {
+                    params = ()
+                    return = (point: Point)
+
+                    point = Point { x = 0.0, y = 0 }
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Field 'y' of struct 'Geometry.Point' expects type 'Float', but found 'Int', \
+             at 11:17"
+        );
+    }
+
+
+    #[test]
+    fn collection_literal_types() {
+        let context = compile(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = ()
+                    return = (result: ({String: Int[]}, (Int, Bool)))
+
+                    numbers = [1, 2, 3]
+                    lengths = {"a": numbers, "b": []}
+                    result = (lengths, (4, true))
+                }
+            }
+        "#})
+        .unwrap();
+
+        let main = context.get_function(&vec![String::from("Main"), String::from("Main")]);
+        let statements = main.unwrap().get_statements();
+        let numbers = IRDataType::List(Box::new(IRDataType::Int));
+        assert_eq!(statements[3].get_output(), &numbers);
+        assert_eq!(
+            statements[7].get_output(),
+            &IRDataType::Dictionary(Box::new(IRDataType::String), Box::new(numbers))
+        );
+    }
+
+
+    #[test]
+    fn list_element_type_mismatch() {
+        let errors = compile(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = ()
+                    return = (values: Int[])
+
+                    values = [1, "two", 3]
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Collection elements expect type 'Int', but found 'String', at 6:18"
+        );
+    }
+
+
+    #[test]
+    fn operator_types() {
+        let context = compile(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = (a: Int, b: Float)
+                    return = (sum: Float, check: Bool)
+
+                    sum = a * 2 + b
+                    check = not (a < 3 or "x" == "y")
+                }
+            }
+        "#})
+        .unwrap();
+
+        let main = context.get_function(&vec![String::from("Main"), String::from("Main")]);
+        let statements = main.unwrap().get_statements();
+        assert_eq!(statements[1].get_output(), &IRDataType::Int);
+        assert_eq!(statements[2].get_output(), &IRDataType::Float);
+
+        let errors = compile(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = (a: Int)
+                    return = (value: Int)
+
+                    value = a + "1"
+                }
+            }
+        "#})
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Operator '+' cannot be applied to types 'Int' and 'String', at 6:19"
+        );
+    }
+
+
+    #[test]
+    fn conditional_types() {
+        let source = indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = (a: Int)
+                    return = (value: Int)
+
+                    value = if CONDITION then a else ELSE
+                }
+            }
+        "#};
+
+        assert!(compile(&source.replace("CONDITION", "a > 0").replace("ELSE", "0")).is_ok());
+
+        let errors = compile(&source.replace("CONDITION", "a").replace("ELSE", "0")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Condition expects type 'Bool', but found 'Int', at 6:17"
+        );
+
+        let errors =
+            compile(&source.replace("CONDITION", "true").replace("ELSE", "0.5")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Branches of conditional expression have different types 'Int' and 'Float', \
+             at 6:17"
+        );
+    }
+
+
+    #[test]
+    fn higher_order_types() {
+        let source = indoc! {r#"
+            Main = mod {
+                Square = function {
+                    params = (x: Int)
+                    return = (value: Int)
+
+                    value = x * x
+                }
+
+                IsEven = function {
+                    params = (x: Int)
+                    return = (value: Bool)
+
+                    value = x % 2 == 0
+                }
+
+                Add = function {
+                    params = (a: Int, b: Int)
+                    return = (value: Int)
+
+                    value = a + b
+                }
+
+                Main = function {
+                    params = (items: Int[])
+                    return = (value: Int)
+
+                    value = CALL
+                }
+            }
+        "#};
+
+        let compile_call = |call: &str| compile(&source.replace("CALL", call));
+        assert!(compile_call("reduce(map(filter(items, IsEven), Square), Add)").is_ok());
+        assert!(compile_call("fold(map(filter(items, IsEven), Square), 0, Add)").is_ok());
+
+        let errors = compile_call("reduce(items, IsEven)").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Function 'Main.IsEven' expects 1 argument(s), but 2 were given, at 27:17"
+        );
+
+        let errors = compile_call("reduce(filter(items, Square), Square)").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Function 'Main.Square' must return type 'Bool', but returns 'Int', at 27:24"
+        );
+
+        let errors = compile_call("reduce(5, Square)").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Function 'reduce' expects a list, but found 'Int', at 27:17"
+        );
+    }
+
+
+    #[test]
+    fn function_value_types() {
+        let source = indoc! {r#"
+            Main = mod {
+                Apply = function {
+                    params = (x: Int, f: fn(Int) -> Int)
+                    return = (value: Int)
+
+                    value = f(x)
+                }
+
+                Negate = function {
+                    params = (x: Int)
+                    return = (value: Int)
+
+                    value = 0 - x
+                }
+
+                Main = function {
+                    params = (offset: Int)
+                    return = (value: Int)
+
+                    shifted = Apply(1, function {
+                        params = (x: Int)
+                        return = (value: Int)
+
+                        value = x + offset
+                    })
+                    value = CALL
+                }
+            }
+        "#};
+
+        let compile_call = |call: &str| compile(&source.replace("CALL", call));
+        assert!(compile_call("Apply(shifted, Negate)").is_ok());
+        assert!(compile_call("reduce(map([1, 2], Negate), extern Length)").is_err());
+
+        let errors = compile_call("Apply(shifted, extern Length)").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Argument 2 of function 'Main.Apply' expects type 'fn(Int) -> Int', but found \
+             'fn(String) -> Int', at 26:17"
+        );
+
+        let errors = compile_call("offset(1)").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Cannot call 'offset' of type 'Int', as it is not a function, at 26:17"
+        );
+    }
+
+
+    #[test]
+    fn nested_capturing_lambdas() {
+        let context = compile(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = (offset: Int)
+                    return = (value: Int[])
+
+                    value = map([1, 2], function {
+                        params = (x: Int)
+                        return = (y: Int)
+
+                        y = fold([x], offset, function {
+                            params = (total: Int, n: Int)
+                            return = (sum: Int)
+
+                            sum = total + n + offset
+                        })
+                    })
+                }
+            }
+        "#})
+        .unwrap();
+
+        let signatures: Vec<String> = context
+            .get_functions()
+            .iter()
+            .map(|f| format!("{}: {:?}", f.path().join("."), f.get_inputs()))
+            .collect();
+        assert_eq!(signatures, vec![
+            "Main.Main: [Int]",
+            "Main.Main.lambda@6:29: [Int, Int]",
+            "Main.Main.lambda@6:29.lambda@10:35: [Int, Int, Int]",
+        ]);
+    }
+
+
+    #[test]
+    fn destructuring_types() {
+        let source = indoc! {r#"
+            Main = mod {
+                DivMod = function {
+                    params = (a: Int, b: Int)
+                    return = (quotient: Int, remainder: Int)
+
+                    quotient = a / b
+                    remainder = a % b
+                }
+
+                Main = function {
+                    params = ()
+                    return = (value: Int)
+
+                    VARIABLES = DivMod(7, 2)
+                    value = q
+                }
+            }
+        "#};
+
+        let compile_variables = |variables: &str| compile(&source.replace("VARIABLES", variables));
+        assert!(compile_variables("q, r").is_ok());
+
+        let errors = compile_variables("q, r, s").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Error: Cannot assign type '(Int, Int)' to 3 variables, as it is not a tuple of 3 \
+             elements, at 14:9"
+        );
+    }
+}
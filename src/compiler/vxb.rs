@@ -0,0 +1,1242 @@
+//! Reads and writes Vertex bytecode using the versioned `.vxb` binary format,
+//! allowing programs to be compiled once and shipped without their source
+//! code.
+//!
+//! A bytecode file contains, in order:
+//!
+//! - The magic bytes `VXB\0`, followed by the format version as a `u16`.
+//! - The constant pool, containing every constant data value.
+//! - The names of all struct types that are constructed.
+//! - The external function table, containing the name and signature of each
+//!   external function. Function pointers are never written, as they are only
+//!   valid within the process that created them.
+//! - The internal function table, containing the identifier path, parameter
+//!   count, operations, and output of each internal function.
+//!
+//! All integers are written in little-endian byte order. Lists are written as
+//! a `u32` length followed by their elements, and strings are written as a list
+//! of UTF-8 bytes.
+
+
+use super::assembly::format_call;
+use super::ast::Operator;
+use super::bytecode::{
+    ExternalFunction, FunctionCall, InternalFunction, Operation, OperationInput, VertexBytecode,
+};
+use super::ir::IRDataType;
+use crate::data::{Data, StructData};
+use crate::registry::FunctionRegistry;
+use thiserror::Error;
+
+
+/// The magic bytes at the start of every bytecode file.
+pub const MAGIC: &[u8; 4] = b"VXB\0";
+
+/// The version of the bytecode format that is written by this module. Files
+/// written with any other version cannot be loaded.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// The file extension of Vertex bytecode files.
+pub const BYTECODE_EXTENSION: &str = "vxb";
+
+
+/// Every built-in operator, where the index of each operator is it's code
+/// within a bytecode file.
+pub(super) const OPERATORS: [Operator; 15] = [
+    Operator::Add,
+    Operator::Subtract,
+    Operator::Multiply,
+    Operator::Divide,
+    Operator::Remainder,
+    Operator::Equal,
+    Operator::NotEqual,
+    Operator::Less,
+    Operator::LessEqual,
+    Operator::Greater,
+    Operator::GreaterEqual,
+    Operator::And,
+    Operator::Or,
+    Operator::Not,
+    Operator::Negate,
+];
+
+
+/// An error that occurred while reading or writing a bytecode file.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BytecodeFileError {
+    /// The file does not start with the bytecode magic bytes.
+    #[error("Not a Vertex bytecode file")]
+    InvalidMagic,
+
+    /// The file was written with a different version of the format.
+    #[error("Unsupported bytecode format version {0}, expected version {1}")]
+    UnsupportedVersion(u16, u16),
+
+    /// The file ends before all of it's contents could be read.
+    #[error("Bytecode file ends unexpectedly")]
+    UnexpectedEnd,
+
+    /// The file contains a value that is not valid within the format.
+    #[error("Invalid bytecode: {0}")]
+    InvalidData(String),
+
+    /// A value within the bytecode cannot be written to a file.
+    #[error("Cannot write bytecode: {0}")]
+    Unserializable(String),
+
+    /// An external function used by the bytecode is not within the function
+    /// registry.
+    #[error("External function '{0}' is not registered")]
+    MissingExtern(String),
+
+    /// An external function used by the bytecode has a different signature
+    /// within the function registry than when the bytecode was compiled.
+    #[error("External function '{0}' was compiled as '{1}', but is registered as '{2}'")]
+    ExternSignatureChanged(String, String, String),
+}
+
+
+/// Writes the given bytecode into the bytecode file format.
+///
+/// Constant function values cannot be written, as they may point to external
+/// functions. The compiler never creates such constants.
+pub fn write_bytecode(bytecode: &VertexBytecode) -> Result<Vec<u8>, BytecodeFileError> {
+    let mut out = MAGIC.to_vec();
+    out.extend(FORMAT_VERSION.to_le_bytes());
+
+    write_len(&mut out, bytecode.get_constants().len())?;
+    for constant in bytecode.get_constants() {
+        write_data(&mut out, constant)?;
+    }
+
+    write_len(&mut out, bytecode.get_struct_types().len())?;
+    for struct_type in bytecode.get_struct_types() {
+        write_string(&mut out, struct_type)?;
+    }
+
+    write_len(&mut out, bytecode.get_external_functions().len())?;
+    for function in bytecode.get_external_functions() {
+        write_string(&mut out, function.get_function_name())?;
+        write_len(&mut out, function.get_inputs().len())?;
+        for input in function.get_inputs() {
+            write_data_type(&mut out, input)?;
+        }
+        write_data_type(&mut out, function.get_output())?;
+    }
+
+    write_len(&mut out, bytecode.get_internal_functions().len())?;
+    for function in bytecode.get_internal_functions() {
+        write_len(&mut out, function.get_path().len())?;
+        for name in function.get_path() {
+            write_string(&mut out, name)?;
+        }
+        write_len(&mut out, function.get_param_count())?;
+
+        write_len(&mut out, function.get_operations().len())?;
+        for operation in function.get_operations() {
+            write_function_call(&mut out, operation.get_function())?;
+            write_len(&mut out, operation.get_inputs().len())?;
+            for input in operation.get_inputs() {
+                write_input(&mut out, input)?;
+            }
+            write_index_option(&mut out, operation.get_after())?;
+        }
+
+        match function.get_output() {
+            Some(output) => {
+                out.push(1);
+                write_input(&mut out, output)?;
+            },
+            None => out.push(0),
+        }
+        write_index_option(&mut out, function.get_after())?;
+    }
+
+    Ok(out)
+}
+
+
+/// Reads bytecode from the bytecode file format, binding each external function
+/// to the function with the same name within the given function registry.
+///
+/// An error is returned if the file is not a valid bytecode file of the current
+/// format version, if an external function is not within the registry, or if
+/// the signature of an external function has changed since the bytecode was
+/// compiled. Values may be nested at most [`MAX_DEPTH`] levels deep.
+pub fn read_bytecode(
+    bytes: &[u8], registry: &FunctionRegistry,
+) -> Result<VertexBytecode, BytecodeFileError> {
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(BytecodeFileError::InvalidMagic);
+    }
+
+    let mut reader = Reader {
+        bytes,
+        offset: MAGIC.len(),
+        depth:  0,
+    };
+
+    let version = u16::from_le_bytes(reader.read_array()?);
+    if version != FORMAT_VERSION {
+        return Err(BytecodeFileError::UnsupportedVersion(version, FORMAT_VERSION));
+    }
+
+    let mut bytecode = VertexBytecode::new();
+
+    for _ in 0..reader.read_len()? {
+        bytecode.add_constant(reader.read_data()?);
+    }
+
+    for _ in 0..reader.read_len()? {
+        bytecode.add_struct_type(reader.read_string()?);
+    }
+
+    for _ in 0..reader.read_len()? {
+        let name = reader.read_string()?;
+        let inputs = reader.read_list(Reader::read_data_type)?;
+        let output = reader.read_data_type()?;
+        bytecode.add_external_function(bind_extern(registry, name, inputs, output)?);
+    }
+
+    for _ in 0..reader.read_len()? {
+        let mut function = InternalFunction::new();
+        function.set_path(reader.read_list(Reader::read_string)?);
+        function.set_param_count(reader.read_len()?);
+
+        for _ in 0..reader.read_len()? {
+            let function_call = reader.read_function_call()?;
+            let inputs = reader.read_list(Reader::read_input)?;
+            let mut operation = Operation::new(function_call, inputs);
+            operation.set_after(reader.read_index_option()?);
+            function.add_operation(operation);
+        }
+
+        let output = match reader.read_u8()? {
+            0 => None,
+            1 => Some(reader.read_input()?),
+            flag => return Err(invalid(format!("unknown output flag {}", flag))),
+        };
+        function.set_output(output);
+        function.set_after(reader.read_index_option()?);
+        bytecode.add_internal_function(function);
+    }
+
+    if reader.offset != bytes.len() {
+        return Err(invalid(String::from("unexpected data after the end of the bytecode")));
+    }
+
+    validate(&bytecode)?;
+    Ok(bytecode)
+}
+
+
+/// Finds the external function with the given name and signature within the
+/// given function registry.
+pub(super) fn bind_extern(
+    registry: &FunctionRegistry, name: String, inputs: Vec<IRDataType>, output: IRDataType,
+) -> Result<ExternalFunction, BytecodeFileError> {
+    let meta = match registry.get_function(&name) {
+        Some(meta) => meta,
+        None => return Err(BytecodeFileError::MissingExtern(name)),
+    };
+
+    if *meta.get_inputs() != inputs || *meta.get_output() != output {
+        let compiled = IRDataType::Function(inputs, Box::new(output)).to_string();
+        let registered = IRDataType::Function(
+            meta.get_inputs().clone(),
+            Box::new(meta.get_output().clone()),
+        )
+        .to_string();
+        return Err(BytecodeFileError::ExternSignatureChanged(name, compiled, registered));
+    }
+
+    Ok(ExternalFunction::new(name, meta.get_func(), inputs, output))
+}
+
+
+/// Checks that every index within the given bytecode points to an existing
+/// element, that every operation only reads the operations before it, and that
+/// every operation is given the number of inputs its function call expects, so
+/// that a damaged file cannot cause a panic when it is executed.
+pub(super) fn validate(bytecode: &VertexBytecode) -> Result<(), BytecodeFileError> {
+    let functions = bytecode.get_internal_functions();
+
+    for (function_index, function) in functions.iter().enumerate() {
+        let operations = function.get_operations();
+
+        // Nodes are created in order when a function is executed, so each
+        // operation may only read the operations that come before it. The
+        // output of the function may read any operation.
+        let check_input = |input: &OperationInput, before: usize| {
+            match *input {
+                OperationInput::Param(index) if index >= function.get_param_count() => {
+                    Err(invalid(format!(
+                        "function {} reads missing parameter {}",
+                        function_index, index
+                    )))
+                },
+                OperationInput::Hidden(index) if index >= operations.len() => {
+                    Err(invalid(format!(
+                        "function {} reads missing operation {}",
+                        function_index, index
+                    )))
+                },
+                OperationInput::Hidden(index) if index >= before => {
+                    Err(invalid(format!(
+                        "function {} operation {} reads operation {}, which is not before it",
+                        function_index, before, index
+                    )))
+                },
+                _ => Ok(()),
+            }
+        };
+
+        let check_after = |after: Option<usize>, before: usize| {
+            match after {
+                Some(index) if index >= operations.len() => {
+                    Err(invalid(format!(
+                        "function {} waits for missing operation {}",
+                        function_index, index
+                    )))
+                },
+                Some(index) if index >= before => {
+                    Err(invalid(format!(
+                        "function {} operation {} waits for operation {}, which is not before it",
+                        function_index, before, index
+                    )))
+                },
+                _ => Ok(()),
+            }
+        };
+
+        for (operation_index, operation) in operations.iter().enumerate() {
+            validate_call(bytecode, operation.get_function())?;
+            for input in operation.get_inputs() {
+                check_input(input, operation_index)?;
+            }
+            check_after(operation.get_after(), operation_index)?;
+
+            let count = operation.get_inputs().len();
+            validate_arity(bytecode, operation.get_function(), count).map_err(|message| {
+                invalid(format!(
+                    "function {} operation {} {}",
+                    function_index, operation_index, message
+                ))
+            })?;
+        }
+
+        function.get_output().map_or(Ok(()), |output| check_input(output, operations.len()))?;
+        check_after(function.get_after(), operations.len())?;
+    }
+
+    Ok(())
+}
+
+
+/// Checks that the given function call points to an existing element within the
+/// given bytecode.
+fn validate_call(bytecode: &VertexBytecode, call: &FunctionCall) -> Result<(), BytecodeFileError> {
+    let (kind, index, count) = match call {
+        FunctionCall::Internal(index) => {
+            ("internal function", *index, bytecode.get_internal_functions().len())
+        },
+        FunctionCall::External(index) => {
+            ("external function", *index, bytecode.get_external_functions().len())
+        },
+        FunctionCall::Constant(index) => ("constant", *index, bytecode.get_constants().len()),
+        FunctionCall::Struct(index) => ("struct type", *index, bytecode.get_struct_types().len()),
+        FunctionCall::Map(target)
+        | FunctionCall::Filter(target)
+        | FunctionCall::Reduce(target)
+        | FunctionCall::Fold(target)
+        | FunctionCall::Closure(target) => return validate_call(bytecode, target),
+        _ => return Ok(()),
+    };
+
+    if index >= count {
+        return Err(invalid(format!("missing {} {}", kind, index)));
+    }
+
+    Ok(())
+}
+
+
+/// Checks that the given function call can be executed with the given number
+/// of inputs. The given function call must point to existing elements.
+///
+/// Higher-order functions pass any inputs after their own to each call of
+/// their target function, before the list elements, so the target function is
+/// checked against the number of inputs it is called with.
+fn validate_arity(
+    bytecode: &VertexBytecode, call: &FunctionCall, count: usize,
+) -> Result<(), String> {
+    let expected = match call {
+        FunctionCall::Internal(index) => {
+            Some(bytecode.get_internal_functions()[*index].get_param_count())
+        },
+        FunctionCall::External(index) => {
+            Some(bytecode.get_external_functions()[*index].get_inputs().len())
+        },
+        FunctionCall::Constant(_) => Some(0),
+        FunctionCall::Field(_) | FunctionCall::Element(_) => Some(1),
+        FunctionCall::Operator(Operator::Not | Operator::Negate) => Some(1),
+        FunctionCall::Operator(_) => Some(2),
+        FunctionCall::Select => Some(3),
+        FunctionCall::BuildDictionary if !count.is_multiple_of(2) => {
+            return Err(format!("passes {} input(s) to dictionary, which expects pairs", count));
+        },
+        FunctionCall::Closure(target) => {
+            return match **target {
+                FunctionCall::Internal(_) | FunctionCall::External(_) => Ok(()),
+                _ => {
+                    Err(format!(
+                        "creates a closure of {}, which is not a function",
+                        format_call(target)
+                    ))
+                },
+            };
+        },
+        // Struct types are stored by name only, so the number of fields they
+        // are constructed with cannot be checked. Fields are bounds checked
+        // when they are read instead.
+        _ => None,
+    };
+
+    if let Some(expected) = expected {
+        if count != expected {
+            return Err(format!(
+                "passes {} input(s) to {}, which expects {}",
+                count,
+                format_call(call),
+                expected
+            ));
+        }
+        return Ok(());
+    }
+
+    // The minimum number of inputs, and the number of inputs each call of the
+    // target function is given beyond the extra inputs.
+    let (minimum, target, arguments) = match call {
+        FunctionCall::Map(target) | FunctionCall::Filter(target) => (1, Some(target), 1),
+        FunctionCall::Reduce(target) => (1, Some(target), 2),
+        FunctionCall::Fold(target) => (2, Some(target), 2),
+        FunctionCall::Invoke => (1, None, 0),
+        _ => (0, None, 0),
+    };
+
+    if count < minimum {
+        return Err(format!(
+            "passes {} input(s) to {}, which expects at least {}",
+            count,
+            format_call(call),
+            minimum
+        ));
+    }
+
+    match target {
+        Some(target) => validate_arity(bytecode, target, count - minimum + arguments),
+        None => Ok(()),
+    }
+}
+
+
+fn invalid(message: String) -> BytecodeFileError {
+    BytecodeFileError::InvalidData(message)
+}
+
+
+fn write_len(out: &mut Vec<u8>, len: usize) -> Result<(), BytecodeFileError> {
+    match u32::try_from(len) {
+        Ok(len) => {
+            out.extend(len.to_le_bytes());
+            Ok(())
+        },
+        Err(_) => Err(BytecodeFileError::Unserializable(format!("{} is too large", len))),
+    }
+}
+
+
+fn write_string(out: &mut Vec<u8>, text: &str) -> Result<(), BytecodeFileError> {
+    write_len(out, text.len())?;
+    out.extend(text.as_bytes());
+    Ok(())
+}
+
+
+fn write_index_option(out: &mut Vec<u8>, index: Option<usize>) -> Result<(), BytecodeFileError> {
+    match index {
+        Some(index) => {
+            out.push(1);
+            write_len(out, index)
+        },
+        None => {
+            out.push(0);
+            Ok(())
+        },
+    }
+}
+
+
+fn write_input(out: &mut Vec<u8>, input: &OperationInput) -> Result<(), BytecodeFileError> {
+    match input {
+        OperationInput::Param(index) => {
+            out.push(0);
+            write_len(out, *index)
+        },
+        OperationInput::Hidden(index) => {
+            out.push(1);
+            write_len(out, *index)
+        },
+    }
+}
+
+
+fn write_data_list(out: &mut Vec<u8>, data: &[Data]) -> Result<(), BytecodeFileError> {
+    write_len(out, data.len())?;
+    data.iter().try_for_each(|d| write_data(out, d))
+}
+
+
+fn write_data(out: &mut Vec<u8>, data: &Data) -> Result<(), BytecodeFileError> {
+    match data {
+        Data::Null => out.push(0),
+        Data::Int(value) => {
+            out.push(1);
+            out.extend(value.to_le_bytes());
+        },
+        Data::Float(value) => {
+            out.push(2);
+            out.extend(value.to_bits().to_le_bytes());
+        },
+        Data::String(value) => {
+            out.push(3);
+            write_string(out, value)?;
+        },
+        Data::Char(value) => {
+            out.push(4);
+            out.extend((*value as u32).to_le_bytes());
+        },
+        Data::Bool(value) => {
+            out.push(5);
+            out.push(*value as u8);
+        },
+        Data::Struct(value) => {
+            out.push(6);
+            write_string(out, &value.struct_type)?;
+            write_data_list(out, &value.fields)?;
+        },
+        Data::List(values) => {
+            out.push(7);
+            write_data_list(out, values)?;
+        },
+        Data::Array(values) => {
+            out.push(8);
+            write_data_list(out, values)?;
+        },
+        Data::Error(message) => {
+            out.push(9);
+            write_string(out, message)?;
+        },
+        Data::Option(value) => {
+            out.push(10);
+            write_data(out, value)?;
+        },
+        Data::Result(value) => {
+            out.push(11);
+            write_data(out, value)?;
+        },
+        Data::Tuple(values) => {
+            out.push(12);
+            write_data_list(out, values)?;
+        },
+        Data::Dictionary(keys, values) => {
+            out.push(13);
+            write_data_list(out, keys)?;
+            write_data_list(out, values)?;
+        },
+        Data::Function(_) => {
+            let message = String::from("function values cannot be stored as constants");
+            return Err(BytecodeFileError::Unserializable(message));
+        },
+    }
+
+    Ok(())
+}
+
+
+fn write_data_type(out: &mut Vec<u8>, dtype: &IRDataType) -> Result<(), BytecodeFileError> {
+    match dtype {
+        IRDataType::Unresolved(name) => {
+            let message = format!("data type '{}' is not resolved", name);
+            return Err(BytecodeFileError::Unserializable(message));
+        },
+        IRDataType::Unknown => {
+            let message = String::from("data type is not known");
+            return Err(BytecodeFileError::Unserializable(message));
+        },
+        IRDataType::Int => out.push(0),
+        IRDataType::Float => out.push(1),
+        IRDataType::String => out.push(2),
+        IRDataType::Char => out.push(3),
+        IRDataType::Bool => out.push(4),
+        IRDataType::Error => out.push(5),
+        IRDataType::Null => out.push(6),
+        IRDataType::List(element) => {
+            out.push(7);
+            write_data_type(out, element)?;
+        },
+        IRDataType::Array(element, len) => {
+            out.push(8);
+            write_data_type(out, element)?;
+            out.extend(len.to_le_bytes());
+        },
+        IRDataType::Option(element) => {
+            out.push(9);
+            write_data_type(out, element)?;
+        },
+        IRDataType::Result(element) => {
+            out.push(10);
+            write_data_type(out, element)?;
+        },
+        IRDataType::Tuple(elements) => {
+            out.push(11);
+            write_len(out, elements.len())?;
+            elements.iter().try_for_each(|e| write_data_type(out, e))?;
+        },
+        IRDataType::Dictionary(key, value) => {
+            out.push(12);
+            write_data_type(out, key)?;
+            write_data_type(out, value)?;
+        },
+        IRDataType::Struct(name, fields) => {
+            out.push(13);
+            write_string(out, name)?;
+            write_len(out, fields.len())?;
+            for (field, field_type) in fields {
+                write_string(out, field)?;
+                write_data_type(out, field_type)?;
+            }
+        },
+        IRDataType::Function(params, output) => {
+            out.push(14);
+            write_len(out, params.len())?;
+            params.iter().try_for_each(|p| write_data_type(out, p))?;
+            write_data_type(out, output)?;
+        },
+    }
+
+    Ok(())
+}
+
+
+fn write_function_call(out: &mut Vec<u8>, call: &FunctionCall) -> Result<(), BytecodeFileError> {
+    match call {
+        FunctionCall::Internal(index) => {
+            out.push(0);
+            write_len(out, *index)?;
+        },
+        FunctionCall::External(index) => {
+            out.push(1);
+            write_len(out, *index)?;
+        },
+        FunctionCall::Constant(index) => {
+            out.push(2);
+            write_len(out, *index)?;
+        },
+        FunctionCall::Field(index) => {
+            out.push(3);
+            write_len(out, *index)?;
+        },
+        FunctionCall::Element(index) => {
+            out.push(4);
+            write_len(out, *index)?;
+        },
+        FunctionCall::Operator(operator) => {
+            out.push(5);
+            out.push(OPERATORS.iter().position(|o| o == operator).unwrap() as u8);
+        },
+        FunctionCall::Select => out.push(6),
+        FunctionCall::Map(target) => {
+            out.push(7);
+            write_function_call(out, target)?;
+        },
+        FunctionCall::Filter(target) => {
+            out.push(8);
+            write_function_call(out, target)?;
+        },
+        FunctionCall::Reduce(target) => {
+            out.push(9);
+            write_function_call(out, target)?;
+        },
+        FunctionCall::Fold(target) => {
+            out.push(10);
+            write_function_call(out, target)?;
+        },
+        FunctionCall::Closure(target) => {
+            out.push(11);
+            write_function_call(out, target)?;
+        },
+        FunctionCall::Invoke => out.push(12),
+        FunctionCall::BuildList => out.push(13),
+        FunctionCall::BuildTuple => out.push(14),
+        FunctionCall::BuildDictionary => out.push(15),
+        FunctionCall::Struct(index) => {
+            out.push(16);
+            write_len(out, *index)?;
+        },
+    }
+
+    Ok(())
+}
+
+
+/// The maximum number of levels that data values, data types, and function
+/// calls may be nested within each other in a bytecode file.
+const MAX_DEPTH: usize = 64;
+
+
+/// Reads values from the contents of a bytecode file, in order.
+struct Reader<'a> {
+    bytes:  &'a [u8],
+    offset: usize,
+    depth:  usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], BytecodeFileError> {
+        let end = self.offset.checked_add(len).ok_or(BytecodeFileError::UnexpectedEnd)?;
+        let bytes = self.bytes.get(self.offset..end).ok_or(BytecodeFileError::UnexpectedEnd)?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeFileError> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+
+    fn read_u8(&mut self) -> Result<u8, BytecodeFileError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+
+    fn read_len(&mut self) -> Result<usize, BytecodeFileError> {
+        Ok(u32::from_le_bytes(self.read_array()?) as usize)
+    }
+
+
+    /// Reads a value that is nested within the value currently being read,
+    /// using the given function.
+    ///
+    /// Nested values are read recursively, so an error is returned once values
+    /// are nested more than [`MAX_DEPTH`] levels deep, rather than letting a
+    /// damaged file overflow the stack.
+    fn nested<T>(
+        &mut self, read: impl FnOnce(&mut Self) -> Result<T, BytecodeFileError>,
+    ) -> Result<T, BytecodeFileError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(invalid(format!("values are nested more than {} levels deep", MAX_DEPTH)));
+        }
+
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+
+    /// Reads a list, where each element is read using the given function.
+    fn read_list<T>(
+        &mut self, read: impl Fn(&mut Self) -> Result<T, BytecodeFileError>,
+    ) -> Result<Vec<T>, BytecodeFileError> {
+        (0..self.read_len()?).map(|_| read(self)).collect()
+    }
+
+
+    fn read_string(&mut self) -> Result<String, BytecodeFileError> {
+        let len = self.read_len()?;
+        let bytes = self.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid(String::from("string is not UTF-8")))
+    }
+
+
+    fn read_index_option(&mut self) -> Result<Option<usize>, BytecodeFileError> {
+        match self.read_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.read_len()?)),
+            flag => Err(invalid(format!("unknown option flag {}", flag))),
+        }
+    }
+
+
+    fn read_input(&mut self) -> Result<OperationInput, BytecodeFileError> {
+        match self.read_u8()? {
+            0 => Ok(OperationInput::Param(self.read_len()?)),
+            1 => Ok(OperationInput::Hidden(self.read_len()?)),
+            tag => Err(invalid(format!("unknown operation input {}", tag))),
+        }
+    }
+
+
+    fn read_data(&mut self) -> Result<Data, BytecodeFileError> {
+        Ok(match self.read_u8()? {
+            0 => Data::Null,
+            1 => Data::Int(i64::from_le_bytes(self.read_array()?)),
+            2 => Data::Float(f64::from_bits(u64::from_le_bytes(self.read_array()?))),
+            3 => Data::String(self.read_string()?),
+            4 => {
+                let code = u32::from_le_bytes(self.read_array()?);
+                let value = char::from_u32(code);
+                Data::Char(value.ok_or_else(|| invalid(format!("invalid character {}", code)))?)
+            },
+            5 => {
+                match self.read_u8()? {
+                    0 => Data::Bool(false),
+                    1 => Data::Bool(true),
+                    value => return Err(invalid(format!("invalid boolean {}", value))),
+                }
+            },
+            6 => {
+                Data::Struct(StructData {
+                    struct_type: self.read_string()?,
+                    fields:      self.read_list(|r| r.nested(Self::read_data))?,
+                })
+            },
+            7 => Data::List(self.read_list(|r| r.nested(Self::read_data))?),
+            8 => Data::Array(self.read_list(|r| r.nested(Self::read_data))?),
+            9 => Data::Error(self.read_string()?),
+            10 => Data::Option(Box::new(self.nested(Self::read_data)?)),
+            11 => Data::Result(Box::new(self.nested(Self::read_data)?)),
+            12 => Data::Tuple(self.read_list(|r| r.nested(Self::read_data))?),
+            13 => {
+                let keys = self.read_list(|r| r.nested(Self::read_data))?;
+                let values = self.read_list(|r| r.nested(Self::read_data))?;
+                if keys.len() != values.len() {
+                    return Err(invalid(String::from("dictionary keys do not match values")));
+                }
+                Data::Dictionary(keys, values)
+            },
+            tag => return Err(invalid(format!("unknown constant type {}", tag))),
+        })
+    }
+
+
+    fn read_data_type(&mut self) -> Result<IRDataType, BytecodeFileError> {
+        Ok(match self.read_u8()? {
+            0 => IRDataType::Int,
+            1 => IRDataType::Float,
+            2 => IRDataType::String,
+            3 => IRDataType::Char,
+            4 => IRDataType::Bool,
+            5 => IRDataType::Error,
+            6 => IRDataType::Null,
+            7 => IRDataType::List(Box::new(self.nested(Self::read_data_type)?)),
+            8 => {
+                let element = self.nested(Self::read_data_type)?;
+                let len = u32::from_le_bytes(self.read_array()?);
+                IRDataType::Array(Box::new(element), len)
+            },
+            9 => IRDataType::Option(Box::new(self.nested(Self::read_data_type)?)),
+            10 => IRDataType::Result(Box::new(self.nested(Self::read_data_type)?)),
+            11 => IRDataType::Tuple(self.read_list(|r| r.nested(Self::read_data_type))?),
+            12 => {
+                let key = self.nested(Self::read_data_type)?;
+                let value = self.nested(Self::read_data_type)?;
+                IRDataType::Dictionary(Box::new(key), Box::new(value))
+            },
+            13 => {
+                let name = self.read_string()?;
+                let fields =
+                    self.read_list(|r| Ok((r.read_string()?, r.nested(Self::read_data_type)?)))?;
+                IRDataType::Struct(name, fields)
+            },
+            14 => {
+                let params = self.read_list(|r| r.nested(Self::read_data_type))?;
+                let output = self.nested(Self::read_data_type)?;
+                IRDataType::Function(params, Box::new(output))
+            },
+            tag => return Err(invalid(format!("unknown data type {}", tag))),
+        })
+    }
+
+
+    fn read_function_call(&mut self) -> Result<FunctionCall, BytecodeFileError> {
+        Ok(match self.read_u8()? {
+            0 => FunctionCall::Internal(self.read_len()?),
+            1 => FunctionCall::External(self.read_len()?),
+            2 => FunctionCall::Constant(self.read_len()?),
+            3 => FunctionCall::Field(self.read_len()?),
+            4 => FunctionCall::Element(self.read_len()?),
+            5 => {
+                let code = self.read_u8()?;
+                match OPERATORS.get(code as usize) {
+                    Some(operator) => FunctionCall::Operator(*operator),
+                    None => return Err(invalid(format!("unknown operator {}", code))),
+                }
+            },
+            6 => FunctionCall::Select,
+            7 => FunctionCall::Map(Box::new(self.nested(Self::read_function_call)?)),
+            8 => FunctionCall::Filter(Box::new(self.nested(Self::read_function_call)?)),
+            9 => FunctionCall::Reduce(Box::new(self.nested(Self::read_function_call)?)),
+            10 => FunctionCall::Fold(Box::new(self.nested(Self::read_function_call)?)),
+            11 => FunctionCall::Closure(Box::new(self.nested(Self::read_function_call)?)),
+            12 => FunctionCall::Invoke,
+            13 => FunctionCall::BuildList,
+            14 => FunctionCall::BuildTuple,
+            15 => FunctionCall::BuildDictionary,
+            16 => FunctionCall::Struct(self.read_len()?),
+            tag => return Err(invalid(format!("unknown function call {}", tag))),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::bytecode::bytecode_from_ir;
+    use crate::compiler::grammar::parse;
+    use crate::compiler::ir::compile_context;
+    use crate::compiler::resolver::resolve_context;
+    use crate::compiler::typecheck::check_types;
+    use crate::multithreading::jobs::{JobScheduler, Scheduler};
+    use crate::registry::FuncMeta;
+    use crate::{multithreading, unwrap_data, vm};
+    use indoc::indoc;
+    use ntest::timeout;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+
+    fn add(inputs: Vec<Arc<Data>>) -> Data {
+        let a = unwrap_data!(inputs[0], Int);
+        let b = unwrap_data!(inputs[1], Int);
+        Data::Int(a + b)
+    }
+
+
+    fn registry(inputs: Vec<IRDataType>) -> FunctionRegistry {
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register(FuncMeta::new(String::from("Add"), add, inputs, IRDataType::Int).unwrap())
+            .unwrap();
+        registry
+    }
+
+
+    fn compile(registry: &FunctionRegistry) -> Vec<u8> {
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Point = struct {
+                    x: Int
+                    y: Int
+                }
+
+                Main = function {
+                    params = ()
+                    return = (sum: Int)
+
+                    point = Point { x = 3, y = 4 }
+                    scaled = Scale(point, 10)
+                    sum = extern Add(scaled.x, scaled.y)
+                }
+
+                Scale = function {
+                    params = (point: Point, factor: Int)
+                    return = (scaled: Point)
+
+                    name = "scaled"
+                    scaled = Point { x = point.x * factor, y = point.y * factor }
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, registry).unwrap();
+        write_bytecode(&bytecode_from_ir(context, registry)).unwrap()
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn round_trip() {
+        let registry = registry(vec![IRDataType::Int, IRDataType::Int]);
+        let bytes = compile(&registry);
+        assert_eq!(&bytes[..6], b"VXB\0\x01\x00");
+
+        let bytecode = read_bytecode(&bytes, &registry).unwrap();
+        assert_eq!(write_bytecode(&bytecode).unwrap(), bytes);
+
+        let functions = bytecode.get_internal_functions();
+        assert_eq!(functions[0].get_path(), &vec!["Main", "Main"]);
+        assert_eq!(functions[1].get_path(), &vec!["Main", "Scale"]);
+        assert_eq!(functions[1].get_param_count(), 2);
+        assert_eq!(bytecode.get_struct_types(), &vec!["Main.Point"]);
+        assert_eq!(bytecode.get_external_functions()[0].get_function_name(), "Add");
+
+        let scheduler = JobScheduler::new().into_async();
+        multithreading::build_workers(&scheduler, 2);
+        let output = vm::evaluate(&scheduler, vm::link(&bytecode)[0].clone()).complete();
+        scheduler.terminate_workers();
+        assert_eq!(*output, Data::Int(70));
+    }
+
+
+    #[test]
+    fn rebind_externs() {
+        let bytes = compile(&registry(vec![IRDataType::Int, IRDataType::Int]));
+
+        let error = read_bytecode(&bytes, &FunctionRegistry::new()).err().unwrap();
+        assert_eq!(error.to_string(), "External function 'Add' is not registered");
+
+        let error = read_bytecode(&bytes, &registry(vec![IRDataType::Int])).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "External function 'Add' was compiled as 'fn(Int, Int) -> Int', but is registered \
+             as 'fn(Int) -> Int'"
+        );
+    }
+
+
+    #[test]
+    fn reject_invalid_files() {
+        let registry = registry(vec![IRDataType::Int, IRDataType::Int]);
+        let bytes = compile(&registry);
+        let read = |bytes: &[u8]| read_bytecode(bytes, &registry).err().unwrap();
+
+        assert_eq!(read(b"#!/bin/sh"), BytecodeFileError::InvalidMagic);
+        assert_eq!(read(b"VXB\0\x02\x00"), BytecodeFileError::UnsupportedVersion(2, 1));
+        assert_eq!(read(&bytes[..bytes.len() - 1]), BytecodeFileError::UnexpectedEnd);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            read(&trailing),
+            BytecodeFileError::InvalidData(String::from(
+                "unexpected data after the end of the bytecode"
+            ))
+        );
+
+        let mut bytecode = read_bytecode(&bytes, &registry).unwrap();
+        let mut function = InternalFunction::new();
+        function.add_operation(Operation::new(FunctionCall::Internal(5), vec![]));
+        bytecode.add_internal_function(function);
+        assert_eq!(
+            read(&write_bytecode(&bytecode).unwrap()),
+            BytecodeFileError::InvalidData(String::from("missing internal function 5"))
+        );
+
+        let mut bytecode = VertexBytecode::new();
+        let mut function = InternalFunction::new();
+        function.add_operation(Operation::new(FunctionCall::BuildList, vec![
+            OperationInput::Param(0),
+        ]));
+        bytecode.add_internal_function(function);
+        assert_eq!(
+            read(&write_bytecode(&bytecode).unwrap()),
+            BytecodeFileError::InvalidData(String::from("function 0 reads missing parameter 0"))
+        );
+    }
+
+
+    /// Writes and reads back a bytecode file containing a function that takes
+    /// two parameters, followed by a function that takes one parameter and has
+    /// the given operations, and returns the reason it was rejected.
+    fn reject(operations: Vec<Operation>) -> String {
+        let mut bytecode = VertexBytecode::new();
+
+        let mut target = InternalFunction::new();
+        target.set_param_count(2);
+        target.set_output(Some(OperationInput::Param(0)));
+        bytecode.add_internal_function(target);
+
+        let mut function = InternalFunction::new();
+        function.set_param_count(1);
+        for operation in operations {
+            function.add_operation(operation);
+        }
+        bytecode.add_internal_function(function);
+
+        let bytes = write_bytecode(&bytecode).unwrap();
+        match read_bytecode(&bytes, &FunctionRegistry::new()) {
+            Err(BytecodeFileError::InvalidData(message)) => message,
+            other => panic!("Expected invalid data, found {:?}", other.map(|_| ())),
+        }
+    }
+
+
+    fn op(call: FunctionCall, inputs: usize) -> Operation {
+        Operation::new(call, (0..inputs).map(|_| OperationInput::Param(0)).collect())
+    }
+
+
+    #[test]
+    fn reject_forward_references() {
+        let hidden = |index| OperationInput::Hidden(index);
+
+        assert_eq!(
+            reject(vec![Operation::new(FunctionCall::BuildList, vec![hidden(0)])]),
+            "function 1 operation 0 reads operation 0, which is not before it"
+        );
+        assert_eq!(
+            reject(vec![
+                Operation::new(FunctionCall::BuildList, vec![hidden(1)]),
+                op(FunctionCall::BuildList, 0),
+            ]),
+            "function 1 operation 0 reads operation 1, which is not before it"
+        );
+
+        let mut waiting = op(FunctionCall::BuildList, 0);
+        waiting.set_after(Some(1));
+        assert_eq!(
+            reject(vec![waiting, op(FunctionCall::BuildList, 0)]),
+            "function 1 operation 0 waits for operation 1, which is not before it"
+        );
+    }
+
+
+    #[test]
+    fn reject_internal_call_arity() {
+        assert_eq!(
+            reject(vec![op(FunctionCall::Internal(0), 1)]),
+            "function 1 operation 0 passes 1 input(s) to internal 0, which expects 2"
+        );
+        assert_eq!(
+            reject(vec![op(FunctionCall::Internal(1), 0)]),
+            "function 1 operation 0 passes 0 input(s) to internal 1, which expects 1"
+        );
+    }
+
+
+    #[test]
+    fn reject_operator_arity() {
+        assert_eq!(
+            reject(vec![op(FunctionCall::Operator(Operator::Add), 1)]),
+            "function 1 operation 0 passes 1 input(s) to operator Add, which expects 2"
+        );
+        assert_eq!(
+            reject(vec![op(FunctionCall::Operator(Operator::Not), 2)]),
+            "function 1 operation 0 passes 2 input(s) to operator Not, which expects 1"
+        );
+    }
+
+
+    #[test]
+    fn reject_select_arity() {
+        assert_eq!(
+            reject(vec![op(FunctionCall::Select, 2)]),
+            "function 1 operation 0 passes 2 input(s) to select, which expects 3"
+        );
+    }
+
+
+    #[test]
+    fn reject_field_and_element_arity() {
+        assert_eq!(
+            reject(vec![op(FunctionCall::Field(0), 0)]),
+            "function 1 operation 0 passes 0 input(s) to field 0, which expects 1"
+        );
+        assert_eq!(
+            reject(vec![op(FunctionCall::Element(1), 2)]),
+            "function 1 operation 0 passes 2 input(s) to element 1, which expects 1"
+        );
+    }
+
+
+    #[test]
+    fn reject_dictionary_arity() {
+        assert_eq!(
+            reject(vec![op(FunctionCall::BuildDictionary, 3)]),
+            "function 1 operation 0 passes 3 input(s) to dictionary, which expects pairs"
+        );
+    }
+
+
+    #[test]
+    fn reject_higher_order_arity() {
+        let target = || Box::new(FunctionCall::Internal(0));
+
+        assert_eq!(
+            reject(vec![op(FunctionCall::Map(target()), 1)]),
+            "function 1 operation 0 passes 1 input(s) to internal 0, which expects 2"
+        );
+        assert_eq!(
+            reject(vec![op(FunctionCall::Fold(target()), 1)]),
+            "function 1 operation 0 passes 1 input(s) to fold(internal 0), which expects at \
+             least 2"
+        );
+        assert_eq!(
+            reject(vec![op(FunctionCall::Invoke, 0)]),
+            "function 1 operation 0 passes 0 input(s) to invoke, which expects at least 1"
+        );
+        assert_eq!(
+            reject(vec![op(FunctionCall::Closure(Box::new(FunctionCall::Select)), 0)]),
+            "function 1 operation 0 creates a closure of select, which is not a function"
+        );
+    }
+
+
+    #[test]
+    fn reject_deeply_nested_values() {
+        let registry = FunctionRegistry::new();
+        let nested = BytecodeFileError::InvalidData(String::from(
+            "values are nested more than 64 levels deep",
+        ));
+
+        // A single operation whose function call is a long chain of map tags.
+        let mut bytes = b"VXB\0\x01\x00".to_vec();
+        bytes.extend([0; 12]);
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend([0; 8]);
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(vec![7; 2_000_000]);
+        assert_eq!(read_bytecode(&bytes, &registry).err().unwrap(), nested);
+
+        // A single constant made of a long chain of options.
+        let mut bytes = b"VXB\0\x01\x00".to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(vec![10; 2_000_000]);
+        assert_eq!(read_bytecode(&bytes, &registry).err().unwrap(), nested);
+
+        let options = |depth| {
+            let mut bytecode = VertexBytecode::new();
+            bytecode.add_constant((0..depth).fold(Data::Null, |d, _| Data::Option(Box::new(d))));
+            write_bytecode(&bytecode).unwrap()
+        };
+        assert!(read_bytecode(&options(64), &registry).is_ok());
+        assert_eq!(read_bytecode(&options(65), &registry).err().unwrap(), nested);
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn construct_with_missing_fields() {
+        let mut bytecode = VertexBytecode::new();
+        bytecode.add_struct_type(String::from("Main.Point"));
+
+        // The struct is constructed without any fields, so reading a field from
+        // it gives an error rather than a panic.
+        let mut function = InternalFunction::new();
+        function.add_operation(Operation::new(FunctionCall::Struct(0), vec![]));
+        function.add_operation(Operation::new(FunctionCall::Field(1), vec![
+            OperationInput::Hidden(0),
+        ]));
+        function.set_output(Some(OperationInput::Hidden(1)));
+        bytecode.add_internal_function(function);
+
+        let bytes = write_bytecode(&bytecode).unwrap();
+        let bytecode = read_bytecode(&bytes, &FunctionRegistry::new()).unwrap();
+
+        let scheduler = JobScheduler::new().into_async();
+        multithreading::build_workers(&scheduler, 2);
+        let output = vm::evaluate(&scheduler, vm::link(&bytecode)[0].clone()).complete();
+        scheduler.terminate_workers();
+        assert_eq!(
+            *output,
+            Data::Error(String::from("Cannot read field 1 from Main.Point {  }"))
+        );
+    }
+}
@@ -66,6 +66,45 @@ pub struct StructData {
 }
 
 
+/// A pointer to a function that can be called from within Vertex.
+#[derive(Debug, Clone, Copy)]
+pub enum FunctionPointer {
+    /// Points to the internal function at the given index within the program
+    /// bytecode.
+    Internal(usize),
+
+    /// Points to an external Rust function.
+    External(VertexFunction),
+}
+
+
+impl PartialEq for FunctionPointer {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FunctionPointer::Internal(a), FunctionPointer::Internal(b)) => a == b,
+            (FunctionPointer::External(a), FunctionPointer::External(b)) => {
+                std::ptr::fn_addr_eq(*a, *b)
+            },
+            _ => false,
+        }
+    }
+}
+
+
+/// Contains a function that is used as a data value within the Vertex runtime,
+/// along with any data values that were captured when the function value was
+/// created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionData {
+    /// The function that is called when this function value is called.
+    pub function: FunctionPointer,
+
+    /// The captured data values, which are passed to the function before all
+    /// other arguments whenever this function value is called.
+    pub captures: Vec<Data>,
+}
+
+
 /// A managed data instance used by Vertex and stored on the heap. Data values
 /// are immutable and statically typed.
 #[derive(Debug, Clone, PartialEq)]
@@ -116,6 +155,10 @@ pub enum Data {
 
     /// A dictionary of key-value pairs of data.
     Dictionary(Vec<Data>, Vec<Data>),
+
+    /// A function that may be passed around as data and called later. See
+    /// [`crate::data::FunctionData`] for more information.
+    Function(FunctionData),
 }
 
 
@@ -146,19 +189,41 @@ impl fmt::Display for Data {
                 })
             },
 
-            Data::List(val) | Data::Array(val) | Data::Tuple(val) => {
+            Data::List(val) | Data::Array(val) => {
+                write!(f, "[")?;
                 for (index, value) in val.iter().enumerate() {
                     if index > 0 {
                         write!(f, ", ")?;
                     }
                     write!(f, "{}", value)?;
                 }
-                Ok(())
+                write!(f, "]")
+            },
+
+            Data::Tuple(val) => {
+                write!(f, "(")?;
+                for (index, value) in val.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, ")")
+            },
+
+            Data::Function(val) => {
+                match val.function {
+                    FunctionPointer::Internal(index) => write!(f, "Function({})", index),
+                    FunctionPointer::External(_) => write!(f, "Function(extern)"),
+                }
             },
 
             Data::Dictionary(keys, values) => {
                 write!(f, "{{")?;
                 for i in 0..keys.len() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
                     write!(f, "{}: {}", keys[i], values[i])?;
                 }
                 write!(f, "}}")?;
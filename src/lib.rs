@@ -13,6 +13,7 @@
 
 pub mod compiler;
 pub mod data;
+pub mod lsp;
 pub mod multithreading;
 pub mod registry;
 pub mod vm;
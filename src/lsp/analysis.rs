@@ -0,0 +1,677 @@
+//! Semantic analysis of a single open document, used to answer the requests
+//! of a language client.
+
+
+use crate::compiler::ast::{ContextNode, FunctionNode, ModuleNode, NodePosition};
+use crate::compiler::diagnostics::{Diagnostic, Severity};
+use crate::compiler::grammar::parse_file_recovering;
+use crate::compiler::ir::{
+    compile_context_partial, IRContext, IRDataType, IRFunction, IRNodeInput, IRStruct,
+};
+use crate::compiler::resolver::resolve_context;
+use crate::compiler::sources::SourceSet;
+use crate::compiler::typecheck::check_types;
+use crate::registry::{FuncMeta, FunctionRegistry};
+use std::fs;
+use std::path::Path;
+
+
+/// The kind of element that a completion item refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A module.
+    Module,
+
+    /// An internal or external function.
+    Function,
+
+    /// A struct.
+    Struct,
+
+    /// A function parameter or variable.
+    Variable,
+}
+
+
+/// A name that can be inserted at the position of the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// The name to insert.
+    pub label: String,
+
+    /// The kind of element the name refers to.
+    pub kind: CompletionKind,
+
+    /// The data type of the element, if it is known.
+    pub detail: Option<String>,
+}
+
+
+/// An element of the program that a name within the source code refers to.
+enum Symbol<'a> {
+    Variable(String, NodePosition, Option<IRDataType>),
+    Function(&'a IRFunction),
+    Struct(&'a IRStruct),
+    Module(Vec<String>, NodePosition),
+    Extern(&'a FuncMeta),
+}
+
+
+/// The result of compiling an open document, along with all of the source files
+/// that it imports.
+///
+/// The document is parsed with the recovering parser and every compiler pass is
+/// run, even if errors are found, so that as much of the program as possible is
+/// known while it is being edited.
+///
+/// Positions given to and returned from an analysis are the same as those of
+/// the abstract syntax tree, where lines and columns both start at 1 and
+/// columns are counted in characters.
+pub struct Analysis<'a> {
+    registry:    &'a FunctionRegistry,
+    text:        String,
+    sources:     SourceSet,
+    file:        usize,
+    context:     ContextNode,
+    modules:     Vec<(Vec<String>, NodePosition)>,
+    ir:          IRContext,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Analysis<'a> {
+    /// Analyses the given document text. If the document has been saved to the
+    /// given path, then the files it imports are loaded relative to that path.
+    pub fn new(path: Option<&Path>, text: String, registry: &'a FunctionRegistry) -> Self {
+        // Imported files are loaded first, so that the document is given the
+        // next free file id.
+        let (context, _) = parse_file_recovering(&text, 0);
+        let mut sources = SourceSet::new();
+        let mut import_errors = vec![];
+
+        let dir = path.and_then(Path::parent).unwrap_or_else(|| Path::new(""));
+        for import in &context.imports {
+            let import_path = dir.join(&import.path);
+            let message = match fs::metadata(&import_path) {
+                Ok(_) => {
+                    match sources.load_file(&import_path) {
+                        Ok(_) => continue,
+                        Err(e) => {
+                            format!("Cannot import {}: {}", import_path.display(), e.get_message())
+                        },
+                    }
+                },
+                Err(e) => format!("Cannot import {}: {}", import_path.display(), e),
+            };
+
+            import_errors.push((message, import.position.clone()));
+        }
+
+        let file = sources.get_files().len();
+        let (context, mut diagnostics) = parse_file_recovering(&text, file);
+        for (message, position) in import_errors {
+            let position = NodePosition {
+                file,
+                ..position
+            };
+
+            let position = Some(position);
+            diagnostics.push(Diagnostic::new(Severity::Error, Some("E0003"), message, position));
+        }
+
+        // A document that imports itself, directly or indirectly, is loaded
+        // from disk as well, so the saved copy is replaced by the open one.
+        let canonical = path.and_then(|p| fs::canonicalize(p).ok());
+        let mut ast = ContextNode {
+            imports: vec![],
+            modules: vec![],
+        };
+
+        for imported in sources.get_files() {
+            let imported_path = fs::canonicalize(imported.get_path()).ok();
+            if canonical.is_none() || imported_path != canonical {
+                ast.modules.extend(imported.get_context().modules.iter().cloned());
+            }
+        }
+        ast.modules.extend(context.modules.iter().cloned());
+
+        let mut modules = vec![];
+        collect_modules(&ast.modules, &[], &mut modules);
+
+        let (mut ir, mut errors) = compile_context_partial(ast, registry);
+        if let Err(e) = resolve_context(&mut ir) {
+            errors.extend(e);
+        }
+        if let Err(e) = check_types(&mut ir, registry) {
+            errors.extend(e);
+        }
+
+        // Errors within imported files are reported when those files are
+        // opened themselves.
+        diagnostics.extend(
+            errors
+                .into_iter()
+                .map(Diagnostic::from)
+                .filter(|d| d.get_position().is_some_and(|p| p.file == file)),
+        );
+
+        Self {
+            registry,
+            text,
+            sources,
+            file,
+            context,
+            modules,
+            ir,
+            diagnostics,
+        }
+    }
+
+
+    /// Gets all diagnostics found within the document.
+    pub fn get_diagnostics(&self) -> &Vec<Diagnostic> {
+        &self.diagnostics
+    }
+
+
+    /// Gets the path of the source file with the given file id.
+    ///
+    /// If the file id refers to the analyzed document itself, then None is
+    /// returned.
+    pub fn get_file_path(&self, file: usize) -> Option<&Path> {
+        self.sources.get_file(file).map(|f| f.get_path())
+    }
+
+
+    /// Checks if the given file id refers to the analyzed document.
+    pub fn is_document(&self, file: usize) -> bool {
+        file == self.file
+    }
+
+
+    /// Finds the position where the module, function, struct, or variable named
+    /// at the given position is defined.
+    ///
+    /// Names are resolved the same way the compiler resolves them, searching
+    /// the scope the name is written in first, and then each enclosing scope.
+    /// External functions have no definition within the source code, so None is
+    /// returned for them.
+    pub fn definition(&self, line: usize, col: usize) -> Option<NodePosition> {
+        match self.symbol_at(line, col)? {
+            Symbol::Variable(_, position, _) => Some(position),
+            Symbol::Function(function) => Some(function.get_position().clone()),
+            Symbol::Struct(structure) => Some(structure.get_position().clone()),
+            Symbol::Module(_, position) => Some(position),
+            Symbol::Extern(_) => None,
+        }
+    }
+
+
+    /// Describes the element named at the given position. Functions are
+    /// described by their signature, structs by their fields, and variables by
+    /// their data type.
+    pub fn hover(&self, line: usize, col: usize) -> Option<String> {
+        Some(match self.symbol_at(line, col)? {
+            Symbol::Variable(name, _, Some(dtype)) => format!("{}: {}", name, dtype),
+            Symbol::Variable(name, _, None) => name,
+            Symbol::Function(function) => {
+                let signature = function_type(function.get_inputs(), function.get_output());
+                format!("{}: {}", function.path().join("."), signature)
+            },
+            Symbol::Struct(structure) => {
+                let fields: Vec<String> = structure
+                    .get_fields()
+                    .iter()
+                    .map(|(name, dtype)| format!("{}: {}", name, dtype))
+                    .collect();
+                format!("{}: struct {{ {} }}", structure.path().join("."), fields.join(", "))
+            },
+            Symbol::Module(path, _) => format!("{}: mod", path.join(".")),
+            Symbol::Extern(function) => {
+                let signature = function_type(function.get_inputs(), function.get_output());
+                format!("extern {}: {}", function.get_name(), signature)
+            },
+        })
+    }
+
+
+    /// Lists the names that can be written at the given cursor position, where
+    /// the column is the character just after the cursor.
+    ///
+    /// After the `extern` keyword, the functions within the function registry
+    /// are listed. After a qualified name, such as `Vector.`, the elements
+    /// nested within the named module or function are listed. Otherwise, the
+    /// parameters and variables of the enclosing function are listed, followed
+    /// by every accessible module, function, and struct within each enclosing
+    /// scope.
+    pub fn completions(&self, line: usize, col: usize) -> Vec<Completion> {
+        let (chars, start) = match self.word_start(line, col) {
+            Some(word) => word,
+            None => return vec![],
+        };
+
+        if follows_extern(&chars, start) {
+            return self
+                .registry
+                .get_functions()
+                .iter()
+                .map(|function| {
+                    Completion {
+                        label:  function.get_name().to_owned(),
+                        kind:   CompletionKind::Function,
+                        detail: Some(function_type(function.get_inputs(), function.get_output())),
+                    }
+                })
+                .collect();
+        }
+
+        let prefix: String = chars[start..(col - 1).min(chars.len())].iter().collect();
+        let (scope, function) = self.scope_at(line, col);
+        let mut completions = vec![];
+
+        match prefix.rfind('.') {
+            Some(index) => {
+                let qualifier: Vec<String> = prefix[..index].split('.').map(String::from).collect();
+                if let Some(parent) = self.find_scope(&scope, &qualifier) {
+                    self.scope_completions(&parent, &scope, &mut completions);
+                }
+            },
+            None => {
+                if let Some(function) = function {
+                    self.variable_completions(&scope, function, &mut completions);
+                }
+
+                for depth in (0..=scope.len()).rev() {
+                    self.scope_completions(&scope[..depth], &scope, &mut completions);
+                }
+            },
+        }
+
+        completions
+    }
+
+
+    /// Resolves the name at the given position to the element it refers to.
+    fn symbol_at(&self, line: usize, col: usize) -> Option<Symbol<'_>> {
+        let (chars, start) = self.word_start(line, col)?;
+        let end = (start..chars.len()).find(|&i| i >= col - 1 && !is_identifier(chars[i]));
+        let end = end.unwrap_or(chars.len());
+        let name: String = chars[start..end].iter().collect();
+        let name = name.trim_matches('.');
+        if name.is_empty() {
+            return None;
+        }
+
+        if follows_extern(&chars, start) {
+            return self.registry.get_function(name).map(Symbol::Extern);
+        }
+
+        let (scope, function) = self.scope_at(line, col);
+        if let Some(function) = function.filter(|_| !name.contains('.')) {
+            if let Some(variable) = self.find_variable(&scope, function, name) {
+                return Some(variable);
+            }
+        }
+
+        let name: Vec<String> = name.split('.').map(String::from).collect();
+        for depth in (0..=scope.len()).rev() {
+            let mut path = scope[..depth].to_vec();
+            path.extend(name.iter().cloned());
+
+            if let Some(function) = self.ir.get_function(&path) {
+                return Some(Symbol::Function(function));
+            }
+
+            if let Some(structure) = self.ir.get_struct(&path) {
+                return Some(Symbol::Struct(structure));
+            }
+
+            if let Some((_, position)) = self.modules.iter().find(|(p, _)| *p == path) {
+                return Some(Symbol::Module(path, position.clone()));
+            }
+        }
+
+        None
+    }
+
+
+    /// Gets the characters of the given line, along with the index of the first
+    /// character of the qualified name that ends just before the given column.
+    /// If there is no such name, then the index of the column is returned.
+    fn word_start(&self, line: usize, col: usize) -> Option<(Vec<char>, usize)> {
+        // The final line of a document has no entry when it is empty.
+        let text = self.text.lines().nth(line.checked_sub(1)?).unwrap_or_default();
+        let chars: Vec<char> = text.chars().collect();
+        let mut start = col.saturating_sub(1).min(chars.len());
+
+        while start > 0 && (is_identifier(chars[start - 1]) || chars[start - 1] == '.') {
+            start -= 1;
+        }
+
+        Some((chars, start))
+    }
+
+
+    /// Gets the identifier path of the innermost module or function that
+    /// contains the given position, along with the innermost function.
+    fn scope_at(&self, line: usize, col: usize) -> (Vec<String>, Option<&FunctionNode>) {
+        let mut scope = vec![];
+        let mut function = None;
+        let mut modules: &[ModuleNode] = &self.context.modules;
+        let mut functions: &[FunctionNode] = &[];
+
+        loop {
+            if let Some(module) = modules.iter().find(|m| contains(&m.position, line, col)) {
+                scope.push(module.name.clone());
+                modules = &module.modules;
+                functions = &module.functions;
+            } else if let Some(f) = functions.iter().find(|f| contains(&f.position, line, col)) {
+                scope.push(f.name.clone());
+                function = Some(f);
+                modules = &[];
+                functions = &f.functions;
+            } else {
+                return (scope, function);
+            }
+        }
+    }
+
+
+    /// Finds the identifier path of the module or function with the given
+    /// qualified name, as seen from the given scope.
+    fn find_scope(&self, scope: &[String], name: &[String]) -> Option<Vec<String>> {
+        (0..=scope.len()).rev().find_map(|depth| {
+            let mut path = scope[..depth].to_vec();
+            path.extend(name.iter().cloned());
+
+            let exists = self.modules.iter().any(|(p, _)| *p == path)
+                || self.ir.get_function(&path).is_some();
+            exists.then_some(path)
+        })
+    }
+
+
+    /// Finds the parameter or variable with the given name within the given
+    /// function, where the scope is the identifier path of that function.
+    fn find_variable(
+        &self, scope: &[String], function: &FunctionNode, name: &str,
+    ) -> Option<Symbol<'_>> {
+        let ir_function = self.ir.get_function(&scope.to_vec());
+
+        if let Some(index) = function.params.arguments.iter().position(|p| p.name == name) {
+            let dtype = ir_function.and_then(|f| f.get_inputs().get(index)).cloned();
+            let position = function.params.arguments[index].position.clone();
+            return Some(Symbol::Variable(name.to_owned(), position, dtype));
+        }
+
+        let mut variables = function.assignments.iter().flat_map(|a| &a.variables);
+        let variable = variables.find(|v| v.name == name)?;
+        let dtype = ir_function.and_then(|f| variable_type(f, name));
+        Some(Symbol::Variable(name.to_owned(), variable.position.clone(), dtype))
+    }
+
+
+    /// Adds the parameters and variables of the given function, where the scope
+    /// is the identifier path of that function.
+    fn variable_completions(
+        &self, scope: &[String], function: &FunctionNode, completions: &mut Vec<Completion>,
+    ) {
+        let names = function
+            .params
+            .arguments
+            .iter()
+            .map(|p| &p.name)
+            .chain(function.assignments.iter().flat_map(|a| &a.variables).map(|v| &v.name));
+
+        for name in names {
+            if let Some(Symbol::Variable(label, _, dtype)) =
+                self.find_variable(scope, function, name)
+            {
+                let detail = dtype.map(|d| d.to_string());
+                push_completion(completions, label, CompletionKind::Variable, detail);
+            }
+        }
+    }
+
+
+    /// Adds every module, function, and struct that is nested directly within
+    /// the given parent and is accessible from the given scope.
+    fn scope_completions(
+        &self, parent: &[String], scope: &[String], completions: &mut Vec<Completion>,
+    ) {
+        let is_child = |path: &[String]| path.len() == parent.len() + 1 && path.starts_with(parent);
+
+        for (path, _) in &self.modules {
+            if is_child(path) {
+                let label = path[parent.len()].clone();
+                push_completion(completions, label, CompletionKind::Module, None);
+            }
+        }
+
+        for function in self.ir.get_functions() {
+            // Anonymous functions are named after their position, and cannot be
+            // referenced by name.
+            let path = function.path();
+            if is_child(path)
+                && !path[parent.len()].contains('@')
+                && function.is_accessible_from(scope)
+            {
+                let label = path[parent.len()].clone();
+                let detail = function_type(function.get_inputs(), function.get_output());
+                push_completion(completions, label, CompletionKind::Function, Some(detail));
+            }
+        }
+
+        for structure in self.ir.get_structs() {
+            if is_child(structure.path()) && structure.is_accessible_from(scope) {
+                let label = structure.path()[parent.len()].clone();
+                push_completion(completions, label, CompletionKind::Struct, None);
+            }
+        }
+    }
+}
+
+
+/// Adds a completion, unless a completion with the same name has already been
+/// added by an inner scope.
+fn push_completion(
+    completions: &mut Vec<Completion>, label: String, kind: CompletionKind, detail: Option<String>,
+) {
+    if !completions.iter().any(|c| c.label == label) {
+        completions.push(Completion {
+            label,
+            kind,
+            detail,
+        });
+    }
+}
+
+
+/// Collects the identifier path and position of every module, including
+/// nested modules.
+fn collect_modules(
+    modules: &[ModuleNode], parent: &[String], out: &mut Vec<(Vec<String>, NodePosition)>,
+) {
+    for module in modules {
+        let mut path = parent.to_vec();
+        path.push(module.name.clone());
+        collect_modules(&module.modules, &path, out);
+        out.push((path, module.position.clone()));
+    }
+}
+
+
+/// Gets the data type of the variable with the given name within the given
+/// function.
+fn variable_type(function: &IRFunction, name: &str) -> Option<IRDataType> {
+    match function.get_variable(name)?.get_value() {
+        IRNodeInput::FunctionParam(index) => function.get_inputs().get(*index as usize).cloned(),
+        IRNodeInput::HiddenNode(index) => {
+            function.get_statements().get(*index as usize).map(|n| n.get_output().clone())
+        },
+    }
+}
+
+
+/// Formats the signature of a function with the given inputs and output.
+fn function_type(inputs: &[IRDataType], output: &IRDataType) -> String {
+    IRDataType::Function(inputs.to_vec(), Box::new(output.clone())).to_string()
+}
+
+
+/// Checks if the given position is within the given range of source code.
+fn contains(position: &NodePosition, line: usize, col: usize) -> bool {
+    let start = (position.line, position.col);
+    let end = (position.end_line, position.end_col);
+    start <= (line, col) && (line, col) <= end
+}
+
+
+/// Checks if the given character can be part of an identifier.
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+
+/// Checks if the name starting at the given index is preceded by the `extern`
+/// keyword.
+fn follows_extern(chars: &[char], start: usize) -> bool {
+    if start == 0 || !chars[start - 1].is_whitespace() {
+        return false;
+    }
+
+    let before: String = chars[..start].iter().collect();
+    before.trim_end().strip_suffix("extern").is_some_and(|rest| !rest.ends_with(is_identifier))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Data;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+
+    const SOURCE: &str = indoc! {r#"
+        Math = export mod {
+            Vector = export struct {
+                x: Float
+                y: Float
+            }
+
+            Length = export function {
+                params = (v: Vector)
+                return = (length: Float)
+
+                length = v.x + v.y
+            }
+        }
+
+        Main = mod {
+            Main = function {
+                params = (a: Int)
+                return = (b: Int)
+
+                v = Math.Vector { x = 1.0, y = 2.0 }
+                length = Math.Length(v)
+                b = extern Add(a, a)
+            }
+        }
+    "#};
+
+
+    fn add(_: Vec<Arc<Data>>) -> Data {
+        Data::Null
+    }
+
+
+    fn registry() -> FunctionRegistry {
+        let mut registry = FunctionRegistry::new();
+        let int_args = vec![IRDataType::Int, IRDataType::Int];
+        registry
+            .register(FuncMeta::new(String::from("Add"), add, int_args, IRDataType::Int).unwrap())
+            .unwrap();
+        registry
+    }
+
+
+    fn labels(completions: Vec<Completion>) -> Vec<String> {
+        completions.into_iter().map(|c| c.label).collect()
+    }
+
+
+    #[test]
+    fn find_definitions() {
+        let registry = registry();
+        let analysis = Analysis::new(None, SOURCE.to_owned(), &registry);
+        assert_eq!(analysis.get_diagnostics(), &vec![]);
+
+        let definition = |line, col| analysis.definition(line, col).map(|p| (p.line, p.col));
+        assert_eq!(definition(21, 24), Some((7, 5)));
+        assert_eq!(definition(21, 18), Some((1, 1)));
+        assert_eq!(definition(20, 18), Some((2, 5)));
+        assert_eq!(definition(21, 30), Some((20, 9)));
+        assert_eq!(definition(22, 24), Some((17, 19)));
+        assert_eq!(definition(22, 21), None);
+        assert_eq!(definition(19, 1), None);
+    }
+
+
+    #[test]
+    fn hover_signatures() {
+        let registry = registry();
+        let analysis = Analysis::new(None, SOURCE.to_owned(), &registry);
+
+        assert_eq!(analysis.hover(21, 24).unwrap(), "Math.Length: fn(Math.Vector) -> Float");
+        assert_eq!(analysis.hover(20, 18).unwrap(), "Math.Vector: struct { x: Float, y: Float }");
+        assert_eq!(analysis.hover(21, 18).unwrap(), "Math: mod");
+        assert_eq!(analysis.hover(21, 30).unwrap(), "v: Math.Vector");
+        assert_eq!(analysis.hover(22, 24).unwrap(), "a: Int");
+        assert_eq!(analysis.hover(22, 21).unwrap(), "extern Add: fn(Int, Int) -> Int");
+    }
+
+
+    #[test]
+    fn complete_identifiers() {
+        let registry = registry();
+        let analysis = Analysis::new(None, SOURCE.to_owned(), &registry);
+
+        assert_eq!(labels(analysis.completions(22, 9)), vec![
+            "a", "v", "length", "b", "Main", "Math"
+        ]);
+        assert_eq!(labels(analysis.completions(21, 23)), vec!["Length", "Vector"]);
+        assert_eq!(labels(analysis.completions(21, 25)), vec!["Length", "Vector"]);
+        assert_eq!(analysis.completions(22, 20), vec![Completion {
+            label:  String::from("Add"),
+            kind:   CompletionKind::Function,
+            detail: Some(String::from("fn(Int, Int) -> Int")),
+        }]);
+    }
+
+
+    #[test]
+    fn analyze_unfinished_document() {
+        let registry = registry();
+        let source = indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = (a: Int)
+                    return = (b: Int)
+
+                    c = a +
+                    b = Missing(a)
+        "#};
+
+        let analysis = Analysis::new(None, source.to_owned(), &registry);
+        let messages: Vec<String> =
+            analysis.get_diagnostics().iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages, vec![
+            "error[E0001]: Syntax error, expected expression, at 6:16",
+            "error[E0102]: Cannot find element 'Missing' within the current scope, at 7:13",
+        ]);
+
+        assert_eq!(labels(analysis.completions(7, 9)), vec!["a", "c", "b", "Main"]);
+        assert_eq!(analysis.hover(7, 21).unwrap(), "a: Int");
+    }
+}
@@ -0,0 +1,14 @@
+//! A language server for Vertex source files, implementing the Language Server
+//! Protocol over JSON-RPC.
+//!
+//! The server publishes diagnostics for each open document whenever it
+//! changes, and answers go-to-definition, hover, and completion requests. Each
+//! document is compiled using the recovering parser, so that a document that
+//! is still being written can be analyzed as well.
+
+
+mod analysis;
+mod protocol;
+mod server;
+
+pub use server::Server;
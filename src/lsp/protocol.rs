@@ -0,0 +1,192 @@
+//! Reads and writes JSON-RPC messages using the base protocol of the Language
+//! Server Protocol, and converts between the values of the protocol and those
+//! of the compiler.
+
+
+use crate::compiler::ast::NodePosition;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+
+/// The largest message content, in bytes, that will be read from a client.
+const MAX_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
+
+
+/// Reads the next message from the given input. Each message is preceded by a
+/// set of headers, of which only `Content-Length` is required.
+///
+/// If the input has ended, then None is returned. If the message content is not
+/// valid JSON, then the parse error is returned within the result. A message
+/// longer than 64 MiB is returned as an error instead, as it's content is not
+/// read.
+pub fn read_message(
+    input: &mut impl BufRead,
+) -> io::Result<Option<Result<Value, serde_json::Error>>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+
+            // Blank lines between messages are skipped.
+            continue;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.unwrap();
+    if length > MAX_CONTENT_LENGTH {
+        let message = format!("Message content of {} bytes is too long", length);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)))
+}
+
+
+/// Writes the given message to the given output, preceded by it's
+/// `Content-Length` header.
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    output.flush()
+}
+
+
+/// Converts a range of source code into a protocol range, where lines and
+/// characters both start at 0.
+pub fn to_range(position: &NodePosition) -> Value {
+    json!({
+        "start": {
+            "line": position.line.saturating_sub(1),
+            "character": position.col.saturating_sub(1),
+        },
+        "end": {
+            "line": position.end_line.saturating_sub(1),
+            "character": position.end_col.saturating_sub(1),
+        },
+    })
+}
+
+
+/// Reads the line and column of a protocol position, where lines and columns
+/// both start at 1.
+pub fn from_position(position: &Value) -> Option<(usize, usize)> {
+    let line = position.get("line")?.as_u64()? as usize;
+    let character = position.get("character")?.as_u64()? as usize;
+    Some((line + 1, character + 1))
+}
+
+
+/// Converts a `file` URI into a file path. Other URI schemes, such as those of
+/// unsaved documents, have no file path.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = vec![];
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3).and_then(|hex| {
+            u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+        });
+
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            },
+        }
+    }
+
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+
+/// Converts a file path into a `file` URI, escaping any characters that are
+/// not allowed within a URI path.
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            },
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    uri
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn read_framed_messages() {
+        let input = "Content-Length: 11\r\n\r\n{\"id\": 1}  \
+                     Content-Type: application/vscode-jsonrpc\r\nContent-Length: 2\r\n\r\n{}";
+        let mut input = input.as_bytes();
+
+        assert_eq!(read_message(&mut input).unwrap().unwrap().unwrap(), json!({"id": 1}));
+        assert_eq!(read_message(&mut input).unwrap().unwrap().unwrap(), json!({}));
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+
+    #[test]
+    fn reject_oversized_message() {
+        let mut input = "Content-Length: 99999999999999\r\n\r\n{}".as_bytes();
+        let error = read_message(&mut input).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Message content of 99999999999999 bytes is too long");
+    }
+
+
+    #[test]
+    fn write_framed_message() {
+        let mut output = vec![];
+        write_message(&mut output, &json!({"id": 1})).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "Content-Length: 8\r\n\r\n{\"id\":1}");
+    }
+
+
+    #[test]
+    fn convert_uris() {
+        assert_eq!(
+            uri_to_path("file:///home/user/my%20project/main.vx"),
+            Some(PathBuf::from("/home/user/my project/main.vx"))
+        );
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+        assert_eq!(
+            path_to_uri(Path::new("/no/such/dir/my project.vx")),
+            "file:///no/such/dir/my%20project.vx"
+        );
+    }
+}
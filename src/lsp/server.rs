@@ -0,0 +1,485 @@
+//! Handles the requests and notifications sent by a language client.
+
+
+use super::analysis::{Analysis, CompletionKind};
+use super::protocol::{
+    from_position, path_to_uri, read_message, to_range, uri_to_path, write_message,
+};
+use crate::compiler::diagnostics::Severity;
+use crate::registry::FunctionRegistry;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+
+/// The error code for a message that is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+
+/// The error code for a message that is not a valid request.
+const INVALID_REQUEST: i64 = -32600;
+
+/// The error code for a request with an unknown method.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The error code for a request with missing or invalid parameters.
+const INVALID_PARAMS: i64 = -32602;
+
+
+/// A language server for Vertex source files, communicating with a language
+/// client through JSON-RPC messages.
+///
+/// Documents are synchronized in full whenever they are changed, and are
+/// analyzed again after every change. Diagnostics are published for a document
+/// each time it is analyzed.
+pub struct Server<'a> {
+    registry:  &'a FunctionRegistry,
+    documents: HashMap<String, Analysis<'a>>,
+    shutdown:  bool,
+}
+
+impl<'a> Server<'a> {
+    /// Creates a new language server, where external functions are looked up
+    /// within the given function registry.
+    pub fn new(registry: &'a FunctionRegistry) -> Self {
+        Self {
+            registry,
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+
+    /// Reads and handles messages from the given input, writing responses and
+    /// notifications to the given output, until an `exit` notification is
+    /// received or the input ends.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(&mut input)? {
+            let message = match message {
+                Ok(message) => message,
+                Err(e) => {
+                    let error = error_response(Value::Null, PARSE_ERROR, e.to_string());
+                    write_message(&mut output, &error)?;
+                    continue;
+                },
+            };
+
+            let method = message.get("method").and_then(Value::as_str).unwrap_or_default();
+            if method == "exit" {
+                break;
+            }
+
+            for response in self.handle_message(&message) {
+                write_message(&mut output, &response)?;
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Checks if a `shutdown` request has been received. Clients expect the
+    /// server to exit with a failure if it exits before being shut down.
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+
+    /// Handles a single request or notification, and returns the messages to
+    /// send back to the client.
+    fn handle_message(&mut self, message: &Value) -> Vec<Value> {
+        let method = message.get("method").and_then(Value::as_str);
+        let params = message.get("params").unwrap_or(&Value::Null);
+
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => {
+                // Responses and unknown notifications are ignored.
+                return match method {
+                    Some(method) => self.handle_notification(method, params),
+                    None => vec![],
+                };
+            },
+        };
+
+        let method = match method {
+            Some(method) => method,
+            None => return vec![error_response(id, INVALID_REQUEST, "Missing method".into())],
+        };
+
+        if self.shutdown {
+            let message = String::from("The server has been shut down");
+            return vec![error_response(id, INVALID_REQUEST, message)];
+        }
+
+        let result = match method {
+            "initialize" => Some(initialize_result()),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            },
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                let message = format!("Unknown method '{}'", method);
+                return vec![error_response(id, METHOD_NOT_FOUND, message)];
+            },
+        };
+
+        match result {
+            Some(result) => vec![json!({"jsonrpc": "2.0", "id": id, "result": result})],
+            None => vec![error_response(id, INVALID_PARAMS, "Invalid parameters".into())],
+        }
+    }
+
+
+    /// Handles a notification, and returns the messages to send back to the
+    /// client.
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = match params["textDocument"]["uri"].as_str() {
+            Some(uri) => uri.to_owned(),
+            None => return vec![],
+        };
+
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            "textDocument/didChange" => {
+                // Only full document synchronization is supported, so the
+                // final change contains the entire document.
+                params["contentChanges"].as_array().and_then(|c| c.last()?["text"].as_str())
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, vec![])];
+            },
+            _ => None,
+        };
+
+        let text = match text {
+            Some(text) => text.to_owned(),
+            None => return vec![],
+        };
+
+        let analysis = Analysis::new(uri_to_path(&uri).as_deref(), text, self.registry);
+        let start = json!({"line": 0, "character": 0});
+        let diagnostics = analysis
+            .get_diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                // Diagnostics without a position are shown at the start of
+                // the document.
+                let range = match diagnostic.get_position() {
+                    Some(position) => to_range(position),
+                    None => json!({"start": start, "end": start}),
+                };
+
+                let severity = match diagnostic.get_severity() {
+                    Severity::Error => 1,
+                    Severity::Warning => 2,
+                    Severity::Note => 3,
+                };
+
+                let mut message = diagnostic.get_message().to_owned();
+                for note in diagnostic.get_notes() {
+                    message.push_str(&format!("\nnote: {}", note));
+                }
+                if let Some(help) = diagnostic.get_help() {
+                    message.push_str(&format!("\nhelp: {}", help));
+                }
+
+                json!({
+                    "range": range,
+                    "severity": severity,
+                    "code": diagnostic.get_code(),
+                    "source": "vertex",
+                    "message": message,
+                })
+            })
+            .collect();
+
+        self.documents.insert(uri.clone(), analysis);
+        vec![publish_diagnostics(&uri, diagnostics)]
+    }
+
+
+    /// Finds the location where the element at the given document position is
+    /// defined. If there is no such element, then the result is null.
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (uri, analysis, line, col) = self.document_position(params)?;
+        let position = match analysis.definition(line, col) {
+            Some(position) => position,
+            None => return Some(Value::Null),
+        };
+
+        let uri = match analysis.get_file_path(position.file) {
+            Some(path) => path_to_uri(path),
+            None if analysis.is_document(position.file) => uri.to_owned(),
+            None => return Some(Value::Null),
+        };
+
+        Some(json!({"uri": uri, "range": to_range(&position)}))
+    }
+
+
+    /// Describes the element at the given document position. If there is no
+    /// such element, then the result is null.
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (_, analysis, line, col) = self.document_position(params)?;
+        Some(match analysis.hover(line, col) {
+            Some(text) => {
+                json!({
+                    "contents": {
+                        "kind": "markdown",
+                        "value": format!("```vertex\n{}\n```", text),
+                    },
+                })
+            },
+            None => Value::Null,
+        })
+    }
+
+
+    /// Lists the names that can be inserted at the given document position.
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (_, analysis, line, col) = self.document_position(params)?;
+        let items: Vec<Value> = analysis
+            .completions(line, col)
+            .into_iter()
+            .map(|completion| {
+                let kind = match completion.kind {
+                    CompletionKind::Module => 9,
+                    CompletionKind::Function => 3,
+                    CompletionKind::Struct => 22,
+                    CompletionKind::Variable => 6,
+                };
+
+                json!({"label": completion.label, "kind": kind, "detail": completion.detail})
+            })
+            .collect();
+
+        Some(Value::Array(items))
+    }
+
+
+    /// Reads the document and position of a request, where the line and column
+    /// both start at 1.
+    ///
+    /// If the parameters are invalid, or the document is not open, then None is
+    /// returned.
+    fn document_position<'b>(
+        &'b self, params: &'b Value,
+    ) -> Option<(&'b str, &'b Analysis<'a>, usize, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let analysis = self.documents.get(uri)?;
+        let (line, col) = from_position(&params["position"])?;
+        Some((uri, analysis, line, col))
+    }
+}
+
+
+/// Describes the capabilities of this server.
+fn initialize_result() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": {
+                "openClose": true,
+                "change": 1,
+            },
+            "definitionProvider": true,
+            "hoverProvider": true,
+            "completionProvider": {
+                "triggerCharacters": ["."],
+            },
+        },
+        "serverInfo": {
+            "name": "vertex-lsp",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+
+/// Creates a notification that replaces all diagnostics of a document.
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {
+            "uri": uri,
+            "diagnostics": diagnostics,
+        },
+    })
+}
+
+
+/// Creates an error response to the request with the given id.
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": code,
+            "message": message,
+        },
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+
+    /// Runs a new server with the given messages as input, and returns every
+    /// message that it writes.
+    fn run_session(messages: &[Value]) -> (Vec<Value>, bool) {
+        let mut input = vec![];
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+
+        let registry = FunctionRegistry::new();
+        let mut server = Server::new(&registry);
+        let mut output = vec![];
+        server.run(input.as_slice(), &mut output).unwrap();
+
+        let mut output = output.as_slice();
+        let mut responses = vec![];
+        while let Some(message) = read_message(&mut output).unwrap() {
+            responses.push(message.unwrap());
+        }
+
+        (responses, server.is_shutdown())
+    }
+
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+    }
+
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({"jsonrpc": "2.0", "method": method, "params": params})
+    }
+
+
+    fn position(line: u64, character: u64) -> Value {
+        json!({
+            "textDocument": {"uri": "untitled:main.vx"},
+            "position": {"line": line, "character": character},
+        })
+    }
+
+
+    #[test]
+    fn editor_session() {
+        let source = indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = (a: Int)
+                    return = (b: Int)
+
+                    b = Double(a)
+                }
+
+                Double = function {
+                    params = (x: Int)
+                    return = (y: Int)
+
+                    y = x * 2
+                }
+            }
+        "#};
+
+        let (responses, shutdown) = run_session(&[
+            request(1, "initialize", json!({"capabilities": {}})),
+            notification("initialized", json!({})),
+            notification("textDocument/didOpen", json!({
+                "textDocument": {
+                    "uri": "untitled:main.vx",
+                    "languageId": "vertex",
+                    "version": 1,
+                    "text": source.replace("Double(a)", "Double(a, a)"),
+                },
+            })),
+            notification("textDocument/didChange", json!({
+                "textDocument": {"uri": "untitled:main.vx", "version": 2},
+                "contentChanges": [{"text": source}],
+            })),
+            request(2, "textDocument/hover", position(5, 13)),
+            request(3, "textDocument/definition", position(5, 13)),
+            request(4, "textDocument/completion", position(5, 8)),
+            request(5, "textDocument/formatting", json!({})),
+            request(6, "shutdown", Value::Null),
+            notification("exit", Value::Null),
+        ]);
+
+        assert_eq!(responses.len(), 8);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["capabilities"]["hoverProvider"], true);
+
+        assert_eq!(responses[1]["method"], "textDocument/publishDiagnostics");
+        assert_eq!(responses[1]["params"]["diagnostics"], json!([{
+            "range": {
+                "start": {"line": 5, "character": 12},
+                "end": {"line": 5, "character": 24},
+            },
+            "severity": 1,
+            "code": "E0105",
+            "source": "vertex",
+            "message": "Function 'Main.Double' expects 1 argument(s), but 2 were given",
+        }]));
+        assert_eq!(responses[2]["params"]["diagnostics"], json!([]));
+
+        assert_eq!(responses[3], json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "result": {
+                "contents": {
+                    "kind": "markdown",
+                    "value": "```vertex\nMain.Double: fn(Int) -> Int\n```",
+                },
+            },
+        }));
+
+        assert_eq!(responses[4]["result"]["uri"], "untitled:main.vx");
+        assert_eq!(responses[4]["result"]["range"]["start"], json!({"line": 8, "character": 4}));
+
+        let labels: Vec<&str> = responses[5]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["a", "b", "Main", "Double"]);
+
+        assert_eq!(responses[6]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[7], json!({"jsonrpc": "2.0", "id": 6, "result": null}));
+        assert!(shutdown);
+    }
+
+
+    #[test]
+    fn invalid_messages() {
+        let mut input = vec![];
+        write!(input, "Content-Length: 5\r\n\r\n{{bad}}").unwrap();
+        write_message(&mut input, &request(1, "textDocument/hover", json!({}))).unwrap();
+
+        let registry = FunctionRegistry::new();
+        let mut server = Server::new(&registry);
+        let mut output = vec![];
+        server.run(input.as_slice(), &mut output).unwrap();
+
+        let mut output = output.as_slice();
+        let parse_error = read_message(&mut output).unwrap().unwrap().unwrap();
+        assert_eq!(parse_error["id"], Value::Null);
+        assert_eq!(parse_error["error"]["code"], PARSE_ERROR);
+
+        let invalid_params = read_message(&mut output).unwrap().unwrap().unwrap();
+        assert_eq!(invalid_params["id"], 1);
+        assert_eq!(invalid_params["error"]["code"], INVALID_PARAMS);
+        assert!(!server.is_shutdown());
+    }
+}
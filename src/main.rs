@@ -1 +1,292 @@
-fn main() {}
+//! The command line interface for compiling and running Vertex programs.
+
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use vertex_lang::compiler::assembly::disassemble;
+use vertex_lang::compiler::bytecode::{bytecode_from_ir, VertexBytecode};
+use vertex_lang::compiler::diagnostics::Diagnostic;
+use vertex_lang::compiler::dot::ir_context_to_dot;
+use vertex_lang::compiler::ir::{compile_context_partial, IRContext};
+use vertex_lang::compiler::optimizer::{optimize_context, Pass};
+use vertex_lang::compiler::resolver::resolve_context;
+use vertex_lang::compiler::sources::SourceSet;
+use vertex_lang::compiler::typecheck::check_types;
+use vertex_lang::compiler::vxb::{read_bytecode, write_bytecode, BYTECODE_EXTENSION};
+use vertex_lang::data::Data;
+use vertex_lang::multithreading::build_workers;
+use vertex_lang::multithreading::jobs::JobScheduler;
+use vertex_lang::registry::{standard_library, FunctionRegistry};
+use vertex_lang::vm;
+
+
+type Result<T> = std::result::Result<T, Vec<Diagnostic>>;
+
+
+/// The compiler and interpreter for the Vertex programming language.
+#[derive(Parser)]
+#[command(name = "vertex", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// The format to print errors in.
+    #[arg(long, value_enum, global = true, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+
+    /// An optimization pass to skip, either `constant-folding` or
+    /// `dead-node-elimination`. May be given multiple times.
+    #[arg(long, global = true, value_name = "PASS")]
+    disable_pass: Vec<Pass>,
+}
+
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ErrorFormat {
+    /// Human-readable messages, with annotated snippets of the source code.
+    Human,
+
+    /// One JSON object per line, for use by editors and other tools.
+    Json,
+}
+
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Emit {
+    /// The intermediate representation, after all compiler passes.
+    Ir,
+
+    /// The disassembled bytecode.
+    Bytecode,
+
+    /// The dataflow graph of every function as a Graphviz DOT digraph, with
+    /// functions clustered by their identifier path.
+    Dot,
+}
+
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compiles and executes a Vertex program.
+    Run {
+        /// The Vertex source file, directory of source files, or compiled
+        /// bytecode file to execute.
+        file: PathBuf,
+
+        /// The identifier path of the function to execute, such as
+        /// `Main.Main`.
+        #[arg(short, long, default_value = "Main.Main")]
+        entry: String,
+
+        /// The number of worker threads to execute the program with. Defaults
+        /// to the number of available CPU cores.
+        #[arg(short, long)]
+        threads: Option<u32>,
+    },
+
+    /// Checks a Vertex program for errors without executing it.
+    Check {
+        /// The Vertex source file, or directory of source files, to check.
+        file: PathBuf,
+
+        /// Prints the compiled program in the given form, instead of only
+        /// reporting errors.
+        #[arg(long, value_enum)]
+        emit: Option<Emit>,
+    },
+
+    /// Compiles a Vertex program into a bytecode file, which can be executed
+    /// without it's source code.
+    Build {
+        /// The Vertex source file, or directory of source files, to compile.
+        file: PathBuf,
+
+        /// The bytecode file to write the compiled output to.
+        #[arg(short, long, default_value = "out.vxb")]
+        output: PathBuf,
+    },
+}
+
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let registry = standard_library();
+    let mut sources = SourceSet::new();
+    let passes: Vec<Pass> =
+        Pass::ALL.into_iter().filter(|pass| !cli.disable_pass.contains(pass)).collect();
+
+    let result = match cli.command {
+        Command::Run {
+            file,
+            entry,
+            threads,
+        } => run(&file, &entry, threads, &passes, &registry, &mut sources),
+        Command::Check {
+            file,
+            emit,
+        } => check(&file, emit, &passes, &registry, &mut sources),
+        Command::Build {
+            file,
+            output,
+        } => build(&file, &output, &passes, &registry, &mut sources),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(errors) => {
+            for e in &errors {
+                match cli.error_format {
+                    ErrorFormat::Human => eprintln!("{}", e.render(&sources)),
+                    ErrorFormat::Json => eprintln!("{}", e.to_json(&sources)),
+                }
+            }
+
+            if let (ErrorFormat::Human, count @ 2..) = (cli.error_format, errors.len()) {
+                eprintln!("error: aborting due to {} previous errors", count);
+            }
+
+            ExitCode::FAILURE
+        },
+    }
+}
+
+
+fn run(
+    file: &Path, entry: &str, threads: Option<u32>, passes: &[Pass], registry: &FunctionRegistry,
+    sources: &mut SourceSet,
+) -> Result<()> {
+    let bytecode = if file.extension().is_some_and(|ext| ext == BYTECODE_EXTENSION) {
+        load_bytecode(file, registry)?
+    } else {
+        bytecode_from_ir(compile(file, passes, registry, sources)?, registry)
+    };
+
+    let entry_path: Vec<String> = entry.split('.').map(String::from).collect();
+    let functions = bytecode.get_internal_functions();
+    let entry_index = match functions.iter().position(|f| *f.get_path() == entry_path) {
+        Some(index) => index,
+        None => {
+            return Err(vec![Diagnostic::error(format!(
+                "Cannot find entry function '{}'",
+                entry
+            ))])
+        },
+    };
+
+    if functions[entry_index].get_param_count() > 0 {
+        let message = format!("Entry function '{}' cannot take any parameters", entry);
+        return Err(vec![Diagnostic::error(message)]);
+    }
+
+    let functions = vm::link(&bytecode);
+
+    let threads = match threads {
+        Some(threads) => threads,
+        None => thread::available_parallelism().map_or(1, |n| n.get() as u32),
+    };
+
+    let scheduler = JobScheduler::new().into_async();
+    build_workers(&scheduler, threads.max(1));
+
+    // Worker threads are left running, as they are cleaned up once the process
+    // exits.
+    let output = vm::evaluate(&scheduler, functions[entry_index].clone()).complete();
+
+    match &*output {
+        Data::Error(message) => return Err(vec![Diagnostic::error(message.clone())]),
+        Data::Null => {},
+        output => println!("{}", output),
+    }
+
+    Ok(())
+}
+
+
+fn check(
+    file: &Path, emit: Option<Emit>, passes: &[Pass], registry: &FunctionRegistry,
+    sources: &mut SourceSet,
+) -> Result<()> {
+    let context = compile(file, passes, registry, sources)?;
+
+    match emit {
+        Some(Emit::Ir) => print!("{}", context),
+        Some(Emit::Bytecode) => print!("{}", disassemble(&bytecode_from_ir(context, registry))),
+        Some(Emit::Dot) => print!("{}", ir_context_to_dot(&context, true)),
+        None => println!("No errors found in {}", file.display()),
+    }
+
+    Ok(())
+}
+
+
+fn build(
+    file: &Path, output: &Path, passes: &[Pass], registry: &FunctionRegistry,
+    sources: &mut SourceSet,
+) -> Result<()> {
+    let context = compile(file, passes, registry, sources)?;
+    let bytecode = bytecode_from_ir(context, registry);
+
+    let written = write_bytecode(&bytecode)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| fs::write(output, bytes).map_err(|e| e.to_string()));
+
+    if let Err(e) = written {
+        let message = format!("Cannot write {}: {}", output.display(), e);
+        return Err(vec![Diagnostic::error(message)]);
+    }
+
+    println!("Compiled {} into {}", file.display(), output.display());
+    Ok(())
+}
+
+
+/// Loads a compiled bytecode file, binding it's external functions to the
+/// functions within the given registry.
+fn load_bytecode(path: &Path, registry: &FunctionRegistry) -> Result<VertexBytecode> {
+    let loaded = fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| read_bytecode(&bytes, registry).map_err(|e| e.to_string()));
+
+    loaded.map_err(|e| vec![Diagnostic::error(format!("Cannot load {}: {}", path.display(), e))])
+}
+
+
+/// Loads, parses, and compiles the given source file, or all source files
+/// within the given directory, into an intermediate representation.
+///
+/// Imported source files are loaded as well. All loaded files are added to the
+/// given source set, so that errors can be rendered with their source code.
+///
+/// Every compiler pass is run even if an earlier pass found errors, so that
+/// all errors within the program are reported at once. The given optimization
+/// passes are only run once no errors were found.
+fn compile(
+    path: &Path, passes: &[Pass], registry: &FunctionRegistry, sources: &mut SourceSet,
+) -> Result<IRContext> {
+    let loaded = if path.is_dir() {
+        sources.load_directory(path)
+    } else {
+        sources.load_file(path).map(|_| ())
+    };
+
+    loaded.map_err(|e| vec![e])?;
+    let ast = sources.merge().map_err(|e| vec![e])?;
+
+    let (mut context, mut errors) = compile_context_partial(ast, registry);
+    if let Err(e) = resolve_context(&mut context) {
+        errors.extend(e);
+    }
+    if let Err(e) = check_types(&mut context, registry) {
+        errors.extend(e);
+    }
+
+    if errors.is_empty() {
+        optimize_context(&mut context, registry, passes);
+        Ok(context)
+    } else {
+        Err(errors.into_iter().map(Diagnostic::from).collect())
+    }
+}
@@ -5,7 +5,7 @@
 //! multiple parts of the graph tree to be evaluated in parallel.
 
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
@@ -79,8 +79,8 @@ impl Debug for JobHandle {
 /// A job instance within the job scheduler that has dependencies that need to
 /// be resolved before it can be added to the main queue.
 struct SleepingJob {
-    handle:       JobHandle,
-    dependencies: Vec<usize>,
+    handle:    JobHandle,
+    remaining: usize,
 }
 
 
@@ -88,8 +88,8 @@ struct SleepingJob {
 /// nested jobs and will not be marked as finished until those nested jobs have
 /// been finished.
 struct HibernatingJob {
-    handle:       JobHandle,
-    dependencies: Vec<usize>,
+    handle:    JobHandle,
+    remaining: usize,
 }
 
 
@@ -168,9 +168,10 @@ pub struct JobScheduler {
     scheduler_uid:      u32,
     min_job_id:         usize,
     cur_job_id:         usize,
-    buffer:             Vec<usize>,
-    sleeping_jobs:      Vec<SleepingJob>,
-    hibernating_jobs:   Vec<HibernatingJob>,
+    buffer:             HashSet<usize>,
+    sleeping_jobs:      HashMap<usize, SleepingJob>,
+    hibernating_jobs:   HashMap<usize, HibernatingJob>,
+    dependents:         HashMap<usize, Vec<usize>>,
     push_notifications: HashMap<usize, Sender<()>>,
     pending_jobs:       PendingJobs,
     job_queue:          JobQueue,
@@ -189,9 +190,10 @@ impl JobScheduler {
             scheduler_uid:      uid,
             min_job_id:         0,
             cur_job_id:         1,
-            buffer:             vec![],
-            sleeping_jobs:      vec![],
-            hibernating_jobs:   vec![],
+            buffer:             HashSet::new(),
+            sleeping_jobs:      HashMap::new(),
+            hibernating_jobs:   HashMap::new(),
+            dependents:         HashMap::new(),
             push_notifications: HashMap::new(),
             pending_jobs:       PendingJobs::new(sender),
             job_queue:          JobQueue::new(receiver),
@@ -224,6 +226,27 @@ impl JobScheduler {
         self.push_notifications.insert(job.job_id, sender);
         Some(receiver)
     }
+
+
+    /// Registers the given job as a dependent of each of the given dependency
+    /// jobs that have not yet finished, and returns the number of unfinished
+    /// dependencies that the job needs to wait on.
+    fn add_dependencies(&mut self, job_id: usize, dependencies: Vec<JobHandle>) -> usize {
+        let mut dependencies: Vec<usize> = dependencies
+            .into_iter()
+            .map(|j| j.job_id)
+            .filter(|id| *id > self.min_job_id)
+            .filter(|id| !self.buffer.contains(id))
+            .collect();
+        dependencies.sort_unstable();
+        dependencies.dedup();
+
+        for id in &dependencies {
+            self.dependents.entry(*id).or_default().push(job_id);
+        }
+
+        dependencies.len()
+    }
 }
 
 
@@ -248,13 +271,6 @@ impl Scheduler for JobScheduler {
             panic!("Tried to use job dependencies from another job system");
         }
 
-        let dependencies: Vec<usize> = dependencies
-            .into_iter()
-            .map(|j| j.job_id)
-            .filter(|id| *id > self.min_job_id)
-            .filter(|id| !self.buffer.contains(id))
-            .collect();
-
         let job_id = self.cur_job_id;
         self.cur_job_id += 1;
 
@@ -264,15 +280,16 @@ impl Scheduler for JobScheduler {
             job: Arc::new(job),
         };
 
-        if dependencies.is_empty() {
+        let remaining = self.add_dependencies(job_id, dependencies);
+        if remaining == 0 {
             self.pending_jobs.send(job.clone());
         } else {
             let sleeping_job = SleepingJob {
                 handle: job.clone(),
-                dependencies,
+                remaining,
             };
 
-            self.sleeping_jobs.push(sleeping_job);
+            self.sleeping_jobs.insert(job_id, sleeping_job);
         }
 
         job
@@ -284,32 +301,26 @@ impl Scheduler for JobScheduler {
             panic!("Tried to hibernate a job from another job system");
         }
 
-        if self.hibernating_jobs.iter().any(|j| j.handle.job_id == job.job_id) {
+        if self.hibernating_jobs.contains_key(&job.job_id) {
             panic!("Job {} already hibernating", job.job_id);
         }
 
-        if self.sleeping_jobs.iter().any(|j| j.handle.job_id == job.job_id) {
+        if self.sleeping_jobs.contains_key(&job.job_id) {
             panic!("Job {} has not yet been queued", job.job_id);
         }
 
-        let dependencies: Vec<usize> = dependencies
-            .into_iter()
-            .map(|j| j.job_id)
-            .filter(|id| *id > self.min_job_id)
-            .filter(|id| !self.buffer.contains(id))
-            .collect();
-
-        if dependencies.is_empty() {
+        let remaining = self.add_dependencies(job.job_id, dependencies);
+        if remaining == 0 {
             self.finish_job(job);
             return;
         }
 
         let hibernator = HibernatingJob {
             handle: job,
-            dependencies,
+            remaining,
         };
 
-        self.hibernating_jobs.push(hibernator);
+        self.hibernating_jobs.insert(hibernator.handle.job_id, hibernator);
     }
 
 
@@ -318,65 +329,44 @@ impl Scheduler for JobScheduler {
             panic!("Tried to finish a job from another job system");
         }
 
-        if self.sleeping_jobs.iter().any(|j| j.handle.job_id == job.job_id) {
+        if self.sleeping_jobs.contains_key(&job.job_id) {
             panic!("Job {} has not yet been queued", job.job_id);
         }
 
-        let job_id = job.job_id;
-
-        // Remove job from sleeping jobs' dependencies
-        for job in &mut self.sleeping_jobs {
-            let dep_index = job.dependencies.iter().position(|id| *id == job_id);
-            match dep_index {
-                Some(index) => {
-                    job.dependencies.remove(index);
-                    if job.dependencies.is_empty() {
-                        let job = job.handle.clone();
-                        self.pending_jobs.send(job);
+        // Hibernating jobs that finish as a result of this job are finished in
+        // the same loop, rather than recursively, as long chains of hibernating
+        // jobs may otherwise overflow the stack.
+        let mut finished_jobs = vec![job];
+        while let Some(job) = finished_jobs.pop() {
+            let job_id = job.job_id;
+
+            // Update the jobs that were waiting on this job
+            for dependent in self.dependents.remove(&job_id).unwrap_or_default() {
+                if let Some(sleeping) = self.sleeping_jobs.get_mut(&dependent) {
+                    sleeping.remaining -= 1;
+                    if sleeping.remaining == 0 {
+                        let sleeping = self.sleeping_jobs.remove(&dependent).unwrap();
+                        self.pending_jobs.send(sleeping.handle);
                     }
-                },
-                None => continue,
-            };
-        }
-        self.sleeping_jobs.retain(|j| !j.dependencies.is_empty());
-
-        // Remove job from hibernating jobs' dependencies
-        let mut finished_jobs = vec![];
-        for job in &mut self.hibernating_jobs {
-            let dep_index = job.dependencies.iter().position(|id| *id == job_id);
-            match dep_index {
-                Some(index) => {
-                    job.dependencies.remove(index);
-                    if job.dependencies.is_empty() {
-                        finished_jobs.push(job.handle.clone());
+                } else if let Some(hibernator) = self.hibernating_jobs.get_mut(&dependent) {
+                    hibernator.remaining -= 1;
+                    if hibernator.remaining == 0 {
+                        let hibernator = self.hibernating_jobs.remove(&dependent).unwrap();
+                        finished_jobs.push(hibernator.handle);
                     }
-                },
-                None => continue,
-            };
-        }
-        self.hibernating_jobs.retain(|j| !j.dependencies.is_empty());
-
-        // Cleanup finished job buffer
-        self.buffer.push(job_id);
-        loop {
-            let next_min_id = self.min_job_id + 1;
-
-            match self.buffer.iter().position(|id| *id <= next_min_id) {
-                Some(index) => self.buffer.remove(index),
-                None => break,
-            };
-
-            self.min_job_id = next_min_id;
-        }
-
-        // Push all jobs that finished hibernating
-        for job in finished_jobs {
-            self.finish_job(job);
-        }
-
-        // Trigger push notifications for finished job, if needed.
-        if let Some(sender) = self.push_notifications.remove(&job.job_id) {
-            sender.send(()).unwrap()
+                }
+            }
+
+            // Cleanup finished job buffer
+            self.buffer.insert(job_id);
+            while self.buffer.remove(&(self.min_job_id + 1)) {
+                self.min_job_id += 1;
+            }
+
+            // Trigger push notifications for finished job, if needed.
+            if let Some(sender) = self.push_notifications.remove(&job_id) {
+                sender.send(()).unwrap()
+            }
         }
     }
 
@@ -625,6 +615,21 @@ mod test {
     }
 
 
+    #[test]
+    fn duplicate_deps() {
+        let blank = Vec::new;
+
+        let mut sch = JobScheduler::new();
+        let queue = sch.get_queue();
+
+        let job1 = sch.new_job(vec![], blank);
+        let job2 = sch.new_job(vec![job1.clone(), job1], blank);
+
+        sch.finish_job(queue.next());
+        assert_eq!(queue.next(), job2);
+    }
+
+
     #[test]
     fn deps_finished_auto_run() {
         let blank = Vec::new;
@@ -640,6 +645,82 @@ mod test {
     }
 
 
+    #[test]
+    fn wait_for_every_dep() {
+        let blank = Vec::new;
+
+        let mut sch = JobScheduler::new();
+        let queue = sch.get_queue();
+        let pending = || queue.receiver.lock().unwrap().try_recv().ok();
+
+        let job1 = sch.new_job(vec![], blank);
+        let job2 = sch.new_job(vec![], blank);
+        let job3 = sch.new_job(vec![job1.clone(), job2.clone()], blank);
+        let job4 = sch.new_job(vec![job1.clone()], blank);
+        assert_eq!(pending(), Some(job1.clone()));
+        assert_eq!(pending(), Some(job2.clone()));
+        assert_eq!(pending(), None);
+
+        sch.finish_job(job1);
+        assert_eq!(pending(), Some(job4));
+        assert_eq!(pending(), None);
+
+        sch.finish_job(job2);
+        assert_eq!(pending(), Some(job3));
+        assert!(sch.sleeping_jobs.is_empty());
+        assert!(sch.dependents.is_empty());
+    }
+
+
+    #[test]
+    fn finish_out_of_order() {
+        let blank = Vec::new;
+
+        let mut sch = JobScheduler::new();
+        let job1 = sch.new_job(vec![], blank);
+        let job2 = sch.new_job(vec![], blank);
+        let job3 = sch.new_job(vec![], blank);
+
+        sch.finish_job(job3.clone());
+        sch.finish_job(job2.clone());
+        assert_eq!(sch.get_finished_jobs(), 2);
+        assert!(!sch.is_done(&job1));
+        assert!(sch.is_done(&job2));
+
+        sch.finish_job(job1.clone());
+        assert_eq!(sch.get_finished_jobs(), 3);
+        assert_eq!(sch.min_job_id, job3.job_id);
+        assert!(sch.buffer.is_empty());
+    }
+
+
+    #[test]
+    #[timeout(5000)]
+    fn finish_hibernating_chain() {
+        let blank = Vec::new;
+
+        // Each job hibernates on the job after it, so finishing the last job
+        // finishes every job within the chain.
+        let mut sch = JobScheduler::new();
+        let queue = sch.get_queue();
+        let jobs: Vec<JobHandle> = (0..100_000).map(|_| sch.new_job(vec![], blank)).collect();
+        let waiting = sch.new_job(vec![jobs[0].clone()], blank);
+
+        for pair in jobs.windows(2) {
+            assert_eq!(queue.next(), pair[0]);
+            sch.hibernate(pair[0].clone(), vec![pair[1].clone()]);
+        }
+
+        assert!(!sch.is_done(&jobs[0]));
+        sch.finish_job(queue.next());
+
+        assert_eq!(sch.get_finished_jobs(), jobs.len());
+        assert!(jobs.iter().all(|job| sch.is_done(job)));
+        assert!(sch.hibernating_jobs.is_empty());
+        assert_eq!(queue.next(), waiting);
+    }
+
+
     #[test]
     #[should_panic]
     fn finish_before_queue() {
@@ -15,6 +15,7 @@ pub struct FuncMeta {
     func:       VertexFunction,
     input_args: Vec<IRDataType>,
     output:     IRDataType,
+    pure:       bool,
 }
 
 
@@ -33,6 +34,7 @@ impl FuncMeta {
             func,
             input_args,
             output,
+            pure: false,
         })
     }
 
@@ -59,6 +61,21 @@ impl FuncMeta {
     pub fn get_output(&self) -> &IRDataType {
         &self.output
     }
+
+
+    /// Checks whether or not this function is pure, meaning that it has no side
+    /// effects and always returns the same output for the same inputs. Calls to
+    /// pure functions with constant inputs may be evaluated while compiling.
+    pub fn is_pure(&self) -> bool {
+        self.pure
+    }
+
+
+    /// Sets whether or not this function is pure. Functions are not pure by
+    /// default.
+    pub fn set_pure(&mut self, pure: bool) {
+        self.pure = pure;
+    }
 }
 
 
@@ -92,6 +109,13 @@ impl FunctionRegistry {
     }
 
 
+    /// Gets a list of all functions within this registry, in the order they
+    /// were registered.
+    pub fn get_functions(&self) -> &Vec<FuncMeta> {
+        &self.functions
+    }
+
+
     /// Gets the function meta data for the given function name.
     ///
     /// If there is no function with the given name, then None is returned.
@@ -5,5 +5,7 @@
 
 mod error;
 mod function;
+mod standard;
 
 pub use function::*;
+pub use standard::*;
@@ -0,0 +1,102 @@
+//! The external functions that are available to every Vertex program run by the
+//! `vertex` and `vertex-lsp` binaries.
+
+
+use super::{FuncMeta, FunctionRegistry};
+use crate::compiler::ir::IRDataType;
+use crate::data::{Data, VertexFunction};
+use std::sync::Arc;
+
+
+/// Creates a function registry containing the standard library functions.
+///
+/// This includes:
+/// - `Print(String) -> Null`, which writes text to the standard output.
+/// - `Println(String) -> Null`, which writes a line to the standard output.
+/// - `Length(String) -> Int`, which counts the characters within a string.
+pub fn standard_library() -> FunctionRegistry {
+    let functions: [(&str, VertexFunction, Vec<IRDataType>, IRDataType, bool); 3] = [
+        ("Print", print, vec![IRDataType::String], IRDataType::Null, false),
+        ("Println", println, vec![IRDataType::String], IRDataType::Null, false),
+        ("Length", length, vec![IRDataType::String], IRDataType::Int, true),
+    ];
+
+    let mut registry = FunctionRegistry::new();
+    for (name, func, inputs, output, pure) in functions {
+        // All data types above are resolved, and all names are unique.
+        let mut function = FuncMeta::new(String::from(name), func, inputs, output).unwrap();
+        function.set_pure(pure);
+        registry.register(function).unwrap();
+    }
+
+    registry
+}
+
+
+fn print(inputs: Vec<Arc<Data>>) -> Data {
+    match &*inputs[0] {
+        Data::String(text) => {
+            print!("{}", text);
+            Data::Null
+        },
+        other => expected_string("Print", other),
+    }
+}
+
+
+fn println(inputs: Vec<Arc<Data>>) -> Data {
+    match &*inputs[0] {
+        Data::String(text) => {
+            println!("{}", text);
+            Data::Null
+        },
+        other => expected_string("Println", other),
+    }
+}
+
+
+fn length(inputs: Vec<Arc<Data>>) -> Data {
+    match &*inputs[0] {
+        Data::String(text) => Data::Int(text.chars().count() as i64),
+        other => expected_string("Length", other),
+    }
+}
+
+
+/// Creates the output of a function that expects a string, but was given the
+/// other data value instead. Errors are passed through unchanged.
+fn expected_string(name: &str, other: &Data) -> Data {
+    match other {
+        Data::Error(e) => Data::Error(e.clone()),
+        other => Data::Error(format!("Function '{}' expects a string, but found {}", name, other)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn standard_functions() {
+        let registry = standard_library();
+
+        let names: Vec<&str> = registry.get_functions().iter().map(|f| f.get_name()).collect();
+        assert_eq!(names, vec!["Print", "Println", "Length"]);
+
+        let length = registry.get_function("Length").unwrap();
+        assert!(length.is_pure());
+        assert!(!registry.get_function("Println").unwrap().is_pure());
+
+        let text = Arc::new(Data::String(String::from("héllo")));
+        assert_eq!((length.get_func())(vec![text]), Data::Int(5));
+
+        let error = Arc::new(Data::Error(String::from("Division by zero")));
+        assert_eq!(
+            (length.get_func())(vec![error]),
+            Data::Error(String::from("Division by zero"))
+        );
+    }
+}
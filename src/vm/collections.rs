@@ -0,0 +1,69 @@
+//! Built-in functions for constructing collection data values.
+
+
+use crate::data::Data;
+use std::sync::Arc;
+
+
+/// Builds a list containing all of the given inputs, in order.
+///
+/// If any of the inputs are an error, then the first error is returned instead.
+pub fn build_list(inputs: Vec<Arc<Data>>) -> Data {
+    match collect_elements(inputs) {
+        Ok(elements) => Data::List(elements),
+        Err(error) => error,
+    }
+}
+
+
+/// Builds a tuple containing all of the given inputs, in order.
+///
+/// If any of the inputs are an error, then the first error is returned instead.
+pub fn build_tuple(inputs: Vec<Arc<Data>>) -> Data {
+    match collect_elements(inputs) {
+        Ok(elements) => Data::Tuple(elements),
+        Err(error) => error,
+    }
+}
+
+
+/// Builds a dictionary from the given inputs, where the inputs alternate
+/// between each key and the value for that key. If a key appears more than
+/// once, then the last value for that key is kept.
+///
+/// If any of the inputs are an error, then the first error is returned instead.
+pub fn build_dictionary(inputs: Vec<Arc<Data>>) -> Data {
+    let elements = match collect_elements(inputs) {
+        Ok(elements) => elements,
+        Err(error) => return error,
+    };
+
+    let mut keys: Vec<Data> = vec![];
+    let mut values = vec![];
+    for entry in elements.chunks(2) {
+        match keys.iter().position(|key| *key == entry[0]) {
+            Some(index) => values[index] = entry[1].clone(),
+            None => {
+                keys.push(entry[0].clone());
+                values.push(entry[1].clone());
+            },
+        }
+    }
+
+    Data::Dictionary(keys, values)
+}
+
+
+/// Copies the given inputs into a list of elements, or returns the first input
+/// that is an error.
+fn collect_elements(inputs: Vec<Arc<Data>>) -> Result<Vec<Data>, Data> {
+    let mut elements = vec![];
+    for input in inputs {
+        match &*input {
+            Data::Error(_) => return Err((*input).clone()),
+            data => elements.push(data.clone()),
+        }
+    }
+
+    Ok(elements)
+}
@@ -1,5 +1,5 @@
 use super::{Node, NodeFunction};
-use crate::data::{Data, VertexFunction};
+use crate::data::{Data, StructData, VertexFunction};
 use crate::multithreading::jobs::Scheduler;
 use std::sync::Arc;
 
@@ -43,3 +43,145 @@ pub fn literal(data: Arc<Data>) -> Arc<dyn NodeFunction> {
         scheduler.new_job(depends, job)
     })
 }
+
+
+/// Creates a node function that takes a single struct as an input and returns
+/// the value of the field at the given index within that struct.
+///
+/// If the input is an error, then that error is returned instead.
+pub fn field(index: usize) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let input = node_fut.get_inputs()[0].get_data().unwrap();
+            let data = match &*input {
+                Data::Struct(val) if index < val.fields.len() => val.fields[index].clone(),
+                Data::Error(_) => (*input).clone(),
+                other => Data::Error(format!("Cannot read field {} from {}", index, other)),
+            };
+            node_fut.set_data(Arc::new(data));
+            vec![]
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Creates a node function that takes a single tuple as an input and returns
+/// the element at the given index within that tuple.
+///
+/// If the input is an error, then that error is returned instead.
+pub fn element(index: usize) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let input = node_fut.get_inputs()[0].get_data().unwrap();
+            let data = match &*input {
+                Data::Tuple(elements) if index < elements.len() => elements[index].clone(),
+                Data::Error(_) => (*input).clone(),
+                other => Data::Error(format!("Cannot read element {} from {}", index, other)),
+            };
+            node_fut.set_data(Arc::new(data));
+            vec![]
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Creates a node function that builds a struct of the given type, where each
+/// node input is assigned to the field at the same index.
+///
+/// If any of the inputs are an error, then the first error is returned instead.
+pub fn construct(struct_type: String) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let struct_type = struct_type.clone();
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let fields: Vec<Data> = node_fut
+                .get_inputs()
+                .iter()
+                .map(|input| (*input.get_data().unwrap()).clone())
+                .collect();
+
+            let data = match fields.iter().find(|field| matches!(field, Data::Error(_))) {
+                Some(error) => error.clone(),
+                None => {
+                    Data::Struct(StructData {
+                        struct_type: struct_type.clone(),
+                        fields,
+                    })
+                },
+            };
+
+            node_fut.set_data(Arc::new(data));
+            vec![]
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Creates a node function that takes a boolean condition followed by two
+/// branch inputs, and outputs the value of the first branch if the condition is
+/// true, or the second branch otherwise.
+///
+/// Only the condition is evaluated before this node is executed. Once the
+/// condition is known, only the selected branch is evaluated, and the other
+/// branch is left untouched. If the condition is an error, then that error is
+/// returned instead.
+pub fn select() -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+        let condition = node.get_inputs()[0].clone();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let inputs = node_fut.get_inputs();
+            let branch = match &*inputs[0].get_data().unwrap() {
+                Data::Bool(true) => inputs[1].clone(),
+                Data::Bool(false) => inputs[2].clone(),
+                other => {
+                    node_fut.set_data(Arc::new(other.clone()));
+                    return vec![];
+                },
+            };
+
+            let handle = match branch.execute() {
+                Some(handle) => handle,
+                None => {
+                    node_fut.set_data(branch.get_data().unwrap());
+                    return vec![];
+                },
+            };
+
+            let node_copy = node_fut.clone();
+            let copy = move || {
+                node_copy.set_data(branch.get_data().unwrap());
+                vec![]
+            };
+
+            let mut scheduler = node_fut.get_scheduler();
+            vec![scheduler.new_job(vec![handle], copy)]
+        };
+
+        let depends = condition.execute().into_iter().collect();
+        scheduler.new_job(depends, job)
+    })
+}
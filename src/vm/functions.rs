@@ -0,0 +1,114 @@
+//! Built-in node functions for creating and calling function values.
+
+
+use super::{extern_func, Node, NodeFunction};
+use crate::data::{Data, FunctionData, FunctionPointer};
+use crate::multithreading::jobs::Scheduler;
+use std::sync::{Arc, OnceLock};
+
+
+/// A table of all linked internal functions, where each function is stored at
+/// the same index as the internal function within the bytecode it was linked
+/// from. The table is assigned once all functions have been linked.
+pub type FunctionTable = OnceLock<Vec<Arc<dyn NodeFunction>>>;
+
+
+/// Creates a node function that outputs a function value for the given
+/// function, capturing the data values of all node inputs.
+///
+/// If any of the inputs are an error, then the first error is returned instead.
+pub fn closure(function: FunctionPointer) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let captures: Vec<Data> = node_fut
+                .get_inputs()
+                .iter()
+                .map(|input| (*input.get_data().unwrap()).clone())
+                .collect();
+
+            let data = match captures.iter().find(|capture| matches!(capture, Data::Error(_))) {
+                Some(error) => error.clone(),
+                None => {
+                    Data::Function(FunctionData {
+                        function,
+                        captures,
+                    })
+                },
+            };
+
+            node_fut.set_data(Arc::new(data));
+            vec![]
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Creates a node function that calls the function value provided by the first
+/// input, passing the captured values of that function value followed by all
+/// remaining inputs as arguments.
+///
+/// Only the function value is evaluated before this node is executed. The
+/// arguments are evaluated as they are needed by the called function. If the
+/// function value is an error, then that error is returned instead.
+pub fn invoke(table: Arc<FunctionTable>) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let table = table.clone();
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+        let callee = node.get_inputs()[0].clone();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let mut inputs = node_fut.get_inputs();
+            let callee = inputs.remove(0).get_data().unwrap();
+            let function = match &*callee {
+                Data::Function(function) => function,
+                Data::Error(_) => {
+                    node_fut.set_data(callee.clone());
+                    return vec![];
+                },
+                other => {
+                    let error = Data::Error(format!("Cannot call {}", other));
+                    node_fut.set_data(Arc::new(error));
+                    return vec![];
+                },
+            };
+
+            let mut scheduler = node_fut.get_scheduler();
+            let mut arguments: Vec<Arc<Node>> = function
+                .captures
+                .iter()
+                .map(|capture| Arc::new(Node::with_data(&scheduler, Arc::new(capture.clone()))))
+                .collect();
+            arguments.extend(inputs);
+
+            let target = match function.function {
+                FunctionPointer::Internal(index) => {
+                    table.get().expect("Function table has not been linked")[index].clone()
+                },
+                FunctionPointer::External(func) => extern_func(func),
+            };
+
+            let call = Arc::new(Node::new(&scheduler, arguments, target));
+            let handle = call.execute().unwrap();
+
+            let node_copy = node_fut.clone();
+            let copy = move || {
+                node_copy.set_data(call.get_data().unwrap());
+                vec![]
+            };
+
+            vec![scheduler.new_job(vec![handle], copy)]
+        };
+
+        let depends = callee.execute().into_iter().collect();
+        scheduler.new_job(depends, job)
+    })
+}
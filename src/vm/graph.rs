@@ -30,6 +30,7 @@ impl Display for NodeInputPointer {
 pub struct NodeInitializer {
     inputs: Vec<NodeInputPointer>,
     func:   Arc<dyn NodeFunction>,
+    after:  Option<usize>,
 }
 
 impl NodeInitializer {
@@ -39,13 +40,27 @@ impl NodeInitializer {
         NodeInitializer {
             inputs,
             func,
+            after: None,
         }
     }
+
+
+    /// Sets the index of the hidden node that must finish executing before the
+    /// created node is executed.
+    pub fn set_after(&mut self, after: Option<usize>) {
+        self.after = after;
+    }
 }
 
 
 /// Creates a new graph as an executable job function.
-pub fn graph(output_node: usize, nodes: Vec<NodeInitializer>) -> Arc<dyn NodeFunction> {
+///
+/// The graph outputs the value of the given output node. If a hidden node index
+/// is given for `after`, then the graph only outputs it's value once that node
+/// has also finished executing.
+pub fn graph(
+    output: NodeInputPointer, after: Option<usize>, nodes: Vec<NodeInitializer>,
+) -> Arc<dyn NodeFunction> {
     Arc::new(move |node| {
         let node = node.clone();
         let inputs = node.get_inputs();
@@ -62,20 +77,34 @@ pub fn graph(output_node: usize, nodes: Vec<NodeInitializer>) -> Arc<dyn NodeFun
             }
 
             let node_func = node_init.func.clone();
-            let hidden_node = Node::new(&scheduler, node_inputs, node_func);
+            let mut hidden_node = Node::new(&scheduler, node_inputs, node_func);
+            if let Some(index) = node_init.after {
+                hidden_node.set_after(hidden_nodes[index].clone());
+            }
+
             hidden_nodes.push(Arc::new(hidden_node));
         }
 
-        let output_node_fut = hidden_nodes[output_node].clone();
+        let output_node = match output {
+            NodeInputPointer::ParamsNode(index) => inputs[index].clone(),
+            NodeInputPointer::HiddenNode(index) => hidden_nodes[index].clone(),
+        };
+
+        // Only the output node, and the node the output is ordered after, are
+        // executed here. Each node executes it's own inputs as needed, so nodes
+        // that do not contribute to the output, such as untaken branches, are
+        // never evaluated.
+        let mut depends: Vec<_> = output_node.execute().into_iter().collect();
+        if let Some(index) = after {
+            depends.extend(hidden_nodes[index].execute());
+        }
+
         let job = move || {
-            let data = output_node_fut.get_data().unwrap();
+            let data = output_node.get_data().unwrap();
             node.set_data(data);
             vec![]
         };
 
-        let mut depends = hidden_nodes[output_node].inputs_as_dependencies();
-        depends.push(hidden_nodes[output_node].execute().unwrap());
-        dbg!(&depends);
         scheduler.new_job(depends, job)
     })
 }
@@ -0,0 +1,404 @@
+//! Built-in higher-order functions that apply a Vertex function to each element
+//! of a list.
+//!
+//! Each function call is evaluated as it's own node, allowing the calls to be
+//! spread across all of the workers of the job scheduler.
+//!
+//! Any node inputs after the inputs that are expected by a higher-order
+//! function are passed to each call of the given function before the elements
+//! of the list. This is used to pass function values to [`super::invoke`].
+
+
+use super::{build_list, Node, NodeFunction};
+use crate::data::Data;
+use crate::multithreading::jobs::{JobHandle, Scheduler};
+use std::sync::Arc;
+
+
+/// Creates a node function that takes a list as an input and outputs a new list
+/// where the given function has been applied to each element.
+///
+/// All function calls are evaluated in parallel. If the list or any of the
+/// results are an error, then the first error is returned instead.
+pub fn map(func: Arc<dyn NodeFunction>) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let func = func.clone();
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let elements = match list_elements(&node_fut) {
+                Ok(elements) => elements,
+                Err(error) => {
+                    node_fut.set_data(Arc::new(error));
+                    return vec![];
+                },
+            };
+
+            let bound = bound_inputs(&node_fut, 1);
+            let calls = elements
+                .into_iter()
+                .map(|element| call(&node_fut, &func, &bound, vec![element]))
+                .collect();
+
+            gather(&node_fut, calls, build_list)
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Creates a node function that takes a list as an input and outputs a new list
+/// containing only the elements for which the given function returns true.
+///
+/// All function calls are evaluated in parallel. If the list or any of the
+/// results are an error, then the first error is returned instead.
+pub fn filter(func: Arc<dyn NodeFunction>) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let func = func.clone();
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let elements = match list_elements(&node_fut) {
+                Ok(elements) => elements,
+                Err(error) => {
+                    node_fut.set_data(Arc::new(error));
+                    return vec![];
+                },
+            };
+
+            let bound = bound_inputs(&node_fut, 1);
+            let calls = elements
+                .iter()
+                .map(|element| call(&node_fut, &func, &bound, vec![element.clone()]))
+                .collect();
+
+            gather(&node_fut, calls, move |results| {
+                let mut kept = vec![];
+                for (element, result) in elements.iter().zip(results) {
+                    match &*result {
+                        Data::Bool(true) => kept.push((*element.get_data().unwrap()).clone()),
+                        Data::Bool(false) => {},
+                        other => return other.clone(),
+                    }
+                }
+
+                Data::List(kept)
+            })
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Creates a node function that takes a list as an input and combines all of
+/// it's elements into a single value using the given function.
+///
+/// The given function must be associative. Elements are combined in pairs,
+/// forming a balanced tree of function calls, where each level of the tree is
+/// evaluated in parallel. Reducing an empty list returns an error.
+pub fn reduce(func: Arc<dyn NodeFunction>) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let func = func.clone();
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let mut level = match list_elements(&node_fut) {
+                Ok(elements) if elements.is_empty() => {
+                    let error = Data::Error(String::from("Cannot reduce an empty list"));
+                    node_fut.set_data(Arc::new(error));
+                    return vec![];
+                },
+                Ok(elements) => elements,
+                Err(error) => {
+                    node_fut.set_data(Arc::new(error));
+                    return vec![];
+                },
+            };
+
+            let bound = bound_inputs(&node_fut, 1);
+            while level.len() > 1 {
+                level = level
+                    .chunks(2)
+                    .map(|pair| {
+                        match pair {
+                            [lhs, rhs] => {
+                                call(&node_fut, &func, &bound, vec![lhs.clone(), rhs.clone()])
+                            },
+                            _ => pair[0].clone(),
+                        }
+                    })
+                    .collect();
+            }
+
+            gather(&node_fut, level, |results| (*results[0]).clone())
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Creates a node function that takes a list and an initial value as inputs,
+/// and combines the initial value with each element of the list, in order,
+/// using the given function.
+///
+/// Unlike [`reduce`], the function calls are evaluated one after another, as
+/// each call depends on the result of the previous call. Folding an empty list
+/// returns the initial value.
+pub fn fold(func: Arc<dyn NodeFunction>) -> Arc<dyn NodeFunction> {
+    Arc::new(move |node: &Arc<Node>| {
+        let func = func.clone();
+        let node = node.clone();
+        let mut scheduler = node.get_scheduler();
+
+        let node_fut = node.clone();
+        let job = move || {
+            let elements = match list_elements(&node_fut) {
+                Ok(elements) => elements,
+                Err(error) => {
+                    node_fut.set_data(Arc::new(error));
+                    return vec![];
+                },
+            };
+
+            let bound = bound_inputs(&node_fut, 2);
+            let mut accumulator = node_fut.get_inputs()[1].clone();
+            for element in elements {
+                accumulator = call(&node_fut, &func, &bound, vec![accumulator, element]);
+            }
+
+            gather(&node_fut, vec![accumulator], |results| {
+                (*results[0]).clone()
+            })
+        };
+
+        let depends = node.inputs_as_dependencies();
+        scheduler.new_job(depends, job)
+    })
+}
+
+
+/// Reads the list from the first input of the given node, and wraps each
+/// element of that list within it's own evaluated node.
+///
+/// If the input is not a list, then the input is returned as an error instead.
+fn list_elements(node: &Arc<Node>) -> Result<Vec<Arc<Node>>, Data> {
+    let scheduler = node.get_scheduler();
+    let input = node.get_inputs()[0].get_data().unwrap();
+    match &*input {
+        Data::List(elements) => {
+            Ok(elements
+                .iter()
+                .map(|element| Arc::new(Node::with_data(&scheduler, Arc::new(element.clone()))))
+                .collect())
+        },
+        Data::Error(_) => Err((*input).clone()),
+        other => Err(Data::Error(format!("Expected a list, but found {}", other))),
+    }
+}
+
+
+/// Gets the inputs of the given node that come after the given number of
+/// inputs expected by the higher-order function.
+fn bound_inputs(node: &Arc<Node>, expected: usize) -> Vec<Arc<Node>> {
+    node.get_inputs().split_off(expected)
+}
+
+
+/// Creates a node that calls the given function with the bound inputs followed
+/// by the given inputs, and starts executing it.
+///
+/// The inputs must already be evaluated, or be executing. Executing each call
+/// as soon as it is created keeps long chains of calls from being evaluated
+/// recursively.
+fn call(
+    node: &Arc<Node>, func: &Arc<dyn NodeFunction>, bound: &[Arc<Node>], inputs: Vec<Arc<Node>>,
+) -> Arc<Node> {
+    let inputs = bound.iter().cloned().chain(inputs).collect();
+    let call = Arc::new(Node::new(&node.get_scheduler(), inputs, func.clone()));
+    call.execute();
+    call
+}
+
+
+/// Waits for all of the given nodes to be evaluated, and then assigns the
+/// combined output of those nodes as the data of the target node.
+///
+/// Returns the list of child jobs that the current job needs to wait on.
+fn gather(
+    node: &Arc<Node>, nodes: Vec<Arc<Node>>,
+    combine: impl Fn(Vec<Arc<Data>>) -> Data + Send + Sync + 'static,
+) -> Vec<JobHandle> {
+    let depends: Vec<JobHandle> = nodes.iter().filter_map(|node| node.execute()).collect();
+    if depends.is_empty() {
+        let results = nodes.iter().map(|node| node.get_data().unwrap()).collect();
+        node.set_data(Arc::new(combine(results)));
+        return vec![];
+    }
+
+    let node_fut = node.clone();
+    let job = move || {
+        let results = nodes.iter().map(|node| node.get_data().unwrap()).collect();
+        node_fut.set_data(Arc::new(combine(results)));
+        vec![]
+    };
+
+    let mut scheduler = node.get_scheduler();
+    vec![scheduler.new_job(depends, job)]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multithreading;
+    use crate::multithreading::jobs::JobScheduler;
+    use crate::vm::{evaluate, extern_func, graph, literal, NodeInitializer, NodeInputPointer};
+    use ntest::timeout;
+    use pretty_assertions::assert_eq;
+
+
+    fn concat(inputs: Vec<Arc<Data>>) -> Data {
+        match (&*inputs[0], &*inputs[1]) {
+            (Data::String(a), Data::String(b)) => Data::String(format!("{}{}", a, b)),
+            (a, b) => Data::Error(format!("Cannot concatenate {} and {}", a, b)),
+        }
+    }
+
+
+    fn subtract(inputs: Vec<Arc<Data>>) -> Data {
+        match (&*inputs[0], &*inputs[1]) {
+            (Data::Int(a), Data::Int(b)) => Data::Int(a - b),
+            (a, b) => Data::Error(format!("Cannot subtract {} from {}", b, a)),
+        }
+    }
+
+
+    fn is_even(inputs: Vec<Arc<Data>>) -> Data {
+        match &*inputs[0] {
+            Data::Int(n) => Data::Bool(n % 2 == 0),
+            other => Data::Error(format!("Expected an Int, but found {}", other)),
+        }
+    }
+
+
+    fn ints(values: &[i64]) -> Data {
+        Data::List(values.iter().map(|v| Data::Int(*v)).collect())
+    }
+
+
+    fn strings(values: &[&str]) -> Data {
+        Data::List(values.iter().map(|v| Data::String(String::from(*v))).collect())
+    }
+
+
+    /// Evaluates the given higher-order function with the given inputs, and
+    /// returns it's output.
+    fn apply(func: Arc<dyn NodeFunction>, inputs: Vec<Data>) -> Data {
+        let count = inputs.len();
+        let mut nodes: Vec<NodeInitializer> = inputs
+            .into_iter()
+            .map(|input| NodeInitializer::new(literal(Arc::new(input)), vec![]))
+            .collect();
+        nodes.push(NodeInitializer::new(
+            func,
+            (0..count).map(NodeInputPointer::HiddenNode).collect(),
+        ));
+
+        let scheduler = JobScheduler::new().into_async();
+        multithreading::build_workers(&scheduler, 2);
+        let output = evaluate(&scheduler, graph(NodeInputPointer::HiddenNode(count), None, nodes))
+            .complete();
+        scheduler.terminate_workers();
+        (*output).clone()
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn empty_lists() {
+        assert_eq!(apply(map(extern_func(is_even)), vec![ints(&[])]), ints(&[]));
+        assert_eq!(apply(filter(extern_func(is_even)), vec![ints(&[])]), ints(&[]));
+        assert_eq!(
+            apply(reduce(extern_func(subtract)), vec![ints(&[])]),
+            Data::Error(String::from("Cannot reduce an empty list"))
+        );
+        assert_eq!(
+            apply(fold(extern_func(subtract)), vec![ints(&[]), Data::Int(7)]),
+            Data::Int(7)
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn single_elements() {
+        assert_eq!(
+            apply(map(extern_func(is_even)), vec![ints(&[4])]),
+            Data::List(vec![Data::Bool(true)])
+        );
+        assert_eq!(apply(filter(extern_func(is_even)), vec![ints(&[3])]), ints(&[]));
+        assert_eq!(apply(reduce(extern_func(subtract)), vec![ints(&[5])]), Data::Int(5));
+        assert_eq!(
+            apply(fold(extern_func(subtract)), vec![ints(&[5]), Data::Int(7)]),
+            Data::Int(2)
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn reduce_keeps_element_order() {
+        let letters = strings(&["a", "b", "c", "d", "e", "f", "g"]);
+        assert_eq!(
+            apply(reduce(extern_func(concat)), vec![letters.clone()]),
+            Data::String(String::from("abcdefg"))
+        );
+        assert_eq!(
+            apply(fold(extern_func(concat)), vec![letters, Data::String(String::from(">"))]),
+            Data::String(String::from(">abcdefg"))
+        );
+        assert_eq!(
+            apply(fold(extern_func(subtract)), vec![ints(&[1, 2, 3]), Data::Int(10)]),
+            Data::Int(4)
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn bound_inputs_come_first() {
+        assert_eq!(
+            apply(map(extern_func(concat)), vec![
+                strings(&["x", "y"]),
+                Data::String(String::from("-")),
+            ]),
+            strings(&["-x", "-y"])
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn errors_propagate() {
+        assert_eq!(
+            apply(filter(extern_func(is_even)), vec![strings(&["a"])]),
+            Data::Error(String::from("Expected an Int, but found a"))
+        );
+        assert_eq!(
+            apply(map(extern_func(is_even)), vec![Data::Int(1)]),
+            Data::Error(String::from("Expected a list, but found 1"))
+        );
+    }
+}
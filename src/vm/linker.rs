@@ -0,0 +1,697 @@
+use super::{
+    build_dictionary, build_list, build_tuple, closure, construct, element, extern_func, field, filter, fold, graph, invoke, literal, map, operator_function, reduce, select, FunctionTable, NodeFunction, NodeInitializer, NodeInputPointer
+};
+use crate::compiler::bytecode::{FunctionCall, OperationInput, VertexBytecode};
+use crate::data::{Data, FunctionPointer};
+use std::sync::{Arc, OnceLock};
+
+
+/// Links the given bytecode into a set of executable node functions.
+///
+/// Each internal function within the bytecode is converted into a graph, where
+/// the returned graph at a given index corresponds to the internal function at
+/// that same index. Functions without an output output a Null value.
+///
+/// Calls to internal functions are resolved lazily when the calling node is
+/// executed, allowing for recursive and mutually recursive function calls.
+pub fn link(bytecode: &VertexBytecode) -> Vec<Arc<dyn NodeFunction>> {
+    let table: Arc<FunctionTable> = Arc::new(OnceLock::new());
+
+    let mut functions = vec![];
+    for function in bytecode.get_internal_functions() {
+        let mut nodes = vec![];
+        for operation in function.get_operations() {
+            let func = link_call(bytecode, &table, operation.get_function());
+
+            let inputs = operation.get_inputs().iter().map(input_pointer).collect();
+            let mut node = NodeInitializer::new(func, inputs);
+            node.set_after(operation.get_after());
+            nodes.push(node);
+        }
+
+        let output = match function.get_output() {
+            Some(output) => input_pointer(output),
+            None => {
+                nodes.push(NodeInitializer::new(literal(Arc::new(Data::Null)), vec![]));
+                NodeInputPointer::HiddenNode(nodes.len() - 1)
+            },
+        };
+
+        functions.push(graph(output, function.get_after(), nodes));
+    }
+
+    if table.set(functions.clone()).is_err() {
+        unreachable!("Function table is only assigned once");
+    }
+
+    functions
+}
+
+
+/// Converts the given operation input into a node input pointer.
+fn input_pointer(input: &OperationInput) -> NodeInputPointer {
+    match input {
+        OperationInput::Param(index) => NodeInputPointer::ParamsNode(*index),
+        OperationInput::Hidden(index) => NodeInputPointer::HiddenNode(*index),
+    }
+}
+
+
+/// Converts the given function call into an executable node function.
+fn link_call(
+    bytecode: &VertexBytecode, table: &Arc<FunctionTable>, call: &FunctionCall,
+) -> Arc<dyn NodeFunction> {
+    match call {
+        FunctionCall::Internal(index) => internal_func(table, *index),
+        FunctionCall::External(index) => {
+            let ext_func = &bytecode.get_external_functions()[*index];
+            extern_func(*ext_func.get_function_exec())
+        },
+        FunctionCall::Constant(index) => {
+            literal(Arc::new(bytecode.get_constants()[*index].clone()))
+        },
+        FunctionCall::Field(index) => field(*index),
+        FunctionCall::Element(index) => element(*index),
+        FunctionCall::Operator(operator) => extern_func(operator_function(*operator)),
+        FunctionCall::Select => select(),
+        FunctionCall::Map(target) => map(link_call(bytecode, table, target)),
+        FunctionCall::Filter(target) => filter(link_call(bytecode, table, target)),
+        FunctionCall::Reduce(target) => reduce(link_call(bytecode, table, target)),
+        FunctionCall::Fold(target) => fold(link_call(bytecode, table, target)),
+        FunctionCall::Closure(target) => {
+            closure(match **target {
+                FunctionCall::Internal(index) => FunctionPointer::Internal(index),
+                FunctionCall::External(index) => {
+                    let ext_func = &bytecode.get_external_functions()[index];
+                    FunctionPointer::External(*ext_func.get_function_exec())
+                },
+                _ => panic!("Closures may only capture internal or external functions"),
+            })
+        },
+        FunctionCall::Invoke => invoke(table.clone()),
+        FunctionCall::BuildList => extern_func(build_list),
+        FunctionCall::BuildTuple => extern_func(build_tuple),
+        FunctionCall::BuildDictionary => extern_func(build_dictionary),
+        FunctionCall::Struct(index) => construct(bytecode.get_struct_types()[*index].clone()),
+    }
+}
+
+
+/// Creates a node function that forwards execution to the internal function at
+/// the given index within the function table.
+///
+/// Note that the function table holds onto the graphs which in turn hold onto
+/// the function table, so linked functions are never deallocated.
+fn internal_func(table: &Arc<FunctionTable>, index: usize) -> Arc<dyn NodeFunction> {
+    let table = table.clone();
+    Arc::new(move |node| {
+        let function = table.get().expect("Function table has not been linked")[index].clone();
+        function(node)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::bytecode::{
+        bytecode_from_ir, ExternalFunction, InternalFunction, Operation
+    };
+    use crate::compiler::grammar::parse;
+    use crate::compiler::ir::{compile_context, IRDataType};
+    use crate::compiler::resolver::resolve_context;
+    use crate::compiler::typecheck::check_types;
+    use crate::data::StructData;
+    use crate::multithreading::jobs::{JobScheduler, Scheduler};
+    use crate::registry::{FuncMeta, FunctionRegistry};
+    use crate::vm::evaluate;
+    use crate::{multithreading, unwrap_data};
+    use indoc::indoc;
+    use ntest::timeout;
+    use std::sync::Mutex;
+
+
+    fn add(inputs: Vec<Arc<Data>>) -> Data {
+        let a = unwrap_data!(inputs[0], Int);
+        let b = unwrap_data!(inputs[1], Int);
+        Data::Int(a + b)
+    }
+
+
+    fn mul(inputs: Vec<Arc<Data>>) -> Data {
+        let a = unwrap_data!(inputs[0], Int);
+        let b = unwrap_data!(inputs[1], Int);
+        Data::Int(a * b)
+    }
+
+
+    fn run(functions: &[Arc<dyn NodeFunction>], index: usize) -> Arc<Data> {
+        let scheduler = JobScheduler::new().into_async();
+        multithreading::build_workers(&scheduler, 2);
+
+        let data = evaluate(&scheduler, functions[index].clone()).complete();
+        scheduler.terminate_workers();
+        data
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_compiled_source() {
+        let mut registry = FunctionRegistry::new();
+        let int_args = vec![IRDataType::Int, IRDataType::Int];
+        registry
+            .register(
+                FuncMeta::new(String::from("Add"), add, int_args.clone(), IRDataType::Int).unwrap(),
+            )
+            .unwrap();
+        registry
+            .register(FuncMeta::new(String::from("Mul"), mul, int_args, IRDataType::Int).unwrap())
+            .unwrap();
+
+        let ast = parse(indoc! {r#"
+            Math = mod {
+                Main = function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = extern Add(extern Mul(2, 4), 6)
+                }
+            }
+        "#})
+        .unwrap();
+
+        let context = compile_context(ast, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(*run(&functions, 0), Data::Int(14));
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_recursive_functions() {
+        let mut bytecode = VertexBytecode::new();
+        bytecode.add_constant(Data::Int(5));
+        let int_args = vec![IRDataType::Int, IRDataType::Int];
+        bytecode.add_external_function(ExternalFunction::new(
+            String::from("Mul"),
+            mul,
+            int_args,
+            IRDataType::Int,
+        ));
+
+        // Main: Square(5), where Square is defined after Main.
+        let mut main = InternalFunction::new();
+        main.add_operation(Operation::new(FunctionCall::Constant(0), vec![]));
+        main.add_operation(Operation::new(FunctionCall::Internal(1), vec![
+            OperationInput::Hidden(0),
+        ]));
+        main.set_output(Some(OperationInput::Hidden(1)));
+        bytecode.add_internal_function(main);
+
+        // Square: Mul(x, x)
+        let mut square = InternalFunction::new();
+        square.add_operation(Operation::new(FunctionCall::External(0), vec![
+            OperationInput::Param(0),
+            OperationInput::Param(0),
+        ]));
+        square.set_output(Some(OperationInput::Hidden(0)));
+        bytecode.add_internal_function(square);
+
+        // Ping and Pong call each other, and are never executed.
+        let mut ping = InternalFunction::new();
+        ping.add_operation(Operation::new(FunctionCall::Internal(3), vec![
+            OperationInput::Param(0),
+        ]));
+        ping.set_output(Some(OperationInput::Hidden(0)));
+        bytecode.add_internal_function(ping);
+
+        let mut pong = InternalFunction::new();
+        pong.add_operation(Operation::new(FunctionCall::Internal(2), vec![
+            OperationInput::Param(0),
+        ]));
+        pong.set_output(Some(OperationInput::Hidden(0)));
+        bytecode.add_internal_function(pong);
+
+        let functions = link(&bytecode);
+        assert_eq!(functions.len(), 4);
+        assert_eq!(*run(&functions, 0), Data::Int(25));
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_field_access() {
+        fn origin(_: Vec<Arc<Data>>) -> Data {
+            Data::Struct(StructData {
+                struct_type: String::from("Geometry.Point"),
+                fields:      vec![Data::Float(1.5), Data::Float(-2.0)],
+            })
+        }
+
+        let point = IRDataType::Struct(String::from("Geometry.Point"), vec![
+            (String::from("x"), IRDataType::Float),
+            (String::from("y"), IRDataType::Float),
+        ]);
+
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register(FuncMeta::new(String::from("Origin"), origin, vec![], point).unwrap())
+            .unwrap();
+
+        let ast = parse(indoc! {r#"
+            Geometry = mod {
+                Main = function {
+                    params = ()
+                    return = (y: Float)
+
+                    point = extern Origin()
+                    y = point.y
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(*run(&functions, 0), Data::Float(-2.0));
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_struct_construction() {
+        let registry = FunctionRegistry::new();
+        let ast = parse(indoc! {r#"
+            Geometry = mod {
+                Point = struct {
+                    x: Int
+                    y: Int
+                }
+
+                Main = function {
+                    params = ()
+                    return = (point: Point)
+
+                    point = Point {
+                        y = 2,
+                        x = 1,
+                    }
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(
+            *run(&functions, 0),
+            Data::Struct(StructData {
+                struct_type: String::from("Geometry.Point"),
+                fields:      vec![Data::Int(1), Data::Int(2)],
+            })
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_collection_literals() {
+        let registry = FunctionRegistry::new();
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = ()
+                    return = (result: (Int[], {String: Bool}))
+
+                    result = ([1, 2, 3], {"yes": true, "no": false})
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(
+            *run(&functions, 0),
+            Data::Tuple(vec![
+                Data::List(vec![Data::Int(1), Data::Int(2), Data::Int(3)]),
+                Data::Dictionary(
+                    vec![Data::String(String::from("yes")), Data::String(String::from("no"))],
+                    vec![Data::Bool(true), Data::Bool(false)]
+                ),
+            ])
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_operators() {
+        let registry = FunctionRegistry::new();
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = ()
+                    return = (result: (Int, Float, Bool, String))
+
+                    result = (1 + 2 * 3 - -4 % 3, 1 / 2.0, 2 >= 2 and not false, "a" + "b")
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(
+            *run(&functions, 0),
+            Data::Tuple(vec![
+                Data::Int(8),
+                Data::Float(0.5),
+                Data::Bool(true),
+                Data::String(String::from("ab")),
+            ])
+        );
+    }
+
+
+    #[test]
+    #[timeout(5000)]
+    fn link_recursive_conditional() {
+        let registry = FunctionRegistry::new();
+        let ast = parse(indoc! {r#"
+            Math = mod {
+                Factorial = function {
+                    params = (n: Int)
+                    return = (value: Int)
+
+                    value = if n <= 1 then 1 else n * Factorial(n - 1)
+                }
+
+                Main = function {
+                    params = ()
+                    return = (value: Int)
+
+                    value = Factorial(10)
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(*run(&functions, 1), Data::Int(3628800));
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn untaken_branch_is_not_evaluated() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn count(_: Vec<Arc<Data>>) -> Data {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Data::Int(0)
+        }
+
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register(FuncMeta::new(String::from("Count"), count, vec![], IRDataType::Int).unwrap())
+            .unwrap();
+
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Main = function {
+                    params = ()
+                    return = (value: Int)
+
+                    counted = extern Count()
+                    value = if 1 > 2 then counted else 7
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(*run(&functions, 0), Data::Int(7));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+    }
+
+
+    #[test]
+    #[timeout(10000)]
+    fn link_higher_order_functions() {
+        fn range(inputs: Vec<Arc<Data>>) -> Data {
+            let count = unwrap_data!(inputs[0], Int);
+            Data::List((1..=*count).map(Data::Int).collect())
+        }
+
+        let mut registry = FunctionRegistry::new();
+        let numbers = IRDataType::List(Box::new(IRDataType::Int));
+        registry
+            .register(
+                FuncMeta::new(String::from("Range"), range, vec![IRDataType::Int], numbers)
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Square = function {
+                    params = (x: Int)
+                    return = (value: Int)
+
+                    value = x * x
+                }
+
+                IsEven = function {
+                    params = (x: Int)
+                    return = (value: Bool)
+
+                    value = x % 2 == 0
+                }
+
+                Add = function {
+                    params = (a: Int, b: Int)
+                    return = (value: Int)
+
+                    value = a + b
+                }
+
+                Subtract = function {
+                    params = (a: Int, b: Int)
+                    return = (value: Int)
+
+                    value = a - b
+                }
+
+                Main = function {
+                    params = ()
+                    return = (value: (Int[], Int, Int, Int))
+
+                    squares = map([1, 2, 3, 4], Square)
+                    evens = filter(squares, IsEven)
+                    total = reduce(extern Range(10000), Add)
+                    difference = fold(extern Range(10000), 0, Subtract)
+                    value = (squares, reduce(evens, Add), total, difference)
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        let squares = [1, 4, 9, 16].into_iter().map(Data::Int).collect();
+        assert_eq!(
+            *run(&functions, 4),
+            Data::Tuple(vec![
+                Data::List(squares),
+                Data::Int(20),
+                Data::Int(50005000),
+                Data::Int(-50005000),
+            ])
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_function_values() {
+        let mut registry = FunctionRegistry::new();
+        let int_args = vec![IRDataType::Int, IRDataType::Int];
+        registry
+            .register(FuncMeta::new(String::from("Add"), add, int_args, IRDataType::Int).unwrap())
+            .unwrap();
+
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Apply = function {
+                    params = (x: Int, f: fn(Int) -> Int)
+                    return = (value: Int)
+
+                    value = f(x)
+                }
+
+                Negate = function {
+                    params = (x: Int)
+                    return = (value: Int)
+
+                    value = 0 - x
+                }
+
+                Main = function {
+                    params = ()
+                    return = (value: (Int, Int[], Int))
+
+                    offset = 10
+                    shifted = map([1, 2, 3], function {
+                        params = (x: Int)
+                        return = (value: Int)
+
+                        value = x + offset
+                    })
+                    value = (Apply(5, Negate), shifted, fold(shifted, 0, extern Add))
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        let shifted = [11, 12, 13].into_iter().map(Data::Int).collect();
+        assert_eq!(
+            *run(&functions, 2),
+            Data::Tuple(vec![Data::Int(-5), Data::List(shifted), Data::Int(36)])
+        );
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_serial_calls() {
+        static LOG: Mutex<Vec<i64>> = Mutex::new(vec![]);
+
+        fn record(inputs: Vec<Arc<Data>>) -> Data {
+            let value = unwrap_data!(inputs[0], Int);
+            LOG.lock().unwrap().push(*value);
+            Data::Int(*value)
+        }
+
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register(
+                FuncMeta::new(
+                    String::from("Record"),
+                    record,
+                    vec![IRDataType::Int],
+                    IRDataType::Int,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                Main = serial function {
+                    params = ()
+                    return = (value: Int)
+
+                    serial extern Record(1)
+                    first = serial extern Record(2)
+                    serial extern Record(10 * (if first > 1 then serial extern Record(3) else serial extern Record(-1)))
+                    serial extern Record(first + 3)
+                    value = first * 10
+                }
+            }
+        "#})
+        .unwrap();
+
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(*run(&functions, 0), Data::Int(20));
+        assert_eq!(*LOG.lock().unwrap(), vec![1, 2, 3, 30, 5]);
+    }
+
+
+    #[test]
+    #[timeout(1000)]
+    fn link_multiple_returns() {
+        let ast = parse(indoc! {r#"
+            Main = mod {
+                DivMod = function {
+                    params = (a: Int, b: Int)
+                    return = (quotient: Int, remainder: Int)
+
+                    remainder = a % b
+                    quotient = a / b
+                }
+
+                First = function {
+                    params = (a: Int, b: Int)
+                    return = (value: Int)
+
+                    value = a
+                }
+
+                Main = function {
+                    params = ()
+                    return = (value: (Int, Int, Int))
+
+                    q, r = DivMod(17, 5)
+                    value = (q, r, First(q, r))
+                }
+            }
+        "#})
+        .unwrap();
+
+        let registry = FunctionRegistry::new();
+        let mut context = compile_context(ast, &registry).unwrap();
+        resolve_context(&mut context).unwrap();
+        check_types(&mut context, &registry).unwrap();
+        let bytecode = bytecode_from_ir(context, &registry);
+        let functions = link(&bytecode);
+
+        assert_eq!(
+            *run(&functions, 2),
+            Data::Tuple(vec![Data::Int(3), Data::Int(2), Data::Int(3)])
+        );
+    }
+}
@@ -1,13 +1,23 @@
 //! This module is in charge of executing a Vertex runtime.
 
 
+mod collections;
 mod externs;
+mod functions;
 mod graph;
+mod higher_order;
+mod linker;
 mod node;
+mod operators;
 
+pub use collections::*;
 pub use externs::*;
+pub use functions::*;
 pub use graph::*;
+pub use higher_order::*;
+pub use linker::*;
 pub use node::*;
+pub use operators::*;
 
 
 #[cfg(test)]
@@ -41,7 +51,7 @@ mod tests {
         let ext_mul = extern_func(mul);
         let ext_add = extern_func(add);
 
-        let graph_madd = graph(1, vec![
+        let graph_madd = graph(NodeInputPointer::HiddenNode(1), None, vec![
             NodeInitializer::new(ext_mul, vec![
                 NodeInputPointer::ParamsNode(0),
                 NodeInputPointer::ParamsNode(1),
@@ -52,7 +62,7 @@ mod tests {
             ]),
         ]);
 
-        let graph_main = graph(3, vec![
+        let graph_main = graph(NodeInputPointer::HiddenNode(3), None, vec![
             NodeInitializer::new(const_2, vec![]),
             NodeInitializer::new(const_4, vec![]),
             NodeInitializer::new(const_6, vec![]),
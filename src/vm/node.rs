@@ -1,6 +1,6 @@
 use crate::data::Data;
 use crate::multithreading::jobs::{AsyncJobScheduler, JobHandle, Scheduler};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 
 
 /// A function that is executed for a node in order to evaluate it's output
@@ -19,12 +19,13 @@ impl<Func: Fn(&Arc<Node>) -> JobHandle + Send + Sync> NodeFunction for Func {}
 
 struct NodeData {
     inputs: Option<Vec<Arc<Node>>>,
+    after:  Option<Arc<Node>>,
     data:   NodeDataState,
 }
 
 enum NodeDataState {
     None,
-    Pending,
+    Pending(Option<JobHandle>),
     Done(Arc<Data>),
 }
 
@@ -34,6 +35,7 @@ pub struct Node {
     scheduler: AsyncJobScheduler,
     func:      Arc<dyn NodeFunction>,
     data:      Mutex<NodeData>,
+    scheduled: Condvar,
 }
 
 impl Node {
@@ -50,12 +52,39 @@ impl Node {
             func,
             data: Mutex::new(NodeData {
                 inputs: Some(inputs),
+                after:  None,
                 data:   NodeDataState::None,
             }),
+            scheduled: Condvar::new(),
         }
     }
 
 
+    /// Creates a new node instance that has already been evaluated to the given
+    /// data value, and has no inputs.
+    pub(crate) fn with_data(scheduler: &AsyncJobScheduler, data: Arc<Data>) -> Self {
+        Node {
+            scheduler: scheduler.clone(),
+            func:      Arc::new(|_: &Arc<Node>| unreachable!("Node already has data")),
+            data:      Mutex::new(NodeData {
+                inputs: None,
+                after:  None,
+                data:   NodeDataState::Done(data),
+            }),
+            scheduled: Condvar::new(),
+        }
+    }
+
+
+    /// Sets the node that must finish executing before this node is executed.
+    ///
+    /// Unlike inputs, the data of this node is not used. This is used to order
+    /// nodes that have side effects.
+    pub(crate) fn set_after(&mut self, after: Arc<Node>) {
+        self.data.get_mut().unwrap().after = Some(after);
+    }
+
+
     /// Gets a reference to the async job scheduler used by this node.
     pub fn get_scheduler(self: &Arc<Node>) -> AsyncJobScheduler {
         self.scheduler.clone()
@@ -90,6 +119,7 @@ impl Node {
         // deallocated if they no longer have any pending children nodes. This helps
         // reduce memory usage by deallocating data that no longer has any references.
         node_data.inputs = None;
+        node_data.after = None;
         node_data.data = NodeDataState::Done(data);
     }
 
@@ -107,18 +137,45 @@ impl Node {
     /// Executes the function for this node in order to evaluate it's data
     /// value based off it's inputs.
     ///
-    /// If this node has already been evaluated, nothing happens and None is
-    /// returned.
+    /// If this node is ordered after another node, that node is executed first.
+    /// If this node is already being evaluated, the job handle of that
+    /// evaluation is returned instead. If this node has already been evaluated,
+    /// nothing happens and None is returned.
     pub(crate) fn execute(self: &Arc<Node>) -> Option<JobHandle> {
         let mut node_data = self.data.lock().unwrap();
-        if let NodeDataState::None = node_data.data {
-            node_data.data = NodeDataState::Pending;
-        } else {
-            return None;
+        loop {
+            match &node_data.data {
+                NodeDataState::None => break,
+                NodeDataState::Pending(Some(handle)) => return Some(handle.clone()),
+                NodeDataState::Pending(None) => {
+                    node_data = self.scheduled.wait(node_data).unwrap();
+                },
+                NodeDataState::Done(_) => return None,
+            }
         }
 
+        node_data.data = NodeDataState::Pending(None);
+        let after = node_data.after.clone();
         drop(node_data);
-        Some((self.func)(self))
+
+        // The node function is only executed once the node it is ordered after
+        // has finished executing.
+        let handle = match after.and_then(|after| after.execute()) {
+            Some(after) => {
+                let node = self.clone();
+                let mut scheduler = self.get_scheduler();
+                scheduler.new_job(vec![after], move || vec![(node.func)(&node)])
+            },
+            None => (self.func)(self),
+        };
+
+        let mut node_data = self.data.lock().unwrap();
+        if let NodeDataState::Pending(pending) = &mut node_data.data {
+            *pending = Some(handle.clone());
+        }
+        self.scheduled.notify_all();
+
+        Some(handle)
     }
 
 
@@ -0,0 +1,320 @@
+//! Built-in functions for the operators within Vertex.
+//!
+//! Arithmetic on two Ints produces an Int, while arithmetic on an Int and a
+//! Float promotes the Int to a Float first. Int arithmetic that overflows, as
+//! well as Int division or remainder by zero, produces an error value. Float
+//! arithmetic follows IEEE 754, so Float division by zero produces an infinity
+//! or NaN value instead. If any operand is an error, that error is returned.
+//!
+//! Int division truncates toward zero, so `-7 / 2` is `-3`. The remainder takes
+//! the sign of the dividend, so that `a == (a / b) * b + a % b`, which makes
+//! `-7 % 2` equal to `-1` and `7 % -2` equal to `1`. Float remainders follow
+//! the same rule.
+
+
+use crate::compiler::ast::Operator;
+use crate::data::{Data, VertexFunction};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+
+/// Gets the function that applies the given operator to it's inputs.
+pub fn operator_function(operator: Operator) -> VertexFunction {
+    match operator {
+        Operator::Add => add,
+        Operator::Subtract => subtract,
+        Operator::Multiply => multiply,
+        Operator::Divide => divide,
+        Operator::Remainder => remainder,
+        Operator::Equal => equal,
+        Operator::NotEqual => not_equal,
+        Operator::Less => less,
+        Operator::LessEqual => less_equal,
+        Operator::Greater => greater,
+        Operator::GreaterEqual => greater_equal,
+        Operator::And => and,
+        Operator::Or => or,
+        Operator::Not => not,
+        Operator::Negate => negate,
+    }
+}
+
+
+fn add(inputs: Vec<Arc<Data>>) -> Data {
+    match (&*inputs[0], &*inputs[1]) {
+        (Data::String(a), Data::String(b)) => Data::String(format!("{}{}", a, b)),
+        (a, b) => arithmetic(a, b, i64::checked_add, |a, b| a + b),
+    }
+}
+
+
+fn subtract(inputs: Vec<Arc<Data>>) -> Data {
+    arithmetic(&inputs[0], &inputs[1], i64::checked_sub, |a, b| a - b)
+}
+
+
+fn multiply(inputs: Vec<Arc<Data>>) -> Data {
+    arithmetic(&inputs[0], &inputs[1], i64::checked_mul, |a, b| a * b)
+}
+
+
+fn divide(inputs: Vec<Arc<Data>>) -> Data {
+    if let Data::Int(0) = &*inputs[1] {
+        if let Data::Int(_) = &*inputs[0] {
+            return Data::Error(String::from("Division by zero"));
+        }
+    }
+
+    arithmetic(&inputs[0], &inputs[1], i64::checked_div, |a, b| a / b)
+}
+
+
+fn remainder(inputs: Vec<Arc<Data>>) -> Data {
+    if let Data::Int(0) = &*inputs[1] {
+        if let Data::Int(_) = &*inputs[0] {
+            return Data::Error(String::from("Division by zero"));
+        }
+    }
+
+    arithmetic(&inputs[0], &inputs[1], i64::checked_rem, |a, b| a % b)
+}
+
+
+fn equal(inputs: Vec<Arc<Data>>) -> Data {
+    match compare(&inputs[0], &inputs[1]) {
+        Ok(ordering) => Data::Bool(ordering == Some(Ordering::Equal)),
+        Err(error) => error,
+    }
+}
+
+
+fn not_equal(inputs: Vec<Arc<Data>>) -> Data {
+    match compare(&inputs[0], &inputs[1]) {
+        Ok(ordering) => Data::Bool(ordering != Some(Ordering::Equal)),
+        Err(error) => error,
+    }
+}
+
+
+fn less(inputs: Vec<Arc<Data>>) -> Data {
+    ordering(&inputs[0], &inputs[1], Ordering::is_lt)
+}
+
+
+fn less_equal(inputs: Vec<Arc<Data>>) -> Data {
+    ordering(&inputs[0], &inputs[1], Ordering::is_le)
+}
+
+
+fn greater(inputs: Vec<Arc<Data>>) -> Data {
+    ordering(&inputs[0], &inputs[1], Ordering::is_gt)
+}
+
+
+fn greater_equal(inputs: Vec<Arc<Data>>) -> Data {
+    ordering(&inputs[0], &inputs[1], Ordering::is_ge)
+}
+
+
+fn and(inputs: Vec<Arc<Data>>) -> Data {
+    match (&*inputs[0], &*inputs[1]) {
+        (Data::Bool(a), Data::Bool(b)) => Data::Bool(*a && *b),
+        (a, b) => invalid_operands("and", a, b),
+    }
+}
+
+
+fn or(inputs: Vec<Arc<Data>>) -> Data {
+    match (&*inputs[0], &*inputs[1]) {
+        (Data::Bool(a), Data::Bool(b)) => Data::Bool(*a || *b),
+        (a, b) => invalid_operands("or", a, b),
+    }
+}
+
+
+fn not(inputs: Vec<Arc<Data>>) -> Data {
+    match &*inputs[0] {
+        Data::Bool(a) => Data::Bool(!a),
+        Data::Error(e) => Data::Error(e.clone()),
+        a => Data::Error(format!("Operator 'not' cannot be applied to {}", a)),
+    }
+}
+
+
+fn negate(inputs: Vec<Arc<Data>>) -> Data {
+    match &*inputs[0] {
+        Data::Int(a) => {
+            match a.checked_neg() {
+                Some(value) => Data::Int(value),
+                None => Data::Error(String::from("Integer overflow")),
+            }
+        },
+        Data::Float(a) => Data::Float(-a),
+        Data::Error(e) => Data::Error(e.clone()),
+        a => Data::Error(format!("Operator '-' cannot be applied to {}", a)),
+    }
+}
+
+
+/// Applies an arithmetic operation to two numeric values, promoting Ints to
+/// Floats if either value is a Float.
+fn arithmetic(
+    a: &Data, b: &Data, int_op: fn(i64, i64) -> Option<i64>, float_op: fn(f64, f64) -> f64,
+) -> Data {
+    match (a, b) {
+        (Data::Int(a), Data::Int(b)) => {
+            match int_op(*a, *b) {
+                Some(value) => Data::Int(value),
+                None => Data::Error(String::from("Integer overflow")),
+            }
+        },
+        (Data::Int(a), Data::Float(b)) => Data::Float(float_op(*a as f64, *b)),
+        (Data::Float(a), Data::Int(b)) => Data::Float(float_op(*a, *b as f64)),
+        (Data::Float(a), Data::Float(b)) => Data::Float(float_op(*a, *b)),
+        (a, b) => invalid_operands("arithmetic", a, b),
+    }
+}
+
+
+/// Checks whether the ordering of the two values matches the given condition.
+fn ordering(a: &Data, b: &Data, condition: fn(Ordering) -> bool) -> Data {
+    match compare(a, b) {
+        Ok(Some(ordering)) => Data::Bool(condition(ordering)),
+        Ok(None) => Data::Bool(false),
+        Err(error) => error,
+    }
+}
+
+
+/// Compares two values, promoting Ints to Floats if compared against a Float.
+/// Values that have no defined ordering, such as NaN, return None.
+///
+/// If either value is an error, then that error is returned.
+fn compare(a: &Data, b: &Data) -> Result<Option<Ordering>, Data> {
+    match (a, b) {
+        (Data::Error(_), _) => Err(a.clone()),
+        (_, Data::Error(_)) => Err(b.clone()),
+        (Data::Int(a), Data::Int(b)) => Ok(Some(a.cmp(b))),
+        (Data::Int(a), Data::Float(b)) => Ok((*a as f64).partial_cmp(b)),
+        (Data::Float(a), Data::Int(b)) => Ok(a.partial_cmp(&(*b as f64))),
+        (Data::Float(a), Data::Float(b)) => Ok(a.partial_cmp(b)),
+        (Data::String(a), Data::String(b)) => Ok(Some(a.cmp(b))),
+        (Data::Char(a), Data::Char(b)) => Ok(Some(a.cmp(b))),
+        (a, b) if a == b => Ok(Some(Ordering::Equal)),
+        _ => Ok(None),
+    }
+}
+
+
+/// Creates the error value for an operator that was applied to invalid
+/// operands. If either operand is already an error, then that error is
+/// returned instead.
+fn invalid_operands(operator: &str, a: &Data, b: &Data) -> Data {
+    match (a, b) {
+        (Data::Error(_), _) => a.clone(),
+        (_, Data::Error(_)) => b.clone(),
+        _ => {
+            Data::Error(format!(
+                "Operator '{}' cannot be applied to {} and {}",
+                operator, a, b
+            ))
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    fn apply(operator: Operator, inputs: &[Data]) -> Data {
+        let inputs = inputs.iter().cloned().map(Arc::new).collect();
+        operator_function(operator)(inputs)
+    }
+
+
+    #[test]
+    fn int_float_promotion() {
+        assert_eq!(
+            apply(Operator::Add, &[Data::Int(1), Data::Int(2)]),
+            Data::Int(3)
+        );
+        assert_eq!(
+            apply(Operator::Add, &[Data::Int(1), Data::Float(0.5)]),
+            Data::Float(1.5)
+        );
+        assert_eq!(
+            apply(Operator::Divide, &[Data::Int(7), Data::Int(2)]),
+            Data::Int(3)
+        );
+        assert_eq!(
+            apply(Operator::Divide, &[Data::Float(7.0), Data::Int(2)]),
+            Data::Float(3.5)
+        );
+        assert_eq!(
+            apply(Operator::Equal, &[Data::Int(2), Data::Float(2.0)]),
+            Data::Bool(true)
+        );
+    }
+
+
+    #[test]
+    fn division_by_zero() {
+        let error = Data::Error(String::from("Division by zero"));
+        assert_eq!(
+            apply(Operator::Divide, &[Data::Int(1), Data::Int(0)]),
+            error
+        );
+        assert_eq!(
+            apply(Operator::Remainder, &[Data::Int(1), Data::Int(0)]),
+            error
+        );
+        assert_eq!(
+            apply(Operator::Divide, &[Data::Float(1.0), Data::Int(0)]),
+            Data::Float(f64::INFINITY)
+        );
+        assert_eq!(
+            apply(Operator::Multiply, &[Data::Int(i64::MAX), Data::Int(2)]),
+            Data::Error(String::from("Integer overflow"))
+        );
+    }
+
+
+    #[test]
+    fn negative_operands() {
+        let int = |operator, a, b| apply(operator, &[Data::Int(a), Data::Int(b)]);
+        assert_eq!(int(Operator::Divide, -7, 2), Data::Int(-3));
+        assert_eq!(int(Operator::Divide, 7, -2), Data::Int(-3));
+        assert_eq!(int(Operator::Divide, -7, -2), Data::Int(3));
+        assert_eq!(int(Operator::Remainder, -7, 2), Data::Int(-1));
+        assert_eq!(int(Operator::Remainder, 7, -2), Data::Int(1));
+        assert_eq!(int(Operator::Remainder, -7, -2), Data::Int(-1));
+        assert_eq!(
+            int(Operator::Divide, i64::MIN, -1),
+            Data::Error(String::from("Integer overflow"))
+        );
+
+        assert_eq!(
+            apply(Operator::Remainder, &[Data::Float(-7.5), Data::Int(2)]),
+            Data::Float(-1.5)
+        );
+        assert_eq!(
+            apply(Operator::Negate, &[Data::Int(-3)]),
+            Data::Int(3)
+        );
+    }
+
+
+    #[test]
+    fn errors_propagate() {
+        let error = Data::Error(String::from("Division by zero"));
+        assert_eq!(apply(Operator::Add, &[Data::Int(1), error.clone()]), error);
+        assert_eq!(apply(Operator::Less, &[error.clone(), Data::Int(1)]), error);
+        assert_eq!(
+            apply(Operator::Negate, &[error]),
+            Data::Error(String::from("Division by zero"))
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Runs the `vertex` binary as a user would, and checks it's output and exit
+//! status.
+
+
+use indoc::indoc;
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command};
+
+
+/// Writes the given source code to a uniquely named file within the temporary
+/// directory, and returns it's path.
+fn source_file(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("vertex-cli-{}-{}.vx", process::id(), name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+
+/// Runs the `vertex` binary with the given arguments, and returns it's
+/// standard output, standard error, and whether or not it exited successfully.
+fn vertex(args: &[&str]) -> (String, String, bool) {
+    let output = Command::new(env!("CARGO_BIN_EXE_vertex")).args(args).output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.success(),
+    )
+}
+
+
+const VALID: &str = indoc! {r#"
+    Main = mod {
+        Main = function {
+            params = ()
+            return = (n: Int)
+
+            n = extern Length("hello") * 2
+        }
+    }
+"#};
+
+
+const INVALID: &str = indoc! {r#"
+    Main = mod {
+        Main = function {
+            params = ()
+            return = (n: Int)
+
+            n = missing + 1
+        }
+    }
+"#};
+
+
+#[test]
+fn run_exit_status() {
+    let valid = source_file("run-valid", VALID);
+    let invalid = source_file("run-invalid", INVALID);
+
+    let (stdout, _, success) = vertex(&["run", valid.to_str().unwrap()]);
+    assert!(success);
+    assert_eq!(stdout, "10\n");
+
+    let (stdout, stderr, success) = vertex(&["run", invalid.to_str().unwrap()]);
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert!(stderr.starts_with("error[E0102]: Cannot find element 'missing'"));
+
+    let (_, stderr, success) = vertex(&["run", valid.to_str().unwrap(), "--entry", "Main.Other"]);
+    assert!(!success);
+    assert!(stderr.contains("Cannot find entry function 'Main.Other'"));
+
+    fs::remove_file(valid).unwrap();
+    fs::remove_file(invalid).unwrap();
+}
+
+
+#[test]
+fn check_exit_status() {
+    let valid = source_file("check-valid", VALID);
+    let invalid = source_file("check-invalid", INVALID);
+
+    let (stdout, _, success) = vertex(&["check", valid.to_str().unwrap()]);
+    assert!(success);
+    assert_eq!(stdout, format!("No errors found in {}\n", valid.display()));
+
+    let (stdout, stderr, success) = vertex(&["check", invalid.to_str().unwrap()]);
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert!(stderr.starts_with("error[E0102]: Cannot find element 'missing'"));
+
+    let (_, _, success) = vertex(&["check", "/nonexistent/main.vx"]);
+    assert!(!success);
+
+    fs::remove_file(valid).unwrap();
+    fs::remove_file(invalid).unwrap();
+}
+
+
+#[test]
+fn run_error_exit_status() {
+    let source = source_file("run-error", indoc! {r#"
+        Main = mod {
+            Main = function {
+                params = ()
+                return = (n: Int)
+
+                n = -7 / (extern Length("") * 2)
+            }
+        }
+    "#});
+
+    let (stdout, stderr, success) = vertex(&["run", source.to_str().unwrap()]);
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr.trim_end(), "error: Division by zero");
+
+    fs::remove_file(source).unwrap();
+}
+
+
+#[test]
+fn check_emit_ir() {
+    let source = source_file("emit-ir", VALID);
+    let path = source.to_str().unwrap();
+
+    let (stdout, _, success) = vertex(&["check", path, "--emit=ir"]);
+    assert!(success);
+    assert_eq!(stdout, indoc! {r#"
+        function 0 Main.Main (access 1): fn() -> Int
+            %0 = int 10: Int
+            result = %0
+    "#});
+
+    let (stdout, _, success) =
+        vertex(&["check", path, "--emit=ir", "--disable-pass", "constant-folding"]);
+    assert!(success);
+    assert_eq!(stdout, indoc! {r#"
+        function 0 Main.Main (access 1): fn() -> Int
+            %0 = string "hello": String
+            %1 = external Length (%0): Int
+            %2 = int 2: Int
+            %3 = operator Multiply (%1, %2): Int
+            result = %3
+    "#});
+
+    fs::remove_file(source).unwrap();
+}
@@ -0,0 +1,101 @@
+//! Runs the `vertex-lsp` binary as a language client would, over the standard
+//! input and output streams.
+
+
+use indoc::indoc;
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+
+/// Runs the language server with the given messages as input, and returns
+/// every message that it writes, along with whether or not it exited
+/// successfully.
+fn run_server(messages: &[Value]) -> (Vec<Value>, bool) {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_vertex-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = server.stdin.take().unwrap();
+    for message in messages {
+        let body = message.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    drop(stdin);
+
+    let output = server.wait_with_output().unwrap();
+    let mut stdout = String::from_utf8(output.stdout).unwrap();
+
+    let mut responses = vec![];
+    while let Some((header, rest)) = stdout.split_once("\r\n\r\n") {
+        let length: usize = header.trim_start_matches("Content-Length: ").parse().unwrap();
+        responses.push(serde_json::from_str(&rest[..length]).unwrap());
+        stdout = rest[length..].to_string();
+    }
+
+    (responses, output.status.success())
+}
+
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+}
+
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+
+#[test]
+fn complete_standard_library_externs() {
+    let source = indoc! {r#"
+        Main = mod {
+            Main = function {
+                params = ()
+                return = (n: Int)
+
+                a = 99999999999999999999
+                n = extern L
+            }
+        }
+    "#};
+
+    let (responses, success) = run_server(&[
+        request(1, "initialize", json!({"capabilities": {}})),
+        notification("initialized", json!({})),
+        notification("textDocument/didOpen", json!({
+            "textDocument": {
+                "uri": "untitled:main.vx",
+                "languageId": "vertex",
+                "version": 1,
+                "text": source,
+            },
+        })),
+        request(2, "textDocument/completion", json!({
+            "textDocument": {"uri": "untitled:main.vx"},
+            "position": {"line": 6, "character": 20},
+        })),
+        request(3, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+
+    assert!(success);
+    assert_eq!(responses.len(), 4);
+
+    // An out of range literal is reported, rather than stopping the server.
+    assert_eq!(responses[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(
+        responses[1]["params"]["diagnostics"][0]["message"],
+        "Literal '99999999999999999999' is out of range for type 'Int'"
+    );
+
+    let items = responses[2]["result"].as_array().unwrap();
+    let length = items.iter().find(|item| item["label"] == "Length").unwrap();
+    assert_eq!(length["detail"], "fn(String) -> Int");
+
+    assert_eq!(responses[3], json!({"jsonrpc": "2.0", "id": 3, "result": null}));
+}